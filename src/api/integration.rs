@@ -37,12 +37,21 @@ impl ApiIntegration {
         spotify.get_playlist_metadata(url).await
     }
 
+    /// Search Spotify tracks using centralized API
+    pub async fn search_spotify_tracks(query: &str, limit: u32) -> Result<Vec<TrackMetadata>> {
+        let api_manager = get_api_manager()?;
+        let spotify_api = api_manager.spotify().await;
+        let mut spotify = spotify_api.write().await;
+        
+        spotify.search_tracks(query, limit).await
+    }
+
     /// Download YouTube audio using existing YouTube downloader
     pub async fn download_youtube_audio(url: &str, output_path: &PathBuf) -> Result<()> {
         // Use existing YouTube downloader directly
         let youtube_downloader = crate::downloader::youtube::YoutubeDownloader::new();
         let config = crate::config::Config::default();
-        youtube_downloader.download_audio(url, output_path, crate::config::AudioFormat::Mp3, crate::config::Bitrate::Kbps320, None, &config).await
+        youtube_downloader.download_audio(url, output_path, crate::config::AudioFormat::Mp3, crate::config::Quality::Cbr(320), None, &config).await
     }
 
     /// Download SoundCloud audio using existing SoundCloud downloader
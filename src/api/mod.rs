@@ -14,6 +14,7 @@ pub struct ApiManager {
     spotify: Arc<RwLock<SpotifyClient>>,
     itunes: Arc<RwLock<ItunesClient>>,
     rate_limits: Arc<RwLock<HashMap<String, RateLimit>>>,
+    lastfm_api_key: Option<String>,
 }
 
 /// Rate limiting information for APIs
@@ -97,7 +98,8 @@ impl ApiManager {
         rate_limits.insert("youtube".to_string(), RateLimit::new(60, 1000, 10000));
         rate_limits.insert("soundcloud".to_string(), RateLimit::new(200, 2000, 20000));
         rate_limits.insert("itunes".to_string(), RateLimit::new(20, 1000, 10000));
-        rate_limits.insert("lastfm".to_string(), RateLimit::new(5, 1000, 10000));
+        // Last.fm allows 5 requests per second per key
+        rate_limits.insert("lastfm".to_string(), RateLimit::new(300, 18000, 432000));
         rate_limits.insert("musicbrainz".to_string(), RateLimit::new(1, 100, 1000));
 
         Self {
@@ -105,6 +107,7 @@ impl ApiManager {
             spotify,
             itunes,
             rate_limits: Arc::new(RwLock::new(rate_limits)),
+            lastfm_api_key: config.lastfm_api_key.clone().filter(|key| !key.is_empty()),
         }
     }
 
@@ -147,10 +150,13 @@ impl ApiManager {
     {
         // Check rate limits
         if !self.can_make_request(api_name).await? {
-            return Err(SpotifyDownloaderError::Api(format!(
-                "Rate limit exceeded for {}",
-                api_name
-            )));
+            let retry_after_secs = self.rate_limits.read().await
+                .get(api_name)
+                .map(|limit| limit.time_until_available().as_secs_f64().ceil() as u64);
+            return Err(SpotifyDownloaderError::RateLimited {
+                message: format!("Rate limit exceeded for {}", api_name),
+                retry_after_secs,
+            });
         }
 
         // Make the request
@@ -162,10 +168,39 @@ impl ApiManager {
         result
     }
 
+    /// Make a rate-limited request, waiting for a free slot instead of failing when the limit is reached
+    pub async fn make_queued_request<F, T>(&self, api_name: &str, request: F) -> Result<T>
+    where
+        F: FnOnce() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<T>> + Send>>,
+    {
+        loop {
+            let wait = {
+                let mut limits = self.rate_limits.write().await;
+                let limit = limits.get_mut(api_name)
+                    .ok_or_else(|| SpotifyDownloaderError::Api(format!("Unknown API: {}", api_name)))?;
+                limit.update_limits();
+                if limit.can_make_request() {
+                    // Take the slot while the lock is held so concurrent callers can't both get it
+                    limit.record_request();
+                    break;
+                }
+                limit.time_until_available()
+            };
+            tokio::time::sleep(wait).await;
+        }
+
+        request().await
+    }
+
     /// Get HTTP client for direct use
     pub fn client(&self) -> &Client {
         &self.client
     }
+
+    /// Get the Last.fm API key from settings, if configured
+    pub fn lastfm_api_key(&self) -> Option<&str> {
+        self.lastfm_api_key.as_deref()
+    }
 }
 
 impl RateLimit {
@@ -212,6 +247,25 @@ impl RateLimit {
             && self.current_day_requests < self.requests_per_day
     }
 
+    /// Time until the exhausted window that resets last opens again
+    pub fn time_until_available(&self) -> std::time::Duration {
+        let now = std::time::SystemTime::now();
+        let remaining = |reset: std::time::SystemTime, window: u64| {
+            std::time::Duration::from_secs(window).saturating_sub(now.duration_since(reset).unwrap_or_default())
+        };
+        let mut wait = std::time::Duration::ZERO;
+        if self.current_minute_requests >= self.requests_per_minute {
+            wait = wait.max(remaining(self.last_reset_minute, 60));
+        }
+        if self.current_hour_requests >= self.requests_per_hour {
+            wait = wait.max(remaining(self.last_reset_hour, 3600));
+        }
+        if self.current_day_requests >= self.requests_per_day {
+            wait = wait.max(remaining(self.last_reset_day, 86400));
+        }
+        wait.max(std::time::Duration::from_millis(50))
+    }
+
     pub fn record_request(&mut self) {
         self.current_minute_requests += 1;
         self.current_hour_requests += 1;
@@ -1,6 +1,7 @@
-use clap::{Parser, Subcommand};
-use crate::config::{AudioFormat, Bitrate};
+use clap::{Args, Parser, Subcommand};
+use crate::config::{AudioFormat, Bitrate, LossyToLosslessPolicy, Quality, RateLimit};
 use crate::errors::Result;
+use crate::utils::retry::{RetryPolicy, RetryStage};
 use std::path::PathBuf;
 
 /// Spotify Downloader - Download music from Spotify using YouTube/SoundCloud as source
@@ -13,6 +14,141 @@ pub struct Cli {
     pub command: Commands,
 }
 
+/// Download flags shared by `download` and `import-csv`
+#[derive(Args, Clone, Debug)]
+pub struct DownloadArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "mp3")]
+    pub format: AudioFormat,
+    
+    /// Audio bitrate
+    #[arg(short, long, value_enum, default_value = "320")]
+    pub bitrate: Bitrate,
+    
+    /// Encoding quality: CBR kbps (e.g. 256k), VBR level (V0–V9) or "original"
+    #[arg(long)]
+    pub quality: Option<Quality>,
+    
+    /// Output directory
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    
+    /// Download lyrics
+    #[arg(long, default_value = "true")]
+    pub lyrics: bool,
+    
+    /// Download cover art
+    #[arg(long, default_value = "true")]
+    pub cover: bool,
+    
+    /// Embed metadata
+    #[arg(long, default_value = "true")]
+    pub metadata: bool,
+    
+    /// Normalize loudness with a two-pass loudnorm (lossy formats only)
+    #[arg(long)]
+    pub normalize: bool,
+    
+    /// Loudness normalization target in LUFS
+    #[arg(long, allow_hyphen_values = true)]
+    pub target_lufs: Option<f64>,
+    
+    /// Loudness normalization true peak ceiling in dBTP
+    #[arg(long, allow_hyphen_values = true)]
+    pub true_peak: Option<f64>,
+    
+    /// Trim leading/trailing silence and intros or outros beyond the Spotify duration
+    #[arg(long)]
+    pub trim: bool,
+    
+    /// Download again even if the track is in the download archive
+    #[arg(long)]
+    pub force: bool,
+    
+    /// Neither check nor update the download archive
+    #[arg(long)]
+    pub no_archive: bool,
+    
+    /// Bandwidth cap for this run (e.g. 500K, 2M), overriding the configured cap and schedule
+    #[arg(long)]
+    pub limit_rate: Option<RateLimit>,
+    
+    /// Resolve metadata and match every track without downloading audio, then print the plan
+    #[arg(long)]
+    pub dry_run: bool,
+    
+    /// Save the dry-run plan as JSON, to edit and download with `plan run`
+    #[arg(long, requires = "dry_run")]
+    pub export_plan: Option<PathBuf>,
+}
+
+impl DownloadArgs {
+    /// Resolve the flags against the saved settings into the options every track is downloaded with
+    pub fn download_options(&self, settings: &crate::settings::Settings) -> crate::downloader::DownloadOptions {
+        let config = settings.config();
+        println!("Format: {}", self.format);
+        
+        // --quality wins over the per-format setting, which wins over --bitrate
+        let quality = self.quality
+            .or_else(|| config.format_quality.get(&self.format).copied())
+            .unwrap_or_else(|| Quality::from(self.bitrate));
+        println!("Quality: {}", quality);
+        
+        let mut normalization = config.normalization.clone();
+        normalization.enabled |= self.normalize;
+        if let Some(target_lufs) = self.target_lufs {
+            normalization.target_lufs = target_lufs;
+        }
+        if let Some(true_peak) = self.true_peak {
+            normalization.true_peak_db = true_peak;
+        }
+        if normalization.enabled {
+            println!("Normalize loudness: {} LUFS, {} dBTP", normalization.target_lufs, normalization.true_peak_db);
+        }
+        let mut trim = config.trim.clone();
+        trim.enabled |= self.trim;
+        println!("Trim silence: {}", trim.enabled);
+        
+        // Get output directory
+        let output_dir = self.output.clone().unwrap_or_else(|| config.download_directory.clone());
+        
+        println!("Output directory: {}", output_dir.display());
+        println!("Download lyrics: {}", self.lyrics);
+        println!("Download cover: {}", self.cover);
+        println!("Embed metadata: {}", self.metadata);
+        
+        crate::downloader::DownloadOptions {
+            format: self.format,
+            bitrate: self.bitrate,
+            quality,
+            output_dir,
+            download_lyrics: self.lyrics,
+            download_cover: self.cover,
+            embed_metadata: self.metadata,
+            cover_width: 500,
+            cover_height: 500,
+            cover_format: "jpeg".to_string(),
+            // Individual Metadata Toggles (CLI defaults to all enabled)
+            embed_title: true,
+            embed_artist: true,
+            embed_album: true,
+            embed_year: true,
+            embed_genre: true,
+            embed_track_number: true,
+            embed_disc_number: true,
+            embed_album_artist: true,
+            embed_composer: true,
+            embed_comment: true,
+            embed_bpm: config.metadata_config.embed_bpm,
+            embed_audio_features: config.metadata_config.embed_audio_features,
+            key_notation: config.metadata_config.key_notation,
+            normalization,
+            trim,
+            output_path: None,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Download a track from Spotify
@@ -20,29 +156,12 @@ pub enum Commands {
         /// Spotify URL (track, album, or playlist)
         url: String,
         
-        /// Output format
-        #[arg(short, long, value_enum, default_value = "mp3")]
-        format: AudioFormat,
+        #[command(flatten)]
+        options: DownloadArgs,
         
-        /// Audio bitrate
-        #[arg(short, long, value_enum, default_value = "320")]
-        bitrate: Bitrate,
-        
-        /// Output directory
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-        
-        /// Download lyrics
-        #[arg(long, default_value = "true")]
-        lyrics: bool,
-        
-        /// Download cover art
-        #[arg(long, default_value = "true")]
-        cover: bool,
-        
-        /// Embed metadata
-        #[arg(long, default_value = "true")]
-        metadata: bool,
+        /// For album URLs, cut the tracks from a single full-album upload
+        #[arg(long)]
+        album_mode: bool,
     },
     
     /// Download only lyrics for a track
@@ -101,29 +220,155 @@ pub enum Commands {
         /// Path to CSV file
         csv_path: PathBuf,
         
-        /// Output format
-        #[arg(short, long, value_enum, default_value = "mp3")]
-        format: AudioFormat,
+        #[command(flatten)]
+        options: DownloadArgs,
         
-        /// Audio bitrate
-        #[arg(short, long, value_enum, default_value = "320")]
-        bitrate: Bitrate,
+        /// Write the tracks that failed to a .json or .csv report, to download again with `retry --from-report`
+        #[arg(long)]
+        failure_report: Option<PathBuf>,
+    },
+    
+    /// Re-embed cover art, lyrics and metadata into already downloaded files
+    Retag {
+        /// Audio file or directory to retag
+        path: PathBuf,
         
-        /// Output directory
-        #[arg(short, long)]
-        output: Option<PathBuf>,
+        /// Spotify URL (track, album, or playlist); defaults to the embedded SPOTIFY_TRACK_ID or a Spotify search by the existing tags
+        #[arg(long)]
+        url: Option<String>,
         
-        /// Download lyrics
-        #[arg(long, default_value = "true")]
-        lyrics: bool,
+        /// Show old vs new tag values without modifying files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Show tags, pictures, lyrics and stream info of audio files
+    Inspect {
+        /// Audio file or directory to inspect
+        path: PathBuf,
         
-        /// Download cover art
-        #[arg(long, default_value = "true")]
-        cover: bool,
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
         
-        /// Embed metadata
-        #[arg(long, default_value = "true")]
-        metadata: bool,
+        /// Export all inspected files to a .json or .csv file
+        #[arg(long)]
+        export: Option<PathBuf>,
+        
+        /// Verify the file's tags against a Spotify track URL
+        #[arg(long)]
+        verify: Option<String>,
+    },
+    
+    /// Manage the persistent download queue
+    Jobs {
+        #[command(subcommand)]
+        command: JobsCommands,
+    },
+    
+    /// Manage the archive of downloaded tracks
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommands,
+    },
+    
+    /// Review or download a plan saved with --dry-run --export-plan
+    Plan {
+        #[command(subcommand)]
+        command: PlanCommands,
+    },
+    
+    /// Download the tracks of a failure report again
+    Retry {
+        /// Report written with --failure-report
+        #[arg(long)]
+        from_report: PathBuf,
+        
+        /// Also accept uploads that look like mixes, albums or live versions, and of any length
+        #[arg(long)]
+        relaxed: bool,
+        
+        /// Neither check nor update the download archive
+        #[arg(long)]
+        no_archive: bool,
+        
+        /// Bandwidth cap for this run (e.g. 500K, 2M), overriding the configured cap and schedule
+        #[arg(long)]
+        limit_rate: Option<RateLimit>,
+        
+        /// Write the tracks that failed to a .json or .csv report, to download again with `retry --from-report`
+        #[arg(long)]
+        failure_report: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PlanCommands {
+    /// Print the tracks, sources and output paths of a plan
+    Show {
+        /// Plan file
+        file: PathBuf,
+    },
+    
+    /// Download the tracks of a plan from the sources it lists, leaving out entries marked "skip"
+    Run {
+        /// Plan file
+        file: PathBuf,
+        
+        /// Download again even if the track is in the download archive
+        #[arg(long)]
+        force: bool,
+        
+        /// Neither check nor update the download archive
+        #[arg(long)]
+        no_archive: bool,
+        
+        /// Bandwidth cap for this run (e.g. 500K, 2M), overriding the configured cap and schedule
+        #[arg(long)]
+        limit_rate: Option<RateLimit>,
+        
+        /// Write the tracks that failed to a .json or .csv report, to download again with `retry --from-report`
+        #[arg(long)]
+        failure_report: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ArchiveCommands {
+    /// List archived tracks
+    List,
+    
+    /// Record the tagged audio files of a folder in the archive
+    Rebuild {
+        /// Folder to scan; defaults to the download directory
+        path: Option<PathBuf>,
+    },
+    
+    /// Forget every archived track
+    Clear,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum JobsCommands {
+    /// List queued, running, completed and failed downloads
+    List,
+    
+    /// Resume downloads that were interrupted
+    Resume {
+        /// Also retry downloads that failed
+        #[arg(long)]
+        retry_failed: bool,
+        
+        /// Write the tracks that failed to a .json or .csv report, to download again with `retry --from-report`
+        #[arg(long)]
+        failure_report: Option<PathBuf>,
+    },
+    
+    /// Remove finished downloads from the queue
+    Clear {
+        /// Remove unfinished downloads as well
+        #[arg(long)]
+        all: bool,
     },
 }
 
@@ -150,6 +395,26 @@ pub enum ConfigCommands {
         bitrate: Bitrate,
     },
     
+    /// Set the encoding quality for one format
+    SetQuality {
+        /// Audio format
+        format: AudioFormat,
+        /// Quality: CBR kbps (e.g. 256k), VBR level (V0–V9) or "original"
+        quality: Quality,
+    },
+    
+    /// Set what happens when a lossless format is requested from a lossy source
+    SetLossyPolicy {
+        /// Policy: allow, warn or refuse
+        policy: LossyToLosslessPolicy,
+    },
+    
+    /// Set the bandwidth cap for all downloads together (e.g. 500K, 2M or unlimited)
+    SetLimitRate {
+        /// Rate limit
+        rate: RateLimit,
+    },
+    
     /// Set Spotify API credentials
     SetSpotify {
         /// Client ID
@@ -172,16 +437,9 @@ impl Cli {
     /// Execute the CLI command
     pub async fn execute(self) -> Result<()> {
         match self.command {
-            Commands::Download { 
-                ref url, 
-                format, 
-                bitrate, 
-                ref output, 
-                lyrics, 
-                cover, 
-                metadata 
-            } => {
-                self.handle_download(url.clone(), format, bitrate, output.clone(), lyrics, cover, metadata).await
+            Commands::Download { ref url, ref options, album_mode } => {
+                Self::apply_limit_rate(options.limit_rate);
+                self.handle_download(url.clone(), options.clone(), album_mode).await
             }
             Commands::Lyrics { ref url, ref output, synced, unsynced } => {
                 self.handle_lyrics(url.clone(), output.clone(), synced, unsynced).await
@@ -195,71 +453,56 @@ impl Cli {
             Commands::Gui => {
                 self.handle_gui().await
             }
-            Commands::ImportCsv { 
-                ref csv_path, 
-                format, 
-                bitrate, 
-                ref output, 
-                lyrics, 
-                cover, 
-                metadata 
-            } => {
-                self.handle_csv_import(csv_path.clone(), format, bitrate, output.clone(), lyrics, cover, metadata).await
+            Commands::ImportCsv { ref csv_path, ref options, ref failure_report } => {
+                Self::apply_limit_rate(options.limit_rate);
+                self.handle_csv_import(csv_path.clone(), options.clone(), failure_report.clone()).await
+            }
+            Commands::Retag { ref path, ref url, dry_run } => {
+                self.handle_retag(path.clone(), url.clone(), dry_run).await
+            }
+            Commands::Inspect { ref path, json, ref export, ref verify } => {
+                self.handle_inspect(path.clone(), json, export.clone(), verify.clone()).await
+            }
+            Commands::Jobs { ref command } => {
+                self.handle_jobs(command.clone()).await
+            }
+            Commands::Archive { ref command } => {
+                self.handle_archive(command.clone()).await
+            }
+            Commands::Plan { ref command } => {
+                self.handle_plan(command.clone()).await
+            }
+            Commands::Retry { ref from_report, relaxed, no_archive, limit_rate, ref failure_report } => {
+                Self::apply_limit_rate(limit_rate);
+                self.handle_retry(from_report.clone(), relaxed, no_archive, failure_report.clone()).await
             }
         }
     }
     
-    async fn handle_download(
-        &self,
-        url: String,
-        format: AudioFormat,
-        bitrate: Bitrate,
-        output: Option<PathBuf>,
-        lyrics: bool,
-        cover: bool,
-        metadata: bool,
-    ) -> Result<()> {
+    /// Replace the configured bandwidth cap and schedule for this run
+    fn apply_limit_rate(limit_rate: Option<RateLimit>) {
+        if let Some(limit) = limit_rate {
+            println!("Bandwidth cap: {}", limit);
+            crate::utils::bandwidth::BandwidthLimiter::global().override_limit(limit);
+        }
+    }
+    
+    async fn handle_download(&self, url: String, args: DownloadArgs, album_mode: bool) -> Result<()> {
         println!("Downloading from: {}", url);
-        println!("Format: {}", format);
-        println!("Bitrate: {} kbps", bitrate.as_u32());
-        
-        // Get output directory
-        let output_dir = if let Some(output_dir) = output {
-            output_dir
-        } else {
-            use crate::settings::Settings;
-            let settings = Settings::load()?;
-            settings.config().download_directory.clone()
-        };
         
-        println!("Output directory: {}", output_dir.display());
-        println!("Download lyrics: {}", lyrics);
-        println!("Download cover: {}", cover);
-        println!("Embed metadata: {}", metadata);
+        let settings = crate::settings::Settings::load()?;
+        let download_options = args.download_options(&settings);
+        let output_dir = download_options.output_dir.clone();
         
-        // Create download options
-        let download_options = crate::downloader::DownloadOptions {
-            format,
-            bitrate,
-            output_dir: output_dir.clone(),
-            download_lyrics: lyrics,
-            download_cover: cover,
-            embed_metadata: metadata,
-            cover_width: 500,
-            cover_height: 500,
-            cover_format: "jpeg".to_string(),
-            // Individual Metadata Toggles (CLI defaults to all enabled)
-            embed_title: true,
-            embed_artist: true,
-            embed_album: true,
-            embed_year: true,
-            embed_genre: true,
-            embed_track_number: true,
-            embed_disc_number: true,
-            embed_album_artist: true,
-            embed_composer: true,
-            embed_comment: true,
-        };
+        if album_mode {
+            if args.dry_run {
+                return Err(crate::errors::SpotifyDownloaderError::Config("Album mode can't be combined with --dry-run".to_string()));
+            }
+            if !url.contains("/album/") {
+                return Err(crate::errors::SpotifyDownloaderError::InvalidUrl(format!("Album mode needs a Spotify album URL: {}", url)));
+            }
+            return self.handle_album_split(&url, &download_options, settings.config()).await;
+        }
         
         // Get track metadata based on URL type
         let track = if url.contains("spotify.com") {
@@ -285,6 +528,9 @@ impl Cli {
                 album_cover_url: None,
                 composer: None,
                 comment: None,
+                audio_features: None,
+                isrc: None,
+                total_tracks: None,
             }
         } else if url.contains("soundcloud.com") {
             println!("Fetching track metadata from SoundCloud...");
@@ -306,6 +552,9 @@ impl Cli {
                 album_cover_url: None,
                 composer: None,
                 comment: None,
+                audio_features: None,
+                isrc: None,
+                total_tracks: None,
             }
         } else {
             return Err(crate::errors::SpotifyDownloaderError::InvalidUrl(format!("Unsupported URL type: {}", url)));
@@ -313,50 +562,110 @@ impl Cli {
         
         println!("Found track: {} - {}", track.artist, track.title);
         
+        let archive = Self::open_archive(args.no_archive);
+        if args.dry_run {
+            return Self::handle_dry_run(vec![track], download_options, archive.as_ref(), settings.config(), args.export_plan).await;
+        }
+        if !args.force {
+            if let Some(path) = crate::downloader::archive::check(archive.as_ref(), &track) {
+                println!("⏭️ Already downloaded: {} (use --force to download again)", path.display());
+                return Ok(());
+            }
+        }
+        
         // Create audio downloader with proxy-configured client
         let api_manager = crate::api::get_api_manager()?;
         let client = api_manager.client().clone();
-        let audio_downloader = crate::downloader::audio::AudioDownloader::new_with_client(client);
+        let mut audio_downloader = crate::downloader::audio::AudioDownloader::new_with_client(client);
+
+        // Ctrl-C stops the running download or conversion and removes its partial output
+        let control = crate::downloader::control::DownloadControl::new();
+        audio_downloader.set_control(control.clone());
+        let cancel_token = control.cancel_token();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                println!("\n🛑 Cancelling download...");
+                control.cancel();
+            }
+        });
         
         // Create progress channel
         let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel();
         
         // Start download in background
-        let download_handle = {
+        let mut download_handle = {
             let track = track.clone();
             let download_options = download_options.clone();
             let mut audio_downloader = audio_downloader;
+            let config = settings.config().clone();
             tokio::spawn(async move {
-                let config = crate::config::Config::default(); // Create config inside spawn
-                audio_downloader.download_track(&track, &download_options, Some(progress_sender), &config).await
+                audio_downloader.download_track_detailed(&track, &download_options, Some(progress_sender), &config).await
             })
         };
         
         // Monitor progress
-        while let Some(progress) = progress_receiver.recv().await {
+        let mut transfer_line_open = false;
+        loop {
+            let progress = tokio::select! {
+                progress = progress_receiver.recv() => match progress {
+                    Some(progress) => progress,
+                    None => break,
+                },
+                _ = cancel_token.cancelled() => {
+                    // Give a running conversion a moment to clean up before dropping the task
+                    if tokio::time::timeout(std::time::Duration::from_secs(5), &mut download_handle).await.is_err() {
+                        download_handle.abort();
+                    }
+                    return Err(crate::errors::SpotifyDownloaderError::Cancelled("Download cancelled".to_string()));
+                }
+            };
+            if let Some(transfer) = progress.transfer() {
+                // Rewrite a single line while bytes are coming in
+                use std::io::Write;
+                print!("\r[{}] {:.1}% {}\x1b[K", progress.stage(), progress.progress * 100.0, transfer.summary());
+                let _ = std::io::stdout().flush();
+                transfer_line_open = true;
+                continue;
+            }
+            if transfer_line_open {
+                println!();
+                transfer_line_open = false;
+            }
+            
             println!("[{}] {} - {:.1}%", 
-                progress.stage, 
-                progress.message, 
+                progress.stage(), 
+                Self::describe_event(&progress), 
                 progress.progress * 100.0
             );
             
-            if matches!(progress.stage, crate::downloader::DownloadStage::Completed | crate::downloader::DownloadStage::Error) {
+            if progress.event.is_final() {
                 break;
             }
         }
         
         // Wait for download to complete
         match download_handle.await {
-            Ok(Ok(output_path)) => {
+            Ok(Ok(download)) => {
+                let output_path = download.path;
                 println!("Download completed successfully!");
                 println!("File saved to: {}", output_path.display());
+                if let Some(format) = &download.source_format {
+                    println!("Source stream: {}", format);
+                }
+                if let Some(trim) = &download.trim {
+                    println!("Trim: {}", trim.summary());
+                    for cut in &trim.cuts {
+                        println!("  ✂️ {}", cut);
+                    }
+                }
+                crate::downloader::archive::record(archive.as_ref(), |archive| archive.record(&track, &output_path));
                 
                 // Download additional content if requested
-                if lyrics {
+                if args.lyrics {
                     self.download_lyrics_for_track(&track, &output_dir).await?;
                 }
                 
-                if cover {
+                if args.cover {
                     self.download_cover_for_track(&track, &output_dir).await?;
                 }
             }
@@ -373,6 +682,43 @@ impl Cli {
         Ok(())
     }
     
+    /// Download a Spotify album by cutting a full-album upload into its tracks
+    async fn handle_album_split(
+        &self,
+        url: &str,
+        download_options: &crate::downloader::DownloadOptions,
+        config: &crate::config::Config,
+    ) -> Result<()> {
+        println!("Fetching album metadata from Spotify...");
+        let album = crate::downloader::api_wrapper::ApiWrapper::get_spotify_album_metadata(url).await?;
+        println!("Found album: {} - {} ({} tracks)", album.artist, album.name, album.tracks.len());
+        
+        let api_manager = crate::api::get_api_manager()?;
+        let mut audio_downloader = crate::downloader::audio::AudioDownloader::new_with_client(api_manager.client().clone());
+        
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
+        let progress_task = tokio::spawn(async move {
+            while let Some(progress) = progress_receiver.recv().await {
+                if progress.transfer().is_none() {
+                    println!("[{}] {} - {:.1}%", progress.stage(), Self::describe_event(&progress), progress.progress * 100.0);
+                }
+            }
+        });
+        
+        let result = audio_downloader.download_album_split(&album, download_options, Some(progress_sender), config).await;
+        let _ = progress_task.await;
+        
+        let downloads = result?;
+        println!("Album download completed: {} tracks", downloads.len());
+        if let Some(format) = downloads.first().and_then(|download| download.source_format.as_ref()) {
+            println!("Source stream: {}", format);
+        }
+        for download in &downloads {
+            println!("  ✓ {}", download.path.display());
+        }
+        Ok(())
+    }
+    
     async fn download_lyrics_for_track(&self, track: &crate::downloader::TrackMetadata, output_dir: &PathBuf) -> Result<()> {
         println!("Downloading lyrics for: {} - {}", track.artist, track.title);
         
@@ -465,6 +811,29 @@ impl Cli {
                 println!("  Download directory: {}", config.download_directory.display());
                 println!("  Default format: {}", config.default_format);
                 println!("  Default bitrate: {} kbps", config.default_bitrate.as_u32());
+                println!("  Lossy-to-lossless policy: {}", config.conversion_config.lossy_to_lossless);
+                if config.normalization.enabled {
+                    println!("  Loudness normalization: {} LUFS, {} dBTP", config.normalization.target_lufs, config.normalization.true_peak_db);
+                } else {
+                    println!("  Loudness normalization: off");
+                }
+                println!("  Trim silence: {}", config.trim.enabled);
+                for stage in [RetryStage::Search, RetryStage::Metadata, RetryStage::Download] {
+                    let policy = RetryPolicy::for_stage(&config.retry, stage);
+                    println!("  Retries for {}: {} (backoff {}–{} ms)",
+                             stage, policy.max_retries, policy.initial_delay.as_millis(), policy.max_delay.as_millis());
+                }
+                println!("  Pipeline workers: {} metadata, {} search, {} download, {} transcode, {} tagging (queues of {})",
+                         config.pipeline.metadata_workers, config.pipeline.search_workers,
+                         config.ui_preferences.max_concurrent_downloads, config.pipeline.transcode_workers,
+                         config.pipeline.tagging_workers, config.pipeline.queue_capacity);
+                println!("  Bandwidth cap: {} (per download: {})", config.bandwidth.limit, config.bandwidth.per_download_limit);
+                for window in &config.bandwidth.schedule {
+                    println!("  Bandwidth {}–{}: {}", window.start, window.end, window.limit);
+                }
+                for (format, quality) in &config.format_quality {
+                    println!("  Quality for {}: {}", format, quality);
+                }
                 println!("  Cover size: {}x{}", config.cover_config.width, config.cover_config.height);
                 println!("  Cover format: {}", config.cover_config.format);
                 println!("  Download lyrics: {}", config.metadata_config.embed_lyrics);
@@ -492,6 +861,21 @@ impl Cli {
                 settings.set_default_bitrate(bitrate)?;
                 println!("Default bitrate updated to: {} kbps", bitrate.as_u32());
             }
+            ConfigCommands::SetQuality { format, quality } => {
+                let mut settings = Settings::load()?;
+                settings.set_format_quality(format, quality)?;
+                println!("Quality for {} updated to: {}", format, quality);
+            }
+            ConfigCommands::SetLossyPolicy { policy } => {
+                let mut settings = Settings::load()?;
+                settings.set_lossy_to_lossless_policy(policy)?;
+                println!("Lossy-to-lossless policy updated to: {}", policy);
+            }
+            ConfigCommands::SetLimitRate { rate } => {
+                let mut settings = Settings::load()?;
+                settings.set_bandwidth_limit(rate)?;
+                println!("Bandwidth cap updated to: {}", rate);
+            }
             ConfigCommands::SetSpotify { client_id, client_secret } => {
                 let mut settings = Settings::load()?;
                 settings.set_spotify_credentials(client_id, client_secret)?;
@@ -519,33 +903,12 @@ impl Cli {
     
 
     
-    async fn handle_csv_import(
-        &self,
-        csv_path: PathBuf,
-        format: AudioFormat,
-        bitrate: Bitrate,
-        output: Option<PathBuf>,
-        lyrics: bool,
-        cover: bool,
-        metadata: bool,
-    ) -> Result<()> {
+    async fn handle_csv_import(&self, csv_path: PathBuf, args: DownloadArgs, failure_report: Option<PathBuf>) -> Result<()> {
         println!("Importing tracks from CSV: {}", csv_path.display());
-        println!("Format: {}", format);
-        println!("Bitrate: {} kbps", bitrate.as_u32());
-        
-        // Get output directory
-        let output_dir = if let Some(output_dir) = output {
-            output_dir
-        } else {
-            use crate::settings::Settings;
-            let settings = Settings::load()?;
-            settings.config().download_directory.clone()
-        };
         
-        println!("Output directory: {}", output_dir.display());
-        println!("Download lyrics: {}", lyrics);
-        println!("Download cover: {}", cover);
-        println!("Embed metadata: {}", metadata);
+        let settings = crate::settings::Settings::load()?;
+        let download_options = args.download_options(&settings);
+        let output_dir = download_options.output_dir.clone();
         
         // Validate CSV format first
         let csv_importer = crate::csv_import::CsvImporter::new();
@@ -558,25 +921,36 @@ impl Cli {
         println!("  Columns: {}", csv_info.column_count);
         println!("  Records: {}", csv_info.record_count);
         
-        // Create batch downloader
-        let mut batch_downloader = crate::csv_import::CsvBatchDownloader::new();
+        // Saved settings such as the lossy-to-lossless policy and retries apply, with the CLI flags on top
+        let mut config = settings.config().clone();
+        config.normalization = download_options.normalization.clone();
+        config.trim = download_options.trim.clone();
         
-        // Progress callback
-        let progress_callback = Box::new(|current: usize, total: usize, message: String| {
-            let percentage = (current as f32 / total as f32) * 100.0;
-            println!("[{}/{}] ({:.1}%) {}", current, total, percentage, message);
-        });
+        if args.dry_run {
+            let tracks = csv_importer.import_from_csv(&csv_path).await?;
+            let archive = Self::open_archive(args.no_archive);
+            return Self::handle_dry_run(tracks, download_options, archive.as_ref(), &config, args.export_plan).await;
+        }
+        
+        // Create batch downloader
+        let mut batch_downloader = crate::csv_import::CsvBatchDownloader::new(
+            config.ui_preferences.max_concurrent_downloads as usize
+        );
+        match crate::downloader::job_store::JobStore::open_shared() {
+            Ok(store) => batch_downloader.set_job_store(store),
+            Err(e) => println!("⚠️ Download queue unavailable, this import can't be resumed: {}", e),
+        }
+        if let Some(archive) = Self::open_archive(args.no_archive) {
+            batch_downloader.set_archive(archive);
+        }
+        batch_downloader.set_force(args.force);
+        let progress_task = Self::watch_batch(batch_downloader.manager_mut());
         
         // Start batch download
-        let config = crate::config::Config::default();
-        let result = batch_downloader.download_from_csv(
-            &csv_path,
-            &output_dir,
-            format,
-            bitrate,
-            Some(progress_callback),
-            &config,
-        ).await?;
+        let result = batch_downloader.download_from_csv(&csv_path, &download_options, &config).await;
+        drop(batch_downloader);
+        let _ = progress_task.await;
+        let result = result?;
         
         // Print results
         println!("\n=== CSV Import Results ===");
@@ -586,11 +960,15 @@ impl Cli {
         
         if !result.failed_tracks.is_empty() {
             println!("\nFailed tracks:");
-            for (track, error) in &result.failed_tracks {
-                println!("  ✗ {} - {}: {}", track.artist, track.title, error);
+            for failure in &result.failed_tracks {
+                println!("  ✗ {} - {}: {}", failure.track.artist, failure.track.title, failure.error);
             }
         }
         
+        let mut report = crate::downloader::failure_report::FailureReport::new();
+        report.failures = result.failed_tracks;
+        Self::save_failure_report(&report, failure_report)?;
+        
         if result.successful_downloads > 0 {
             println!("\n✓ Successfully downloaded {} tracks to: {}", 
                      result.successful_downloads, output_dir.display());
@@ -598,4 +976,480 @@ impl Cli {
         
         Ok(())
     }
+    
+    async fn handle_retag(&self, path: PathBuf, url: Option<String>, dry_run: bool) -> Result<()> {
+        use crate::settings::Settings;
+        
+        println!("Retagging: {}", path.display());
+        if let Some(url) = &url {
+            println!("Using Spotify URL: {}", url);
+        } else {
+            println!("Using embedded Spotify track IDs");
+        }
+        if dry_run {
+            println!("Dry run: no files will be modified");
+        }
+        
+        let settings = Settings::load()?;
+        let config = settings.config();
+        
+        // Retagging keeps the existing audio, so only the embedding options matter
+        let options = crate::downloader::DownloadOptions {
+            output_dir: path.parent().map(|parent| parent.to_path_buf()).unwrap_or_else(|| path.clone()),
+            download_lyrics: config.metadata_config.embed_lyrics,
+            download_cover: config.metadata_config.embed_cover,
+            embed_metadata: true,
+            cover_width: config.cover_config.width,
+            cover_height: config.cover_config.height,
+            cover_format: config.cover_config.format.clone(),
+            embed_bpm: config.metadata_config.embed_bpm,
+            embed_audio_features: config.metadata_config.embed_audio_features,
+            key_notation: config.metadata_config.key_notation,
+            ..Default::default()
+        };
+        
+        let retagger = crate::downloader::retag::Retagger::new(options);
+        let results = retagger.retag_path(&path, url.as_deref(), dry_run).await?;
+        
+        // Retagged files are still the archived downloads, just with new tags
+        if !dry_run {
+            let archive = Self::open_archive(false);
+            for result in results.iter().filter(|result| result.error.is_none()) {
+                crate::downloader::archive::record(archive.as_ref(), |archive| archive.refresh(&result.path));
+            }
+        }
+        
+        let mut failed = 0;
+        for result in &results {
+            match &result.track {
+                Some(track) => println!("\n{} -> {} - {}", result.path.display(), track.artist, track.title),
+                None => println!("\n{}", result.path.display()),
+            }
+            if let Some(error) = &result.error {
+                failed += 1;
+                println!("  ✗ {}", error);
+                continue;
+            }
+            if result.changes.is_empty() {
+                println!("  (no changes)");
+            }
+            for change in &result.changes {
+                match (&change.old, &change.new) {
+                    (Some(old), Some(new)) => println!("  ~ {}: {} -> {}", change.key, old, new),
+                    (None, Some(new)) => println!("  + {}: {}", change.key, new),
+                    (Some(old), None) => println!("  - {}: {}", change.key, old),
+                    (None, None) => {}
+                }
+            }
+        }
+        
+        println!("\n=== Retag Results ===");
+        println!("Files processed: {}", results.len());
+        println!("Retagged: {}", results.len() - failed);
+        println!("Failed: {}", failed);
+        
+        Ok(())
+    }
+    
+    async fn handle_inspect(
+        &self,
+        path: PathBuf,
+        json: bool,
+        export: Option<PathBuf>,
+        verify: Option<String>,
+    ) -> Result<()> {
+        let inspector = crate::downloader::inspect::TagInspector::new();
+        let inspections = inspector.inspect_path(&path)?;
+        
+        if json {
+            println!("{}", serde_json::to_string_pretty(&inspections)?);
+        } else {
+            for inspection in &inspections {
+                let stream = &inspection.stream;
+                println!("\n{} ({} bytes)", inspection.path.display(), inspection.file_size);
+                println!("  Codec: {}", stream.codec.as_deref().unwrap_or("unknown"));
+                if let Some(bitrate) = stream.bitrate_kbps {
+                    println!("  Bitrate: {} kbps", bitrate);
+                }
+                if let Some(sample_rate) = stream.sample_rate {
+                    println!("  Sample rate: {} Hz", sample_rate);
+                }
+                if let Some(channels) = stream.channels {
+                    println!("  Channels: {}", channels);
+                }
+                if let Some(bit_depth) = stream.bit_depth {
+                    println!("  Bit depth: {}", bit_depth);
+                }
+                println!("  Duration: {}:{:02}", stream.duration_ms / 60000, (stream.duration_ms / 1000) % 60);
+                
+                println!("  Tags:");
+                for (key, value) in &inspection.tags {
+                    println!("    {}: {}", key, value);
+                }
+                
+                if inspection.pictures.is_empty() {
+                    println!("  Pictures: none");
+                } else {
+                    println!("  Pictures:");
+                    for picture in &inspection.pictures {
+                        println!("    {}", crate::downloader::inspect::TagInspector::describe_picture(picture));
+                    }
+                }
+                println!("  Lyrics: {}", inspection.lyrics.summary());
+            }
+            println!("\nInspected {} file(s)", inspections.len());
+        }
+        
+        if let Some(export_path) = export {
+            inspector.export(&inspections, &export_path)?;
+        }
+        
+        if let Some(url) = verify {
+            if !path.is_file() {
+                return Err(crate::errors::SpotifyDownloaderError::InvalidFormat(
+                    "--verify needs a single file, not a directory".to_string()
+                ));
+            }
+            let track = crate::downloader::api_wrapper::ApiWrapper::get_spotify_track_metadata(&url).await?;
+            let embedder = crate::downloader::metadata::MetadataEmbedder::new();
+            embedder.verify_metadata_embedding(&path, &track).await?;
+        }
+        
+        Ok(())
+    }
+    
+    async fn handle_jobs(&self, command: JobsCommands) -> Result<()> {
+        let store = crate::downloader::job_store::JobStore::open_shared()?;
+        let lock_error = |e: std::sync::PoisonError<_>| {
+            crate::errors::SpotifyDownloaderError::Unknown(format!("Download queue unavailable: {}", e))
+        };
+        
+        match command {
+            JobsCommands::List => {
+                let store = store.lock().map_err(lock_error)?;
+                let jobs: Vec<_> = store.jobs().collect();
+                if jobs.is_empty() {
+                    println!("No downloads in the queue ({})", store.path().display());
+                    return Ok(());
+                }
+                for job in &jobs {
+                    let stage = job.stage.as_ref().map(|stage| stage.to_string()).unwrap_or_else(|| "-".to_string());
+                    println!(
+                        "[{}] {} - {} (attempts: {}, stage: {})",
+                        job.status, job.track.artist, job.track.title, job.attempts, stage
+                    );
+                    if let Some(path) = &job.output_path {
+                        println!("    → {}", path.display());
+                    }
+                    if let Some(error) = &job.last_error {
+                        println!("    ❌ {}", error);
+                    }
+                }
+                let unfinished = jobs.iter().filter(|job| job.is_unfinished()).count();
+                println!("\n{} job(s), {} unfinished", jobs.len(), unfinished);
+            }
+            JobsCommands::Resume { retry_failed, failure_report } => {
+                if retry_failed {
+                    let requeued = store.lock().map_err(lock_error)?.requeue_failed()?;
+                    println!("🔁 Requeued {} failed download(s)", requeued);
+                }
+                
+                let settings = crate::settings::Settings::load()?;
+                let mut manager = crate::downloader::async_manager::AsyncDownloadManager::new(
+                    settings.config().ui_preferences.max_concurrent_downloads as usize
+                );
+                manager.set_job_store(store);
+                if let Some(archive) = Self::open_archive(false) {
+                    manager.set_archive(archive);
+                }
+                
+                let progress_task = Self::watch_batch(&mut manager);
+                
+                let results = manager.resume_jobs(settings.config()).await;
+                drop(manager);
+                let _ = progress_task.await;
+                
+                let results = results?;
+                let successful = Self::print_failures(&results, failure_report)?;
+                println!("Resumed downloads completed: {}/{} successful", successful, results.len());
+            }
+            JobsCommands::Clear { all } => {
+                let removed = store.lock().map_err(lock_error)?.clear(all)?;
+                println!("🧹 Removed {} job(s) from the queue", removed);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Print the track and batch progress of a manager until its batch is done
+    fn watch_batch(manager: &mut crate::downloader::async_manager::AsyncDownloadManager) -> tokio::task::JoinHandle<()> {
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
+        manager.set_progress_sender(progress_sender);
+        let (batch_sender, mut batch_receiver) = tokio::sync::mpsc::unbounded_channel::<crate::downloader::BatchEvent>();
+        manager.set_batch_sender(batch_sender);
+        // Downloads run in process groups of their own, so Ctrl-C has to stop them through the control
+        let control = manager.control();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                println!("\n🛑 Cancelling downloads...");
+                control.cancel();
+            }
+        });
+        tokio::spawn(async move {
+            use crate::downloader::BatchEvent;
+            use std::io::Write;
+            let mut transfer_line_open = false;
+            loop {
+                let line = tokio::select! {
+                    Some(progress) = progress_receiver.recv() => {
+                        if progress.transfer().is_some() {
+                            continue;
+                        }
+                        format!("[{}] {} - {:.1}%", progress.stage(), Self::describe_event(&progress), progress.progress * 100.0)
+                    }
+                    Some(event) = batch_receiver.recv() => match event {
+                        BatchEvent::Transfer { downloaded_bytes, speed_bytes_per_sec, eta_secs } => {
+                            // Rewrite a single line with the combined transfer of the running downloads
+                            let eta = eta_secs.map(|eta| format!(", ETA {}:{:02}", eta / 60, eta % 60)).unwrap_or_default();
+                            print!(
+                                "\r📥 {:.1} MiB at {:.1} MiB/s{}\x1b[K",
+                                downloaded_bytes as f64 / (1024.0 * 1024.0),
+                                speed_bytes_per_sec / (1024.0 * 1024.0),
+                                eta,
+                            );
+                            let _ = std::io::stdout().flush();
+                            transfer_line_open = true;
+                            continue;
+                        }
+                        BatchEvent::TrackFinished { success, completed, failed, total } => {
+                            format!("📊 {} {}/{} done, {} failed", if success { "✓" } else { "✗" }, completed + failed, total, failed)
+                        }
+                    },
+                    else => break,
+                };
+                if transfer_line_open {
+                    println!();
+                    transfer_line_open = false;
+                }
+                println!("{}", line);
+            }
+        })
+    }
+    
+    /// Progress line for an event, showing the chosen source, conversion progress and file size
+    fn describe_event(progress: &crate::downloader::DownloadProgress) -> String {
+        use crate::downloader::DownloadEvent;
+        match &progress.event {
+            DownloadEvent::Resolved { source, title, url, score } => {
+                format!("Found {} source: {} <{}> (match {:.0}%)", source, title, url, score * 100.0)
+            }
+            DownloadEvent::Converting { percent } => format!("Converting audio... {:.0}%", percent),
+            DownloadEvent::Completed { path, size } => {
+                format!("Saved {} ({:.1} MiB)", path.display(), *size as f64 / (1024.0 * 1024.0))
+            }
+            DownloadEvent::Failed { stage, error_kind } => {
+                format!("Failed while {} ({}): {}", stage.to_string().to_lowercase(), error_kind, progress.message)
+            }
+            _ => progress.message.clone(),
+        }
+    }
+    
+    /// Open the download archive unless it is turned off, downloading without it when it can't be opened
+    fn open_archive(no_archive: bool) -> Option<crate::downloader::archive::SharedArchive> {
+        if no_archive {
+            return None;
+        }
+        match crate::downloader::archive::DownloadArchive::open_shared() {
+            Ok(archive) => Some(archive),
+            Err(e) => {
+                println!("⚠️ Download archive unavailable, already downloaded tracks won't be skipped: {}", e);
+                None
+            }
+        }
+    }
+    
+    async fn handle_archive(&self, command: ArchiveCommands) -> Result<()> {
+        let mut archive = crate::downloader::archive::DownloadArchive::open_default()?;
+        
+        match command {
+            ArchiveCommands::List => {
+                let entries: Vec<_> = archive.entries().collect();
+                if entries.is_empty() {
+                    println!("No tracks in the archive ({})", archive.path().display());
+                    return Ok(());
+                }
+                for entry in &entries {
+                    let status = if entry.path.exists() { "✓" } else { "missing" };
+                    println!("[{}] {} - {} ({})", status, entry.artist, entry.title, entry.key);
+                    println!("    → {}", entry.path.display());
+                }
+                let missing = entries.iter().filter(|entry| !entry.path.exists()).count();
+                println!("\n{} archived track(s), {} missing on disk", entries.len(), missing);
+            }
+            ArchiveCommands::Rebuild { path } => {
+                let path = match path {
+                    Some(path) => path,
+                    None => crate::settings::Settings::load()?.config().download_directory.clone(),
+                };
+                println!("🔍 Scanning {} for tagged audio files...", path.display());
+                let summary = archive.rebuild_from(&path)?;
+                println!(
+                    "📚 Recorded {} of {} file(s) in the archive ({} without a Spotify ID, ISRC or title)",
+                    summary.recorded, summary.scanned, summary.unidentified
+                );
+            }
+            ArchiveCommands::Clear => {
+                let removed = archive.clear()?;
+                println!("🧹 Removed {} track(s) from the archive", removed);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Match every track and print the plan, saving it when asked
+    async fn handle_dry_run(
+        tracks: Vec<crate::downloader::TrackMetadata>,
+        options: crate::downloader::DownloadOptions,
+        archive: Option<&crate::downloader::archive::SharedArchive>,
+        config: &crate::config::Config,
+        export_plan: Option<PathBuf>,
+    ) -> Result<()> {
+        println!("🧪 Dry run: matching {} track(s), no audio will be downloaded", tracks.len());
+        let plan = crate::downloader::plan::DownloadPlan::build(tracks, options, archive, config).await;
+        plan.print();
+        
+        if let Some(path) = export_plan {
+            plan.save(&path)?;
+            println!("💾 Plan saved to {}", path.display());
+            println!("   Edit sources or set \"skip\": true, then download it with: plan run {}", path.display());
+        }
+        Ok(())
+    }
+    
+    async fn handle_plan(&self, command: PlanCommands) -> Result<()> {
+        match command {
+            PlanCommands::Show { file } => {
+                crate::downloader::plan::DownloadPlan::load(&file)?.print();
+            }
+            PlanCommands::Run { file, force, no_archive, limit_rate, failure_report } => {
+                Self::apply_limit_rate(limit_rate);
+                let plan = crate::downloader::plan::DownloadPlan::load(&file)?;
+                let planned = plan.entries.iter().filter(|entry| !entry.skip).count();
+                println!("📋 Running plan {}: {} track(s), {} skipped", file.display(), planned, plan.entries.len() - planned);
+                
+                let settings = crate::settings::Settings::load()?;
+                let mut manager = crate::downloader::async_manager::AsyncDownloadManager::new(
+                    settings.config().ui_preferences.max_concurrent_downloads as usize
+                );
+                match crate::downloader::job_store::JobStore::open_shared() {
+                    Ok(store) => manager.set_job_store(store),
+                    Err(e) => println!("⚠️ Download queue unavailable, this run can't be resumed: {}", e),
+                }
+                if let Some(archive) = Self::open_archive(no_archive) {
+                    manager.set_archive(archive);
+                }
+                manager.set_force(force);
+                let progress_task = Self::watch_batch(&mut manager);
+                
+                let results = manager.download_plan(&plan, settings.config()).await;
+                drop(manager);
+                let _ = progress_task.await;
+                
+                let results = results?;
+                let successful = Self::print_failures(&results, failure_report)?;
+                println!("Plan downloads completed: {}/{} successful", successful, results.len());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn handle_retry(&self, from_report: PathBuf, relaxed: bool, no_archive: bool, failure_report: Option<PathBuf>) -> Result<()> {
+        let mut report = crate::downloader::failure_report::FailureReport::load(&from_report)?;
+        if report.is_empty() {
+            println!("No failed tracks in {}", from_report.display());
+            return Ok(());
+        }
+        println!("🔁 Retrying {} failed track(s) from {}", report.failures.len(), from_report.display());
+        
+        // CSV reports only keep the basic track fields, fetch the rest from Spotify where possible
+        for failure in report.failures.iter_mut().filter(|failure| failure.options.is_none()) {
+            if failure.track.spotify_url.is_empty() {
+                continue;
+            }
+            match crate::downloader::api_wrapper::ApiWrapper::get_spotify_track_metadata(&failure.track.spotify_url).await {
+                Ok(track) => failure.track = track,
+                Err(e) => println!("⚠️ Using the metadata from the report for {} - {}: {}", failure.track.artist, failure.track.title, e),
+            }
+        }
+        
+        let settings = crate::settings::Settings::load()?;
+        let mut config = settings.config().clone();
+        if relaxed {
+            println!("Relaxed matching: mixes, albums, live versions and uploads of any length are accepted");
+            config.matching.relaxed = true;
+        }
+        let format = config.default_format;
+        let quality = config.format_quality.get(&format).copied()
+            .unwrap_or_else(|| Quality::from(config.default_bitrate));
+        let default_options = crate::csv_import::CsvBatchDownloader::download_options(
+            &config.download_directory,
+            format,
+            config.default_bitrate,
+            quality,
+            &config,
+        );
+        
+        let mut manager = crate::downloader::async_manager::AsyncDownloadManager::new(
+            config.ui_preferences.max_concurrent_downloads as usize
+        );
+        match crate::downloader::job_store::JobStore::open_shared() {
+            Ok(store) => manager.set_job_store(store),
+            Err(e) => println!("⚠️ Download queue unavailable, this retry can't be resumed: {}", e),
+        }
+        if let Some(archive) = Self::open_archive(no_archive) {
+            manager.set_archive(archive);
+        }
+        let progress_task = Self::watch_batch(&mut manager);
+        
+        let results = manager.retry_failures(&report, &default_options, &config).await;
+        drop(manager);
+        let _ = progress_task.await;
+        
+        let results = results?;
+        let successful = Self::print_failures(&results, failure_report)?;
+        println!("Retried downloads completed: {}/{} successful", successful, results.len());
+        Ok(())
+    }
+    
+    /// Print the tracks of a batch that failed and save them as a report when asked, returning how many succeeded
+    fn print_failures(
+        results: &[crate::downloader::DownloadTaskResult],
+        failure_report: Option<PathBuf>,
+    ) -> Result<usize> {
+        for result in results.iter().filter(|result| !result.success) {
+            println!(
+                "  ✗ {} - {}: {}",
+                result.track.artist,
+                result.track.title,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+        let report = crate::downloader::failure_report::FailureReport::from_results(results);
+        Self::save_failure_report(&report, failure_report)?;
+        Ok(results.iter().filter(|result| result.success).count())
+    }
+    
+    /// Write a failure report if one was asked for
+    fn save_failure_report(report: &crate::downloader::failure_report::FailureReport, path: Option<PathBuf>) -> Result<()> {
+        let Some(path) = path else { return Ok(()) };
+        report.save(&path)?;
+        println!("💾 {} failed track(s) written to {}", report.failures.len(), path.display());
+        if !report.is_empty() {
+            println!("   Download them again with: retry --from-report {}", path.display());
+        }
+        Ok(())
+    }
 }
@@ -1,14 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use crate::errors::{Result, SpotifyDownloaderError};
 
 /// Supported audio formats
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AudioFormat {
     Mp3,
     M4a,
     Flac,
     Wav,
+    Opus,
+    Ogg,
+    Alac,
+    Aiff,
 }
 
 impl std::fmt::Display for AudioFormat {
@@ -18,6 +23,10 @@ impl std::fmt::Display for AudioFormat {
             AudioFormat::M4a => write!(f, "m4a"),
             AudioFormat::Flac => write!(f, "flac"),
             AudioFormat::Wav => write!(f, "wav"),
+            AudioFormat::Opus => write!(f, "opus"),
+            AudioFormat::Ogg => write!(f, "ogg"),
+            AudioFormat::Alac => write!(f, "alac"),
+            AudioFormat::Aiff => write!(f, "aiff"),
         }
     }
 }
@@ -31,11 +40,61 @@ impl std::str::FromStr for AudioFormat {
             "m4a" => Ok(AudioFormat::M4a),
             "flac" => Ok(AudioFormat::Flac),
             "wav" => Ok(AudioFormat::Wav),
+            "opus" => Ok(AudioFormat::Opus),
+            "ogg" | "vorbis" => Ok(AudioFormat::Ogg),
+            "alac" => Ok(AudioFormat::Alac),
+            "aiff" | "aif" => Ok(AudioFormat::Aiff),
             _ => Err(SpotifyDownloaderError::InvalidFormat(s.to_string())),
         }
     }
 }
 
+impl AudioFormat {
+    /// File extension used for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Alac => "m4a",
+            AudioFormat::Aiff => "aiff",
+        }
+    }
+
+    /// Value passed to yt-dlp's --audio-format
+    pub fn yt_dlp_format(&self) -> &'static str {
+        match self {
+            AudioFormat::Ogg => "vorbis",
+            AudioFormat::Alac => "alac",
+            // yt-dlp cannot produce AIFF, extract lossless WAV and convert afterwards
+            AudioFormat::Aiff => "wav",
+            _ => self.extension(),
+        }
+    }
+
+    /// FFmpeg encoder used to produce this format
+    pub fn ffmpeg_codec(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::M4a => "aac",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "pcm_s16le",
+            AudioFormat::Opus => "libopus",
+            AudioFormat::Ogg => "libvorbis",
+            AudioFormat::Alac => "alac",
+            AudioFormat::Aiff => "pcm_s16be",
+        }
+    }
+
+    /// Whether the format stores audio without lossy compression
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav | AudioFormat::Alac | AudioFormat::Aiff)
+    }
+}
+
 /// Supported bitrates
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Bitrate {
@@ -81,6 +140,142 @@ impl Bitrate {
     }
 }
 
+/// Encoding quality for lossy formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Quality {
+    /// Constant bitrate in kbps
+    Cbr(u32),
+    /// Variable bitrate level, 0 (best) to 9 (smallest) like LAME's V0–V9
+    Vbr(u8),
+    /// Keep the source stream untouched instead of re-encoding
+    Original,
+}
+
+/// Average LAME bitrate for each VBR level, used as the target for encoders without a native scale
+const VBR_TARGET_KBPS: [u32; 10] = [245, 225, 190, 175, 165, 130, 115, 100, 85, 65];
+
+impl std::fmt::Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quality::Cbr(kbps) => write!(f, "{}k", kbps),
+            Quality::Vbr(level) => write!(f, "V{}", level),
+            Quality::Original => write!(f, "original"),
+        }
+    }
+}
+
+impl std::str::FromStr for Quality {
+    type Err = SpotifyDownloaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let value = s.trim().to_lowercase();
+        match value.as_str() {
+            "original" | "best" | "source" => return Ok(Quality::Original),
+            _ => {}
+        }
+
+        if let Some(level) = value.strip_prefix('v') {
+            return match level.parse::<u8>() {
+                Ok(level) if level <= 9 => Ok(Quality::Vbr(level)),
+                _ => Err(SpotifyDownloaderError::InvalidBitrate(format!("VBR level must be V0–V9, got {}", s))),
+            };
+        }
+
+        let kbps = value.strip_suffix("kbps")
+            .or_else(|| value.strip_suffix('k'))
+            .unwrap_or(&value);
+        match kbps.parse::<u32>() {
+            Ok(kbps) if (32..=512).contains(&kbps) => Ok(Quality::Cbr(kbps)),
+            _ => Err(SpotifyDownloaderError::InvalidBitrate(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for Quality {
+    type Error = SpotifyDownloaderError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Quality> for String {
+    fn from(quality: Quality) -> Self {
+        quality.to_string()
+    }
+}
+
+impl From<Bitrate> for Quality {
+    fn from(bitrate: Bitrate) -> Self {
+        Quality::Cbr(bitrate.as_u32())
+    }
+}
+
+impl Quality {
+    /// Choices offered in the GUI
+    pub const PRESETS: [Quality; 8] = [
+        Quality::Cbr(128),
+        Quality::Cbr(192),
+        Quality::Cbr(256),
+        Quality::Cbr(320),
+        Quality::Vbr(0),
+        Quality::Vbr(2),
+        Quality::Vbr(5),
+        Quality::Original,
+    ];
+
+    /// Approximate average bitrate in kbps, None for original streams
+    pub fn target_kbps(&self) -> Option<u32> {
+        match self {
+            Quality::Cbr(kbps) => Some(*kbps),
+            Quality::Vbr(level) => Some(VBR_TARGET_KBPS[(*level).min(9) as usize]),
+            Quality::Original => None,
+        }
+    }
+
+    /// Value passed to yt-dlp's --audio-quality
+    pub fn yt_dlp_audio_quality(&self) -> String {
+        match self {
+            Quality::Cbr(kbps) => format!("{}K", kbps),
+            // yt-dlp uses the same 0 (best) to 9 scale for VBR
+            Quality::Vbr(level) => level.to_string(),
+            Quality::Original => "0".to_string(),
+        }
+    }
+}
+
+/// Notation used when writing the musical key tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum KeyNotation {
+    /// Standard notation such as "C#m" or "Ab"
+    #[default]
+    Standard,
+    /// Camelot wheel notation such as "12A" or "4B"
+    Camelot,
+}
+
+impl std::fmt::Display for KeyNotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyNotation::Standard => write!(f, "standard"),
+            KeyNotation::Camelot => write!(f, "camelot"),
+        }
+    }
+}
+
+impl std::str::FromStr for KeyNotation {
+    type Err = SpotifyDownloaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "standard" => Ok(KeyNotation::Standard),
+            "camelot" => Ok(KeyNotation::Camelot),
+            _ => Err(SpotifyDownloaderError::Config(format!("Invalid key notation: {}", s))),
+        }
+    }
+}
+
 /// Cover art configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoverConfig {
@@ -116,6 +311,18 @@ pub struct MetadataConfig {
     pub embed_duration: bool,
     pub embed_bpm: bool,
     pub embed_isrc: bool,
+    /// Fetch Last.fm tags, album and duration hints before searching
+    #[serde(default)]
+    pub enrich_from_lastfm: bool,
+    /// Analyse loudness after conversion and write ReplayGain tags
+    #[serde(default)]
+    pub embed_replaygain: bool,
+    /// Notation for the musical key written alongside BPM
+    #[serde(default)]
+    pub key_notation: KeyNotation,
+    /// Write energy, danceability, valence etc. as SPOTIFY_* custom tags
+    #[serde(default)]
+    pub embed_audio_features: bool,
 }
 
 impl Default for MetadataConfig {
@@ -135,6 +342,10 @@ impl Default for MetadataConfig {
             embed_duration: true,
             embed_bpm: false,
             embed_isrc: false,
+            enrich_from_lastfm: false,
+            embed_replaygain: false,
+            key_notation: KeyNotation::Standard,
+            embed_audio_features: false,
         }
     }
 }
@@ -265,12 +476,527 @@ impl Default for ProxyConfig {
     }
 }
 
+/// What to do when a lossless format is requested but the source is lossy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum LossyToLosslessPolicy {
+    /// Convert silently
+    Allow,
+    /// Convert but print a warning
+    #[default]
+    Warn,
+    /// Fail the download
+    Refuse,
+}
+
+impl std::fmt::Display for LossyToLosslessPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossyToLosslessPolicy::Allow => write!(f, "allow"),
+            LossyToLosslessPolicy::Warn => write!(f, "warn"),
+            LossyToLosslessPolicy::Refuse => write!(f, "refuse"),
+        }
+    }
+}
+
+impl std::str::FromStr for LossyToLosslessPolicy {
+    type Err = SpotifyDownloaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "allow" => Ok(LossyToLosslessPolicy::Allow),
+            "warn" => Ok(LossyToLosslessPolicy::Warn),
+            "refuse" => Ok(LossyToLosslessPolicy::Refuse),
+            _ => Err(SpotifyDownloaderError::Config(format!("Unknown lossy-to-lossless policy: {}", s))),
+        }
+    }
+}
+
+/// Conversion configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionConfig {
+    /// Handling of lossless output formats fed from lossy sources
+    #[serde(default)]
+    pub lossy_to_lossless: LossyToLosslessPolicy,
+    /// Stop an ffmpeg run after this many seconds, 0 disables the limit
+    #[serde(default = "default_conversion_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_conversion_timeout_secs() -> u64 {
+    600
+}
+
+impl Default for ConversionConfig {
+    fn default() -> Self {
+        Self {
+            lossy_to_lossless: LossyToLosslessPolicy::default(),
+            timeout_secs: default_conversion_timeout_secs(),
+        }
+    }
+}
+
+impl ConversionConfig {
+    /// The ffmpeg time limit, if any
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        (self.timeout_secs > 0).then(|| std::time::Duration::from_secs(self.timeout_secs))
+    }
+}
+
+/// Destructive loudness normalization with ffmpeg's two-pass loudnorm filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalizationConfig {
+    /// Normalize lossy downloads; lossless output is never touched
+    #[serde(default)]
+    pub enabled: bool,
+    /// Integrated loudness target in LUFS
+    #[serde(default = "default_target_lufs")]
+    pub target_lufs: f64,
+    /// Maximum true peak in dBTP
+    #[serde(default = "default_true_peak_db")]
+    pub true_peak_db: f64,
+    /// Loudness range target in LU
+    #[serde(default = "default_loudness_range")]
+    pub loudness_range: f64,
+}
+
+fn default_target_lufs() -> f64 {
+    -14.0
+}
+
+fn default_true_peak_db() -> f64 {
+    -1.0
+}
+
+fn default_loudness_range() -> f64 {
+    11.0
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lufs: default_target_lufs(),
+            true_peak_db: default_true_peak_db(),
+            loudness_range: default_loudness_range(),
+        }
+    }
+}
+
+/// Trimming of silence and non-music sections around downloaded tracks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimConfig {
+    /// Trim downloads before they are converted
+    #[serde(default)]
+    pub enabled: bool,
+    /// Audio quieter than this (dBFS) counts as silence
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f64,
+    /// Shortest gap that counts as silence, in seconds
+    #[serde(default = "default_min_silence_secs")]
+    pub min_silence_secs: f64,
+    /// Silence left in place at each cut, in seconds
+    #[serde(default = "default_trim_padding_secs")]
+    pub padding_secs: f64,
+    /// Cut intros and outros at silent gaps when the source is longer than the Spotify track
+    #[serde(default = "default_true")]
+    pub trim_to_reference: bool,
+    /// How much longer than the Spotify track a source may be before it is trimmed, in seconds
+    #[serde(default = "default_reference_tolerance_secs")]
+    pub reference_tolerance_secs: f64,
+    /// Never cut more than this from either end when trimming to the reference, in seconds
+    #[serde(default = "default_max_excess_trim_secs")]
+    pub max_excess_trim_secs: f64,
+}
+
+fn default_silence_threshold_db() -> f64 {
+    -50.0
+}
+
+fn default_min_silence_secs() -> f64 {
+    0.5
+}
+
+fn default_trim_padding_secs() -> f64 {
+    0.2
+}
+
+fn default_reference_tolerance_secs() -> f64 {
+    5.0
+}
+
+fn default_max_excess_trim_secs() -> f64 {
+    90.0
+}
+
+impl Default for TrimConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            silence_threshold_db: default_silence_threshold_db(),
+            min_silence_secs: default_min_silence_secs(),
+            padding_secs: default_trim_padding_secs(),
+            trim_to_reference: true,
+            reference_tolerance_secs: default_reference_tolerance_secs(),
+            max_excess_trim_secs: default_max_excess_trim_secs(),
+        }
+    }
+}
+
+/// Tolerances for splitting a single full-album upload into tracks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumSplitConfig {
+    /// How far the source length may differ from the album length, in seconds
+    #[serde(default = "default_album_total_tolerance_secs")]
+    pub total_tolerance_secs: f64,
+    /// How far a chapter may differ from its track's length, in seconds
+    #[serde(default = "default_album_track_tolerance_secs")]
+    pub track_tolerance_secs: f64,
+    /// Move track boundaries into silent gaps within this many seconds, 0 disables snapping
+    #[serde(default = "default_snap_window_secs")]
+    pub snap_window_secs: f64,
+}
+
+fn default_album_total_tolerance_secs() -> f64 {
+    60.0
+}
+
+fn default_album_track_tolerance_secs() -> f64 {
+    15.0
+}
+
+fn default_snap_window_secs() -> f64 {
+    3.0
+}
+
+impl Default for AlbumSplitConfig {
+    fn default() -> Self {
+        Self {
+            total_tolerance_secs: default_album_total_tolerance_secs(),
+            track_tolerance_secs: default_album_track_tolerance_secs(),
+            snap_window_secs: default_snap_window_secs(),
+        }
+    }
+}
+
+/// Retry limits for one stage of the download pipeline
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StageRetry {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after every attempt
+    pub initial_delay_ms: u64,
+    /// Upper bound for the delay between attempts
+    pub max_delay_ms: u64,
+}
+
+impl StageRetry {
+    const fn new(max_retries: u32, initial_delay_ms: u64, max_delay_ms: u64) -> Self {
+        Self { max_retries, initial_delay_ms, max_delay_ms }
+    }
+}
+
+/// Automatic retries for transient failures (network errors, 5xx, rate limiting)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Searching YouTube and SoundCloud
+    #[serde(default = "default_search_retry")]
+    pub search: StageRetry,
+    /// Looking up the formats and chapters of a source
+    #[serde(default = "default_metadata_retry")]
+    pub metadata: StageRetry,
+    /// Downloading the source audio
+    #[serde(default = "default_download_retry")]
+    pub download: StageRetry,
+    /// Fraction of each delay that is randomized so parallel downloads don't retry in lockstep
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: f64,
+}
+
+fn default_search_retry() -> StageRetry {
+    StageRetry::new(3, 1000, 15000)
+}
+
+fn default_metadata_retry() -> StageRetry {
+    StageRetry::new(2, 1000, 10000)
+}
+
+fn default_download_retry() -> StageRetry {
+    StageRetry::new(3, 2000, 60000)
+}
+
+fn default_retry_jitter() -> f64 {
+    0.3
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            search: default_search_retry(),
+            metadata: default_metadata_retry(),
+            download: default_download_retry(),
+            jitter: default_retry_jitter(),
+        }
+    }
+}
+
+/// How search results are matched to tracks
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MatchingConfig {
+    /// Keep uploads that look like mixes, albums or live versions, and of any length
+    #[serde(default)]
+    pub relaxed: bool,
+}
+
+/// Worker pools for the download pipeline stages; network downloads use `max_concurrent_downloads`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    /// Last.fm enrichment and other metadata lookups
+    #[serde(default = "default_metadata_workers")]
+    pub metadata_workers: usize,
+    /// YouTube and SoundCloud searches and format lookups
+    #[serde(default = "default_search_workers")]
+    pub search_workers: usize,
+    /// ffmpeg trimming and conversion
+    #[serde(default = "default_transcode_workers")]
+    pub transcode_workers: usize,
+    /// Loudness analysis, tagging, lyrics and cover art
+    #[serde(default = "default_tagging_workers")]
+    pub tagging_workers: usize,
+    /// Tracks that may wait between two stages before the earlier stage holds back
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+fn default_metadata_workers() -> usize {
+    4
+}
+
+fn default_search_workers() -> usize {
+    4
+}
+
+fn default_transcode_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| cores.get())
+        .unwrap_or(2)
+}
+
+fn default_tagging_workers() -> usize {
+    2
+}
+
+fn default_queue_capacity() -> usize {
+    4
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            metadata_workers: default_metadata_workers(),
+            search_workers: default_search_workers(),
+            transcode_workers: default_transcode_workers(),
+            tagging_workers: default_tagging_workers(),
+            queue_capacity: default_queue_capacity(),
+        }
+    }
+}
+
+/// A transfer rate cap, written like yt-dlp's --limit-rate (e.g. 500K, 2M) or "unlimited"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(try_from = "String", into = "String")]
+pub enum RateLimit {
+    #[default]
+    Unlimited,
+    /// Bytes per second
+    BytesPerSec(u64),
+}
+
+impl RateLimit {
+    /// Bytes per second, None when unlimited
+    pub fn bytes_per_sec(&self) -> Option<u64> {
+        match self {
+            RateLimit::Unlimited => None,
+            RateLimit::BytesPerSec(bytes) => Some(*bytes),
+        }
+    }
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimit::Unlimited => write!(f, "unlimited"),
+            RateLimit::BytesPerSec(bytes) if bytes % (1024 * 1024) == 0 => write!(f, "{}M", bytes / (1024 * 1024)),
+            RateLimit::BytesPerSec(bytes) if bytes % 1024 == 0 => write!(f, "{}K", bytes / 1024),
+            RateLimit::BytesPerSec(bytes) => write!(f, "{}", bytes),
+        }
+    }
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = SpotifyDownloaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let value = s.trim().to_lowercase();
+        if matches!(value.as_str(), "unlimited" | "none" | "off" | "0") {
+            return Ok(RateLimit::Unlimited);
+        }
+
+        let value = value.strip_suffix("/s").unwrap_or(&value);
+        let value = value.strip_suffix('b').unwrap_or(value);
+        let (number, multiplier) = match value.chars().last() {
+            Some('k') => (&value[..value.len() - 1], 1024.0),
+            Some('m') => (&value[..value.len() - 1], 1024.0 * 1024.0),
+            Some('g') => (&value[..value.len() - 1], 1024.0 * 1024.0 * 1024.0),
+            _ => (value, 1.0),
+        };
+        match number.trim().parse::<f64>() {
+            Ok(number) if number > 0.0 && number.is_finite() => Ok(RateLimit::BytesPerSec((number * multiplier).round().max(1.0) as u64)),
+            _ => Err(SpotifyDownloaderError::Config(format!("Invalid rate limit: {} (use e.g. 500K, 2M or unlimited)", s))),
+        }
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = SpotifyDownloaderError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<RateLimit> for String {
+    fn from(limit: RateLimit) -> Self {
+        limit.to_string()
+    }
+}
+
+/// Local time of day as minutes after midnight, written as HH:MM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub u16);
+
+impl std::fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl std::str::FromStr for TimeOfDay {
+    type Err = SpotifyDownloaderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s.trim().split_once(':')
+            .and_then(|(hours, minutes)| Some((hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?)));
+        match parsed {
+            Some((hours, minutes)) if hours < 24 && minutes < 60 => Ok(TimeOfDay(hours * 60 + minutes)),
+            Some((24, 0)) => Ok(TimeOfDay(24 * 60)),
+            _ => Err(SpotifyDownloaderError::Config(format!("Invalid time of day: {} (use HH:MM)", s))),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = SpotifyDownloaderError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+/// A daily window with its own rate limit; a window ending before it starts runs past midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BandwidthWindow {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    pub limit: RateLimit,
+}
+
+impl BandwidthWindow {
+    /// Whether the window covers a minute of the day
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start.0 <= self.end.0 {
+            (self.start.0..self.end.0).contains(&minute)
+        } else {
+            minute >= self.start.0 || minute < self.end.0
+        }
+    }
+}
+
+/// Bandwidth cap shared by every download, split between yt-dlp jobs and enforced on our own transfers
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BandwidthConfig {
+    /// Cap for all downloads together outside the scheduled windows
+    #[serde(default)]
+    pub limit: RateLimit,
+    /// Cap for any single download on top of its share of the total
+    #[serde(default)]
+    pub per_download_limit: RateLimit,
+    /// Time-of-day windows that replace the total cap, the first matching window wins
+    #[serde(default)]
+    pub schedule: Vec<BandwidthWindow>,
+}
+
+impl BandwidthConfig {
+    /// Total cap in effect at a minute of the day
+    pub fn limit_at(&self, minute: u16) -> RateLimit {
+        self.schedule.iter()
+            .find(|window| window.contains(minute))
+            .map(|window| window.limit)
+            .unwrap_or(self.limit)
+    }
+}
+
+/// Preferences for picking a source stream from the formats yt-dlp offers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatSelectionConfig {
+    /// Codecs in order of preference, e.g. "opus", "aac"
+    #[serde(default = "default_preferred_codecs")]
+    pub preferred_codecs: Vec<String>,
+    /// Skip formats below this average bitrate when better ones exist
+    #[serde(default)]
+    pub min_abr_kbps: u32,
+    /// Skip dynamic range compressed ("-drc") variants
+    #[serde(default = "default_true")]
+    pub avoid_drc: bool,
+    /// Prefer streams that can be copied into the output format without re-encoding
+    #[serde(default = "default_true")]
+    pub prefer_remuxable: bool,
+}
+
+fn default_preferred_codecs() -> Vec<String> {
+    vec!["opus".to_string(), "aac".to_string(), "vorbis".to_string(), "mp3".to_string()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for FormatSelectionConfig {
+    fn default() -> Self {
+        Self {
+            preferred_codecs: default_preferred_codecs(),
+            min_abr_kbps: 0,
+            avoid_drc: true,
+            prefer_remuxable: true,
+        }
+    }
+}
+
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub download_directory: PathBuf,
     pub default_format: AudioFormat,
     pub default_bitrate: Bitrate,
+    /// Quality override per output format, falls back to the default bitrate
+    #[serde(default)]
+    pub format_quality: HashMap<AudioFormat, Quality>,
     pub cover_config: CoverConfig,
     pub metadata_config: MetadataConfig,
     pub api_keys: ApiKeys,
@@ -278,6 +1004,24 @@ pub struct Config {
     pub sponsorblock_config: SponsorBlockConfig,
     pub cookies_config: CookiesConfig,
     pub proxy_config: ProxyConfig,
+    #[serde(default)]
+    pub conversion_config: ConversionConfig,
+    #[serde(default)]
+    pub format_selection: FormatSelectionConfig,
+    #[serde(default)]
+    pub normalization: NormalizationConfig,
+    #[serde(default)]
+    pub trim: TrimConfig,
+    #[serde(default)]
+    pub album_split: AlbumSplitConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    #[serde(default)]
+    pub matching: MatchingConfig,
 }
 
 impl Default for Config {
@@ -288,6 +1032,7 @@ impl Default for Config {
                 .join("SpotifyDownloads"),
             default_format: AudioFormat::Mp3,
             default_bitrate: Bitrate::Kbps320,
+            format_quality: HashMap::new(),
             cover_config: CoverConfig::default(),
             metadata_config: MetadataConfig::default(),
             api_keys: ApiKeys::default(),
@@ -295,6 +1040,15 @@ impl Default for Config {
             sponsorblock_config: SponsorBlockConfig::default(),
             cookies_config: CookiesConfig::default(),
             proxy_config: ProxyConfig::default(),
+            conversion_config: ConversionConfig::default(),
+            format_selection: FormatSelectionConfig::default(),
+            normalization: NormalizationConfig::default(),
+            trim: TrimConfig::default(),
+            album_split: AlbumSplitConfig::default(),
+            retry: RetryConfig::default(),
+            bandwidth: BandwidthConfig::default(),
+            pipeline: PipelineConfig::default(),
+            matching: MatchingConfig::default(),
         }
     }
 }
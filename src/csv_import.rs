@@ -1,4 +1,8 @@
-use crate::downloader::TrackMetadata;
+use crate::downloader::{AudioFeatures, TrackMetadata};
+use crate::downloader::async_manager::AsyncDownloadManager;
+use crate::downloader::job_store::SharedJobStore;
+use crate::downloader::archive::SharedArchive;
+use crate::downloader::failure_report::{FailureRecord, FailureReport};
 use crate::errors::{Result, SpotifyDownloaderError};
 use std::path::PathBuf;
 use std::collections::HashMap;
@@ -25,7 +29,7 @@ pub struct SpotifyRecord {
     pub record_label: String,
     pub danceability: f32,
     pub energy: f32,
-    pub key: u32,
+    pub key: i32,
     pub loudness: f32,
     pub mode: u32,
     pub speechiness: f32,
@@ -118,9 +122,10 @@ impl CsvImporter {
             .and_then(|s| s.parse::<f32>().ok())
             .unwrap_or(0.0);
         
+        // Spotify uses -1 when no key was detected
         let key = record.get(14)
-            .and_then(|s| s.parse::<u32>().ok())
-            .unwrap_or(0);
+            .and_then(|s| s.parse::<i32>().ok())
+            .unwrap_or(-1);
         
         let loudness = record.get(15)
             .and_then(|s| s.parse::<f32>().ok())
@@ -213,6 +218,21 @@ impl CsvImporter {
             id.clone()
         };
 
+        let audio_features = AudioFeatures {
+            tempo: record.tempo,
+            key: record.key,
+            mode: record.mode,
+            time_signature: record.time_signature,
+            loudness: record.loudness,
+            energy: record.energy,
+            danceability: record.danceability,
+            valence: record.valence,
+            acousticness: record.acousticness,
+            instrumentalness: record.instrumentalness,
+            liveness: record.liveness,
+            speechiness: record.speechiness,
+        };
+
         // Cover art will be fetched by CoverDownloader during download
 
         TrackMetadata {
@@ -232,6 +252,9 @@ impl CsvImporter {
             album_cover_url: None, // Will be fetched by CoverDownloader during download
             composer: None, // Not available in CSV
             comment: Some("Imported from Spotify CSV".to_string()),
+            audio_features: Some(audio_features),
+            isrc: None,
+            total_tracks: None,
         }
     }
 
@@ -325,55 +348,53 @@ pub struct CsvInfo {
     pub headers: Vec<String>,
 }
 
-/// Batch download tracks from CSV
+/// Batch download tracks from CSV through the download pipeline
 pub struct CsvBatchDownloader {
     csv_importer: CsvImporter,
-    audio_downloader: crate::downloader::audio::AudioDownloader,
+    manager: AsyncDownloadManager,
 }
 
 impl CsvBatchDownloader {
-    /// Create a new CSV batch downloader
-    pub fn new() -> Self {
-        // Get proxy-configured client
-        let client = crate::api::get_api_manager()
-            .map(|api_manager| api_manager.client().clone())
-            .unwrap_or_else(|_| reqwest::Client::new());
-        
+    /// Create a CSV batch downloader running up to `max_concurrent` downloads at once
+    pub fn new(max_concurrent: usize) -> Self {
         Self {
             csv_importer: CsvImporter::new(),
-            audio_downloader: crate::downloader::audio::AudioDownloader::new_with_client(client),
+            manager: AsyncDownloadManager::new(max_concurrent),
         }
     }
 
-    /// Download all tracks from a CSV file
-    pub async fn download_from_csv(
-        &mut self,
-        csv_path: &PathBuf,
+    /// Record every download in a persistent queue so an interrupted import can pick up where it stopped
+    pub fn set_job_store(&mut self, store: SharedJobStore) {
+        self.manager.set_job_store(store);
+    }
+
+    /// Skip tracks found in the download archive and record every finished download in it
+    pub fn set_archive(&mut self, archive: SharedArchive) {
+        self.manager.set_archive(archive);
+    }
+
+    /// Download every track again, ignoring the archive and completed jobs
+    pub fn set_force(&mut self, force: bool) {
+        self.manager.set_force(force);
+    }
+
+    /// Manager the tracks are downloaded with, to follow their progress or cancel the import
+    pub fn manager_mut(&mut self) -> &mut AsyncDownloadManager {
+        &mut self.manager
+    }
+
+    /// Options CSV imports download with: everything embedded, audio features as configured
+    pub fn download_options(
         output_dir: &PathBuf,
         format: crate::config::AudioFormat,
         bitrate: crate::config::Bitrate,
-        progress_callback: Option<Box<dyn Fn(usize, usize, String) + Send + Sync>>,
+        quality: crate::config::Quality,
         config: &crate::config::Config,
-    ) -> Result<CsvDownloadResult> {
-        println!("Starting batch download from CSV: {}", csv_path.display());
-
-        // Import tracks from CSV
-        let tracks = self.csv_importer.import_from_csv(csv_path).await?;
-        let total_tracks = tracks.len();
-
-        if total_tracks == 0 {
-            return Ok(CsvDownloadResult {
-                total_tracks: 0,
-                successful_downloads: 0,
-                failed_downloads: 0,
-                failed_tracks: Vec::new(),
-            });
-        }
-
-        // Create download options
-        let download_options = crate::downloader::DownloadOptions {
+    ) -> crate::downloader::DownloadOptions {
+        crate::downloader::DownloadOptions {
             format,
             bitrate,
+            quality,
             output_dir: output_dir.clone(),
             download_lyrics: true,
             download_cover: true,
@@ -392,35 +413,45 @@ impl CsvBatchDownloader {
             embed_album_artist: true,
             embed_composer: true,
             embed_comment: true,
-        };
+            embed_bpm: config.metadata_config.embed_bpm,
+            embed_audio_features: config.metadata_config.embed_audio_features,
+            key_notation: config.metadata_config.key_notation,
+            normalization: config.normalization.clone(),
+            trim: config.trim.clone(),
+            output_path: None,
+        }
+    }
 
-        let mut successful_downloads = 0;
-        let mut failed_downloads = 0;
-        let mut failed_tracks = Vec::new();
+    /// Download all tracks from a CSV file
+    pub async fn download_from_csv(
+        &self,
+        csv_path: &PathBuf,
+        download_options: &crate::downloader::DownloadOptions,
+        config: &crate::config::Config,
+    ) -> Result<CsvDownloadResult> {
+        println!("Starting batch download from CSV: {}", csv_path.display());
 
-        // Download each track
-        for (index, track) in tracks.iter().enumerate() {
-            if let Some(ref callback) = progress_callback {
-                callback(index + 1, total_tracks, format!("Downloading: {} - {}", track.artist, track.title));
-            }
+        // Import tracks from CSV
+        let tracks = self.csv_importer.import_from_csv(csv_path).await?;
+        let total_tracks = tracks.len();
 
-            match self.audio_downloader.download_track(track, &download_options, None, config).await {
-                Ok(_) => {
-                    successful_downloads += 1;
-                    println!("✓ Downloaded: {} - {}", track.artist, track.title);
-                }
-                Err(e) => {
-                    failed_downloads += 1;
-                    failed_tracks.push((track.clone(), e.to_string()));
-                    println!("✗ Failed: {} - {} - Error: {}", track.artist, track.title, e);
-                }
-            }
+        if total_tracks == 0 {
+            return Ok(CsvDownloadResult {
+                total_tracks: 0,
+                successful_downloads: 0,
+                failed_downloads: 0,
+                failed_tracks: Vec::new(),
+            });
         }
 
+        // Tracks that are archived or finished in an earlier run come back as successful without downloading
+        let results = self.manager.download_tracks(tracks, download_options, config).await?;
+        let failed_tracks = FailureReport::from_results(&results).failures;
+
         let result = CsvDownloadResult {
             total_tracks,
-            successful_downloads,
-            failed_downloads,
+            successful_downloads: results.iter().filter(|result| result.success).count(),
+            failed_downloads: failed_tracks.len(),
             failed_tracks,
         };
 
@@ -439,5 +470,5 @@ pub struct CsvDownloadResult {
     pub total_tracks: usize,
     pub successful_downloads: usize,
     pub failed_downloads: usize,
-    pub failed_tracks: Vec<(TrackMetadata, String)>,
+    pub failed_tracks: Vec<FailureRecord>,
 }
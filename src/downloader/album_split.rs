@@ -0,0 +1,278 @@
+use crate::config::AlbumSplitConfig;
+use crate::downloader::TrackMetadata;
+use crate::downloader::trim::SilenceInterval;
+use crate::downloader::youtube::YtDlpInfo;
+use crate::errors::{Result, SpotifyDownloaderError};
+
+/// Audio kept next to the sound when a boundary is moved into a silent gap
+const SNAP_LEAD_IN_SECS: f64 = 0.1;
+
+/// Where the track boundaries of a full-album upload came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterSource {
+    /// Chapters set on the video itself
+    Chapters,
+    /// Timestamps listed in the video description
+    Description,
+    /// Cumulative Spotify track durations
+    Durations,
+}
+
+impl std::fmt::Display for ChapterSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChapterSource::Chapters => write!(f, "video chapters"),
+            ChapterSource::Description => write!(f, "description timestamps"),
+            ChapterSource::Durations => write!(f, "Spotify track durations"),
+        }
+    }
+}
+
+/// A titled start position in the album source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start_secs: f64,
+}
+
+/// The part of the album source that becomes one track
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSegment {
+    /// Index into the album's track list
+    pub track_index: usize,
+    pub start_secs: f64,
+    pub end_secs: f64,
+}
+
+impl TrackSegment {
+    /// Length of the segment
+    pub fn duration_secs(&self) -> f64 {
+        self.end_secs - self.start_secs
+    }
+}
+
+/// How a full-album source is cut into tracks.
+/// Consecutive segments share their boundary so no audio is lost or duplicated between tracks,
+/// unless chapters between them were left out.
+#[derive(Debug, Clone)]
+pub struct AlbumSplitPlan {
+    pub source: ChapterSource,
+    pub segments: Vec<TrackSegment>,
+}
+
+/// Aligns the chapters of a full-album upload with the album's Spotify track list
+pub struct AlbumSplitter {
+    config: AlbumSplitConfig,
+}
+
+impl AlbumSplitter {
+    /// Create a splitter with the given tolerances
+    pub fn new(config: AlbumSplitConfig) -> Self {
+        Self { config }
+    }
+
+    /// Chapters from the video itself, falling back to timestamps in its description
+    pub fn chapters_from_info(info: &YtDlpInfo) -> Option<(Vec<Chapter>, ChapterSource)> {
+        let chapters: Vec<Chapter> = info.chapters.iter()
+            .flatten()
+            .map(|chapter| Chapter {
+                title: chapter.title.clone().unwrap_or_default(),
+                start_secs: chapter.start_time,
+            })
+            .collect();
+        if chapters.len() > 1 {
+            return Some((chapters, ChapterSource::Chapters));
+        }
+
+        let chapters = Self::parse_description(info.description.as_deref().unwrap_or_default());
+        (chapters.len() > 1).then_some((chapters, ChapterSource::Description))
+    }
+
+    /// Parse tracklist lines such as "03. Title 12:34" or "1:02:03 - Title" from a description
+    pub fn parse_description(description: &str) -> Vec<Chapter> {
+        let timestamp = regex::Regex::new(r"(?:(\d{1,2}):)?(\d{1,2}):(\d{2})").unwrap();
+        let leading_number = regex::Regex::new(r"^\s*\d{1,3}\s*[.)\-:]\s+").unwrap();
+
+        let mut chapters: Vec<Chapter> = Vec::new();
+        for line in description.lines() {
+            let Some(captures) = timestamp.captures(line) else { continue };
+            let hours = captures.get(1).and_then(|hours| hours.as_str().parse::<f64>().ok()).unwrap_or(0.0);
+            let minutes = captures[2].parse::<f64>().unwrap_or(0.0);
+            let seconds = captures[3].parse::<f64>().unwrap_or(0.0);
+            let start_secs = hours * 3600.0 + minutes * 60.0 + seconds;
+
+            // Timestamps must increase, anything else is not part of the tracklist
+            if chapters.last().is_some_and(|previous| previous.start_secs >= start_secs) {
+                continue;
+            }
+
+            let whole = captures.get(0).unwrap();
+            let title = format!("{} {}", &line[..whole.start()], &line[whole.end()..]);
+            let title = leading_number.replace(title.trim(), "");
+            let title = title
+                .trim_matches(|c: char| c.is_whitespace() || "-–—|:()[]".contains(c))
+                .to_string();
+            chapters.push(Chapter { title, start_secs });
+        }
+
+        // A tracklist starts at the beginning of the video
+        if chapters.first().is_some_and(|first| first.start_secs > 0.0) {
+            return Vec::new();
+        }
+        chapters
+    }
+
+    /// Align chapters (or, without any, the Spotify durations) with the album's tracks
+    pub fn plan(
+        &self,
+        chapters: Option<(Vec<Chapter>, ChapterSource)>,
+        tracks: &[TrackMetadata],
+        source_duration_secs: f64,
+    ) -> Result<AlbumSplitPlan> {
+        if tracks.is_empty() {
+            return Err(SpotifyDownloaderError::Download("Album has no tracks".to_string()));
+        }
+
+        let album_secs: f64 = tracks.iter().map(|track| track.duration_ms as f64 / 1000.0).sum();
+        if (source_duration_secs - album_secs).abs() > self.config.total_tolerance_secs {
+            return Err(SpotifyDownloaderError::Download(format!(
+                "Source is {:.0}s long but the album is {:.0}s",
+                source_duration_secs, album_secs
+            )));
+        }
+
+        let (segments, source) = match chapters {
+            Some((chapters, source)) => (self.align_chapters(&chapters, tracks, source_duration_secs)?, source),
+            None => (Self::segments_from_durations(tracks, source_duration_secs), ChapterSource::Durations),
+        };
+
+        Ok(AlbumSplitPlan { source, segments })
+    }
+
+    /// Move each boundary between two tracks into a nearby silent gap.
+    /// Segments that touch keep sharing their boundary, the ends of a gap left between chapters snap on their own.
+    pub fn snap_to_silence(&self, plan: &mut AlbumSplitPlan, silences: &[SilenceInterval]) {
+        // The nearest snapped position within the window, strictly between the neighbouring boundaries
+        let nearest = |boundary: f64, lower: f64, upper: f64, snap: &dyn Fn(&SilenceInterval) -> f64| {
+            silences.iter()
+                .map(snap)
+                .filter(|snapped| (snapped - boundary).abs() <= self.config.snap_window_secs)
+                .filter(|snapped| *snapped > lower && *snapped < upper)
+                .min_by(|a, b| (a - boundary).abs().total_cmp(&(b - boundary).abs()))
+        };
+        // Tracks start just before the sound returns and end just after it fades out
+        let track_start = |silence: &SilenceInterval| (silence.end_secs - SNAP_LEAD_IN_SECS).max(silence.start_secs);
+        let track_end = |silence: &SilenceInterval| (silence.start_secs + SNAP_LEAD_IN_SECS).min(silence.end_secs);
+
+        for index in 1..plan.segments.len() {
+            let previous = plan.segments[index - 1].clone();
+            let current = plan.segments[index].clone();
+
+            if previous.end_secs == current.start_secs {
+                let boundary = current.start_secs;
+                if let Some(snapped) = nearest(boundary, previous.start_secs, current.end_secs, &track_start) {
+                    if (snapped - boundary).abs() > f64::EPSILON {
+                        println!("🔇 Moved boundary before track {} from {:.2}s to {:.2}s", index + 1, boundary, snapped);
+                    }
+                    plan.segments[index - 1].end_secs = snapped;
+                    plan.segments[index].start_secs = snapped;
+                }
+                continue;
+            }
+
+            if let Some(snapped) = nearest(previous.end_secs, previous.start_secs, current.start_secs, &track_end) {
+                if (snapped - previous.end_secs).abs() > f64::EPSILON {
+                    println!("🔇 Moved end of track {} from {:.2}s to {:.2}s", index, previous.end_secs, snapped);
+                }
+                plan.segments[index - 1].end_secs = snapped;
+            }
+            let lower = plan.segments[index - 1].end_secs;
+            if let Some(snapped) = nearest(current.start_secs, lower, current.end_secs, &track_start) {
+                if (snapped - current.start_secs).abs() > f64::EPSILON {
+                    println!("🔇 Moved start of track {} from {:.2}s to {:.2}s", index + 1, current.start_secs, snapped);
+                }
+                plan.segments[index].start_secs = snapped;
+            }
+        }
+    }
+
+    /// Pair chapters with tracks in order when the counts match, by title otherwise
+    fn align_chapters(&self, chapters: &[Chapter], tracks: &[TrackMetadata], source_duration_secs: f64) -> Result<Vec<TrackSegment>> {
+        let chapter_end = |index: usize| chapters.get(index + 1).map(|next| next.start_secs).unwrap_or(source_duration_secs);
+
+        let matched: Vec<usize> = if chapters.len() == tracks.len() {
+            (0..tracks.len()).collect()
+        } else {
+            let mut matched = Vec::new();
+            let mut next_chapter = 0;
+            for track in tracks {
+                let title = Self::normalize(&track.title);
+                let found = (next_chapter..chapters.len()).find(|index| {
+                    let chapter_title = Self::normalize(&chapters[*index].title);
+                    !chapter_title.is_empty() && (chapter_title.contains(&title) || title.contains(&chapter_title))
+                });
+                let Some(found) = found else {
+                    return Err(SpotifyDownloaderError::Download(format!(
+                        "No chapter matches \"{}\" ({} chapters for {} tracks)",
+                        track.title, chapters.len(), tracks.len()
+                    )));
+                };
+                matched.push(found);
+                next_chapter = found + 1;
+            }
+            matched
+        };
+
+        let segments: Vec<TrackSegment> = matched.iter()
+            .enumerate()
+            .map(|(track_index, chapter_index)| TrackSegment {
+                track_index,
+                start_secs: chapters[*chapter_index].start_secs,
+                end_secs: chapter_end(*chapter_index),
+            })
+            .collect();
+
+        let mismatched: Vec<String> = segments.iter()
+            .filter_map(|segment| {
+                let track = &tracks[segment.track_index];
+                let expected = track.duration_ms as f64 / 1000.0;
+                ((segment.duration_secs() - expected).abs() > self.config.track_tolerance_secs).then(|| {
+                    format!("{} ({:.0}s vs {:.0}s)", track.title, segment.duration_secs(), expected)
+                })
+            })
+            .collect();
+        if !mismatched.is_empty() {
+            return Err(SpotifyDownloaderError::Download(format!(
+                "Chapters do not match the track durations: {}",
+                mismatched.join(", ")
+            )));
+        }
+
+        Ok(segments)
+    }
+
+    /// Boundaries at the cumulative Spotify durations, the last track runs to the end of the source
+    fn segments_from_durations(tracks: &[TrackMetadata], source_duration_secs: f64) -> Vec<TrackSegment> {
+        let mut start = 0.0;
+        let mut segments = Vec::with_capacity(tracks.len());
+        for (track_index, track) in tracks.iter().enumerate() {
+            let end = if track_index + 1 == tracks.len() {
+                source_duration_secs
+            } else {
+                (start + track.duration_ms as f64 / 1000.0).min(source_duration_secs)
+            };
+            segments.push(TrackSegment { track_index, start_secs: start, end_secs: end });
+            start = end;
+        }
+        segments
+    }
+
+    fn normalize(text: &str) -> String {
+        // Drop bracketed suffixes like "(Remastered 2011)" before comparing
+        let without_suffix = text.split(['(', '[']).next().unwrap_or(text);
+        without_suffix.to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect()
+    }
+}
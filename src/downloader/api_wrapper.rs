@@ -27,6 +27,25 @@ impl ApiWrapper {
         }
     }
 
+    /// Search Spotify tracks - tries centralized API first, falls back to existing client
+    pub async fn search_spotify_tracks(query: &str, limit: u32) -> Result<Vec<TrackMetadata>> {
+        match crate::api::integration::ApiIntegration::search_spotify_tracks(query, limit).await {
+            Ok(tracks) => Ok(tracks),
+            Err(_) => {
+                let settings = crate::settings::Settings::load_from_local_json()
+                    .unwrap_or_else(|_| crate::settings::Settings::default());
+                let config = settings.config();
+                
+                let mut spotify_client = crate::downloader::spotify::SpotifyClient::new(
+                    config.api_keys.spotify_client_id.clone().unwrap_or_default(),
+                    config.api_keys.spotify_client_secret.clone().unwrap_or_default(),
+                );
+                
+                spotify_client.search_tracks(query, limit).await
+            }
+        }
+    }
+
     /// Get Spotify album metadata - tries centralized API first, falls back to existing client
     pub async fn get_spotify_album_metadata(url: &str) -> Result<AlbumMetadata> {
         // Try centralized API first
@@ -74,7 +93,7 @@ impl ApiWrapper {
         // Use existing YouTube downloader directly
         let youtube_downloader = crate::downloader::youtube::YoutubeDownloader::new();
         let config = crate::config::Config::default();
-        youtube_downloader.download_audio(url, output_path, crate::config::AudioFormat::Mp3, crate::config::Bitrate::Kbps320, None, &config).await
+        youtube_downloader.download_audio(url, output_path, crate::config::AudioFormat::Mp3, crate::config::Quality::Cbr(320), None, &config).await
     }
 
     /// Download SoundCloud audio - uses existing SoundCloud downloader
@@ -113,6 +132,9 @@ impl ApiWrapper {
             album_cover_url: None,
             composer: None,
             comment: None,
+            audio_features: None,
+            isrc: None,
+            total_tracks: None,
         };
         
         match cover_downloader.find_cover_art(&track).await {
@@ -138,23 +160,57 @@ impl ApiWrapper {
         crate::api::get_api_manager().is_ok()
     }
 
-    /// Get Last.fm track info - uses Last.fm API with client_id and client_secret
-    pub async fn get_lastfm_track_info(artist: &str, track: &str) -> Result<LastfmTrackInfo> {
-        let client_id = std::env::var("LASTFM_CLIENT_ID")
-            .map_err(|_| SpotifyDownloaderError::Lastfm("LASTFM_CLIENT_ID not set".to_string()))?;
-        let _client_secret = std::env::var("LASTFM_CLIENT_SECRET")
-            .map_err(|_| SpotifyDownloaderError::Lastfm("LASTFM_CLIENT_SECRET not set".to_string()))?;
+    /// Get the Last.fm API key - prefers the given config, falls back to the API manager
+    pub fn lastfm_api_key(config: &crate::config::Config) -> Result<String> {
+        config.api_keys.lastfm_api_key.clone()
+            .filter(|key| !key.is_empty())
+            .or_else(|| {
+                crate::api::get_api_manager().ok()
+                    .and_then(|api_manager| api_manager.lastfm_api_key().map(str::to_string))
+            })
+            .ok_or_else(|| SpotifyDownloaderError::Lastfm("Last.fm API key not configured".to_string()))
+    }
 
-        let client = reqwest::Client::new();
+    /// Make a Last.fm API call through the shared client, waiting for the rate limiter
+    async fn lastfm_request(api_key: &str, params: &[(&str, &str)]) -> Result<serde_json::Value> {
+        let api_manager = crate::api::get_api_manager()?;
+
+        let mut query = vec![("api_key", api_key), ("format", "json")];
+        query.extend_from_slice(params);
         let url = format!(
-            "https://ws.audioscrobbler.com/2.0/?method=track.getinfo&api_key={}&artist={}&track={}&format=json",
-            client_id,
-            urlencoding::encode(artist),
-            urlencoding::encode(track)
+            "https://ws.audioscrobbler.com/2.0/?{}",
+            crate::utils::net::NetworkUtils::build_query_string(&query)
         );
 
-        let response = client.get(&url).send().await?;
-        let json: serde_json::Value = response.json().await?;
+        let client = api_manager.client().clone();
+        let json: serde_json::Value = api_manager.make_queued_request("lastfm", move || {
+            Box::pin(async move {
+                let response = client.get(&url).send().await?;
+                if !response.status().is_success() {
+                    return Err(SpotifyDownloaderError::from_response("Last.fm request failed", response).await);
+                }
+                Ok(response.json::<serde_json::Value>().await?)
+            })
+        }).await?;
+
+        // Last.fm reports failures in the body with a 200 status
+        if let Some(message) = json.get("message").and_then(|v| v.as_str()) {
+            if json.get("error").is_some() {
+                return Err(SpotifyDownloaderError::Lastfm(message.to_string()));
+            }
+        }
+
+        Ok(json)
+    }
+
+    /// Get Last.fm track info
+    pub async fn get_lastfm_track_info(api_key: &str, artist: &str, track: &str) -> Result<LastfmTrackInfo> {
+        let json = Self::lastfm_request(api_key, &[
+            ("method", "track.getinfo"),
+            ("artist", artist),
+            ("track", track),
+            ("autocorrect", "1"),
+        ]).await?;
 
         if let Some(track_data) = json.get("track") {
             let name = track_data.get("name")
@@ -176,6 +232,7 @@ impl ApiWrapper {
             let duration = track_data.get("duration")
                 .and_then(|v| v.as_str())
                 .and_then(|s| s.parse::<u32>().ok())
+                .filter(|ms| *ms > 0)
                 .map(|ms| ms / 1000); // Convert to seconds
 
             let playcount = track_data.get("playcount")
@@ -213,19 +270,12 @@ impl ApiWrapper {
     }
 
     /// Get Last.fm artist info
-    pub async fn get_lastfm_artist_info(artist: &str) -> Result<LastfmArtistInfo> {
-        let client_id = std::env::var("LASTFM_CLIENT_ID")
-            .map_err(|_| SpotifyDownloaderError::Lastfm("LASTFM_CLIENT_ID not set".to_string()))?;
-
-        let client = reqwest::Client::new();
-        let url = format!(
-            "https://ws.audioscrobbler.com/2.0/?method=artist.getinfo&api_key={}&artist={}&format=json",
-            client_id,
-            urlencoding::encode(artist)
-        );
-
-        let response = client.get(&url).send().await?;
-        let json: serde_json::Value = response.json().await?;
+    pub async fn get_lastfm_artist_info(api_key: &str, artist: &str) -> Result<LastfmArtistInfo> {
+        let json = Self::lastfm_request(api_key, &[
+            ("method", "artist.getinfo"),
+            ("artist", artist),
+            ("autocorrect", "1"),
+        ]).await?;
 
         if let Some(artist_data) = json.get("artist") {
             let name = artist_data.get("name")
@@ -429,8 +479,7 @@ impl ApiWrapper {
     pub async fn get_api_status() -> Result<ApiStatus> {
         let api_manager = crate::api::get_api_manager()?;
         
-        let lastfm_configured = std::env::var("LASTFM_CLIENT_ID").is_ok() && 
-                               std::env::var("LASTFM_CLIENT_SECRET").is_ok();
+        let lastfm_configured = api_manager.lastfm_api_key().is_some();
         
         let youtube_configured = std::env::var("YOUTUBE_API_KEY").is_ok();
         let soundcloud_configured = std::env::var("SOUNDCLOUD_CLIENT_ID").is_ok();
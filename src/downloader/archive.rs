@@ -0,0 +1,424 @@
+use crate::config::Config;
+use crate::downloader::metadata::MetadataEmbedder;
+use crate::downloader::retag::Retagger;
+use crate::downloader::TrackMetadata;
+use crate::errors::{Result, SpotifyDownloaderError};
+use crate::utils::fs::LogLock;
+use crate::utils::Utils;
+use lofty::file::TaggedFileExt;
+use lofty::tag::Accessor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A download archive shared between concurrent download tasks
+pub type SharedArchive = Arc<Mutex<DownloadArchive>>;
+
+/// A track that was downloaded before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Spotify track ID, or the ISRC or artist and title when there is none
+    pub key: String,
+    pub spotify_id: Option<String>,
+    pub isrc: Option<String>,
+    pub artist: String,
+    pub title: String,
+    pub path: PathBuf,
+    /// MD5 of the file when it was recorded
+    pub content_hash: Option<String>,
+    /// Size of the file in bytes when it was recorded
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Modification time of the file when it was recorded, in Unix nanoseconds
+    #[serde(default)]
+    pub modified: Option<u128>,
+    /// Unix timestamp of when the track was recorded
+    pub recorded_at: u64,
+}
+
+/// Result of rebuilding the archive from a folder of tagged files
+#[derive(Debug, Clone, Default)]
+pub struct RebuildSummary {
+    pub scanned: usize,
+    pub recorded: usize,
+    /// Files without a Spotify ID, ISRC or artist and title
+    pub unidentified: usize,
+}
+
+/// Memory of every track that was downloaded, kept as an append-only JSON Lines log.
+/// Tracks are matched by Spotify track ID first, then by ISRC, then by artist and title; the last line for a key wins.
+/// The log is compacted when it is opened or rebuilt while no other archive has it open.
+pub struct DownloadArchive {
+    path: PathBuf,
+    entries: HashMap<String, ArchiveEntry>,
+    order: Vec<String>,
+    /// ISRC to archive key
+    isrc_index: HashMap<String, String>,
+    /// Lowercase artist and title to archive key, for entries without a Spotify ID or ISRC
+    track_index: HashMap<String, String>,
+    writer: BufWriter<File>,
+    lock: LogLock,
+}
+
+impl DownloadArchive {
+    /// Location of the archive in the config directory
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("download_archive.jsonl"))
+    }
+
+    /// Open the archive in the config directory
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    /// Open an archive, replaying its log and compacting it when no other archive has it open
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let lock = LogLock::acquire(path)?;
+        let mut archive = Self {
+            path: path.to_path_buf(),
+            entries: HashMap::new(),
+            order: Vec::new(),
+            isrc_index: HashMap::new(),
+            track_index: HashMap::new(),
+            writer: Self::open_writer(path)?,
+            lock,
+        };
+        archive.reload()?;
+        Ok(archive)
+    }
+
+    /// Entries of an archive log, one per line
+    fn read_log(path: &Path) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = Vec::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (line_number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<ArchiveEntry>(&line) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => println!("⚠️ Skipping unreadable archive entry on line {}: {}", line_number + 1, e),
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn open_writer(path: &Path) -> Result<BufWriter<File>> {
+        Ok(BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+
+    /// Open the default archive for sharing between tasks
+    pub fn open_shared() -> Result<SharedArchive> {
+        Ok(Arc::new(Mutex::new(Self::open_default()?)))
+    }
+
+    /// Key a track is archived under
+    pub fn archive_key(track: &TrackMetadata) -> String {
+        Self::key_for(track.spotify_track_id().as_deref(), track.isrc.as_deref(), &track.artist, &track.title)
+    }
+
+    /// Archived entry for a track, matched by Spotify ID, ISRC or artist and title
+    pub fn find(&self, track: &TrackMetadata) -> Option<&ArchiveEntry> {
+        self.entries.get(&Self::archive_key(track))
+            .or_else(|| {
+                let isrc = track.isrc.as_ref()?;
+                self.entries.get(self.isrc_index.get(&isrc.to_uppercase())?)
+            })
+            .or_else(|| {
+                // Tracks recorded from untagged files are only known by artist and title,
+                // entries with their own Spotify ID or ISRC never match a different recording this way
+                let track_key = Self::track_key(&track.artist, &track.title)?;
+                self.entries.get(self.track_index.get(&track_key)?)
+            })
+    }
+
+    /// File of an archived entry that is still on disk as it was recorded;
+    /// a missing or changed file means the track is downloaded again.
+    /// Hashing can take a while on large files, so shared archives call this after unlocking.
+    pub fn verify(entry: &ArchiveEntry) -> Option<PathBuf> {
+        if !entry.path.exists() {
+            return None;
+        }
+        if !Self::is_unchanged(entry) {
+            println!("⚠️ {} changed since it was archived, downloading it again", entry.path.display());
+            return None;
+        }
+        Some(entry.path.clone())
+    }
+
+    /// Update the size and hash recorded for a file whose tags were rewritten after it was archived
+    pub fn refresh(&mut self, path: &Path) -> Result<()> {
+        let Some(mut entry) = self.entries().find(|entry| entry.path == path).cloned() else {
+            return Ok(());
+        };
+        entry.content_hash = Self::content_hash(path);
+        entry.size = Self::file_size(path);
+        entry.modified = Self::modified(path);
+        self.append(&entry)?;
+        self.insert(entry);
+        Ok(())
+    }
+
+    /// Remember a finished download together with the hash of its file
+    pub fn record(&mut self, track: &TrackMetadata, path: &Path) -> Result<()> {
+        let content_hash = Self::content_hash(path);
+        let spotify_id = track.spotify_track_id();
+        let entry = ArchiveEntry {
+            key: Self::key_for(spotify_id.as_deref(), track.isrc.as_deref(), &track.artist, &track.title),
+            spotify_id,
+            isrc: track.isrc.clone(),
+            artist: track.artist.clone(),
+            title: track.title.clone(),
+            path: path.to_path_buf(),
+            content_hash,
+            size: Self::file_size(path),
+            modified: Self::modified(path),
+            recorded_at: Self::now(),
+        };
+        self.append(&entry)?;
+        self.insert(entry);
+        Ok(())
+    }
+
+    /// Record every tagged audio file under a folder, identified by its embedded Spotify ID or ISRC
+    pub fn rebuild_from(&mut self, folder: &Path) -> Result<RebuildSummary> {
+        let files = Retagger::collect_audio_files(folder)?;
+        let metadata_embedder = MetadataEmbedder::new();
+        let mut summary = RebuildSummary {
+            scanned: files.len(),
+            ..Default::default()
+        };
+
+        for file in &files {
+            let spotify_id = metadata_embedder.read_spotify_track_id(file);
+            let isrc = metadata_embedder.read_isrc(file);
+            let (artist, title) = Self::read_artist_title(file);
+            if spotify_id.is_none() && isrc.is_none() && (artist.is_empty() || title.is_empty()) {
+                summary.unidentified += 1;
+                continue;
+            }
+
+            let entry = ArchiveEntry {
+                key: Self::key_for(spotify_id.as_deref(), isrc.as_deref(), &artist, &title),
+                spotify_id,
+                isrc,
+                artist,
+                title,
+                path: file.clone(),
+                content_hash: Self::content_hash(file),
+                size: Self::file_size(file),
+                modified: Self::modified(file),
+                recorded_at: Self::now(),
+            };
+            self.append(&entry)?;
+            self.insert(entry);
+            summary.recorded += 1;
+        }
+
+        self.reload()?;
+        Ok(summary)
+    }
+
+    /// Forget every entry, returning how many were removed.
+    /// Fails while another archive has the log open, since it would keep writing to the old file.
+    pub fn clear(&mut self) -> Result<usize> {
+        let path = &self.path;
+        let cleared = self.lock.exclusively(|| {
+            let removed = Self::latest(Self::read_log(path)?).len();
+            Self::write_snapshot(path, &[])?;
+            Ok(removed)
+        })?;
+        self.writer = Self::open_writer(&self.path)?;
+
+        let removed = cleared.ok_or_else(|| SpotifyDownloaderError::Unknown(
+            "The download archive is in use by another download; try again once it finishes".to_string(),
+        ))?;
+        self.reset(Vec::new());
+        Ok(removed)
+    }
+
+    /// All entries in the order they were first recorded
+    pub fn entries(&self) -> impl Iterator<Item = &ArchiveEntry> {
+        self.order.iter().filter_map(|key| self.entries.get(key))
+    }
+
+    /// Path of the archive log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn key_for(spotify_id: Option<&str>, isrc: Option<&str>, artist: &str, title: &str) -> String {
+        match (spotify_id, isrc) {
+            (Some(spotify_id), _) => format!("spotify:{}", spotify_id),
+            (None, Some(isrc)) => format!("isrc:{}", isrc.to_uppercase()),
+            (None, None) => format!("track:{} - {}", artist.to_lowercase(), title.to_lowercase()),
+        }
+    }
+
+    fn track_key(artist: &str, title: &str) -> Option<String> {
+        if artist.is_empty() || title.is_empty() {
+            return None;
+        }
+        Some(format!("{} - {}", artist.to_lowercase(), title.to_lowercase()))
+    }
+
+    fn insert(&mut self, entry: ArchiveEntry) {
+        if let Some(isrc) = &entry.isrc {
+            self.isrc_index.insert(isrc.to_uppercase(), entry.key.clone());
+        }
+        if entry.spotify_id.is_none() && entry.isrc.is_none() {
+            if let Some(track_key) = Self::track_key(&entry.artist, &entry.title) {
+                self.track_index.insert(track_key, entry.key.clone());
+            }
+        }
+        if !self.entries.contains_key(&entry.key) {
+            self.order.push(entry.key.clone());
+        }
+        self.entries.insert(entry.key.clone(), entry);
+    }
+
+    fn read_artist_title(file: &Path) -> (String, String) {
+        let Ok(tagged_file) = lofty::read_from_path(file) else {
+            return (String::new(), String::new());
+        };
+        let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) else {
+            return (String::new(), String::new());
+        };
+        (
+            tag.artist().map(|artist| artist.to_string()).unwrap_or_default(),
+            tag.title().map(|title| title.to_string()).unwrap_or_default(),
+        )
+    }
+
+    /// Whether a file still has the size and hash it was recorded with.
+    /// The size and modification time are checked first, the file is only hashed when it was touched.
+    fn is_unchanged(entry: &ArchiveEntry) -> bool {
+        if entry.size.is_some() && Self::file_size(&entry.path) != entry.size {
+            return false;
+        }
+        if entry.size.is_some() && entry.modified.is_some() && Self::modified(&entry.path) == entry.modified {
+            return true;
+        }
+        match &entry.content_hash {
+            Some(hash) => Self::content_hash(&entry.path).as_ref() == Some(hash),
+            None => true,
+        }
+    }
+
+    fn file_size(path: &Path) -> Option<u64> {
+        std::fs::metadata(path).ok().map(|metadata| metadata.len())
+    }
+
+    fn modified(path: &Path) -> Option<u128> {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        Some(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_nanos())
+    }
+
+    fn content_hash(path: &Path) -> Option<String> {
+        match Utils::calculate_file_hash(&path.to_path_buf()) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                println!("⚠️ Failed to hash {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn append(&mut self, entry: &ArchiveEntry) -> Result<()> {
+        writeln!(self.writer, "{}", serde_json::to_string(entry)?)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Replay the log, compacting it to one line per entry when no other archive has it open
+    fn reload(&mut self) -> Result<()> {
+        let path = &self.path;
+        let compacted = self.lock.exclusively(|| {
+            let entries = Self::latest(Self::read_log(path)?);
+            Self::write_snapshot(path, &entries)?;
+            Ok(entries)
+        })?;
+        self.writer = Self::open_writer(&self.path)?;
+
+        let entries = match compacted {
+            Some(entries) => entries,
+            None => Self::read_log(&self.path)?,
+        };
+        self.reset(entries);
+        Ok(())
+    }
+
+    fn reset(&mut self, entries: Vec<ArchiveEntry>) {
+        self.entries.clear();
+        self.order.clear();
+        self.isrc_index.clear();
+        self.track_index.clear();
+        for entry in entries {
+            self.insert(entry);
+        }
+    }
+
+    /// Last entry for each key, in the order the keys were first recorded
+    fn latest(entries: Vec<ArchiveEntry>) -> Vec<ArchiveEntry> {
+        let mut order = Vec::new();
+        let mut latest = HashMap::new();
+        for entry in entries {
+            if !latest.contains_key(&entry.key) {
+                order.push(entry.key.clone());
+            }
+            latest.insert(entry.key.clone(), entry);
+        }
+        order.into_iter().filter_map(|key| latest.remove(&key)).collect()
+    }
+
+    /// Rewrite the log with the given entries, replacing the old file atomically
+    fn write_snapshot(path: &Path, entries: &[ArchiveEntry]) -> Result<()> {
+        let temp_path = path.with_extension("jsonl.tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            for entry in entries {
+                writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Apply a change to a shared archive, logging instead of failing the download when it can't be saved
+pub fn record(archive: Option<&SharedArchive>, change: impl FnOnce(&mut DownloadArchive) -> Result<()>) {
+    let Some(archive) = archive else { return };
+    match archive.lock() {
+        Ok(mut archive) => {
+            if let Err(e) = change(&mut archive) {
+                println!("⚠️ Failed to update the download archive: {}", e);
+            }
+        }
+        Err(e) => println!("⚠️ Download archive unavailable: {}", e),
+    }
+}
+
+/// File of a track that is archived and still on disk
+pub fn check(archive: Option<&SharedArchive>, track: &TrackMetadata) -> Option<PathBuf> {
+    let entry = archive?.lock().ok()?.find(track).cloned()?;
+    DownloadArchive::verify(&entry)
+}
@@ -1,38 +1,76 @@
-use crate::downloader::{DownloadOptions, DownloadProgress, TrackMetadata};
+use crate::downloader::{BatchEvent, BatchProgress, DownloadEvent, DownloadOptions, DownloadProgress, TrackMetadata};
+use crate::downloader::loudness::{LoudnessAnalysis, LoudnessAnalyzer, ReplayGainInfo};
+use crate::downloader::format_selection::SelectedFormat;
+use crate::downloader::failure_report::{FailureDetails, FailureReport};
+use crate::downloader::metadata::MetadataEmbedder;
+use crate::downloader::trim::TrimReport;
+use crate::downloader::job_store::{self, EnqueueOutcome, JobStore, SharedJobStore};
+use crate::downloader::control::DownloadControl;
+use crate::downloader::pipeline::{DownloadPipeline, PipelineJob};
+use crate::downloader::services::{ServiceContext, SharedServices};
+use crate::downloader::archive::{self, SharedArchive};
+use crate::downloader::plan::{DownloadPlan, PlannedSource};
+use crate::downloader::youtube::SearchResult;
 use crate::errors::{Result, SpotifyDownloaderError};
 use std::path::PathBuf;
 use tokio::sync::{mpsc, Semaphore};
-use tokio::task::JoinHandle;
 use std::sync::Arc;
 
-/// Async download manager that handles concurrent downloads
+/// Async download manager that runs batches through the staged download pipeline
 pub struct AsyncDownloadManager {
     audio_downloader: crate::downloader::audio::AudioDownloader,
     semaphore: Arc<Semaphore>,
+    /// Concurrent network downloads in the pipeline
+    download_workers: usize,
     progress_sender: Option<mpsc::UnboundedSender<DownloadProgress>>,
+    /// Receives batch totals derived from the track events
+    batch_sender: Option<mpsc::UnboundedSender<BatchEvent>>,
+    job_store: Option<SharedJobStore>,
+    archive: Option<SharedArchive>,
+    /// Download tracks again even if the archive or job store says they are done
+    force: bool,
+    control: DownloadControl,
+    /// Clients and caches shared by every track this manager downloads
+    services: SharedServices,
 }
 
 /// Download task result
 #[derive(Debug, Clone)]
 pub struct DownloadTaskResult {
     pub track: TrackMetadata,
+    /// Options the track was downloaded with, so a retry uses them again
+    pub options: DownloadOptions,
     pub success: bool,
     pub output_path: Option<PathBuf>,
+    pub source_format: Option<SelectedFormat>,
+    pub trim: Option<TrimReport>,
     pub error: Option<String>,
+    /// The download was stopped before it finished and can be started again
+    pub cancelled: bool,
+    /// Stage, error kind and sources tried when the download failed in the pipeline
+    pub failure: Option<FailureDetails>,
+    /// Loudness measured while tagging, when ReplayGain is enabled
+    pub loudness: Option<LoudnessAnalysis>,
 }
 
 impl AsyncDownloadManager {
     /// Create a new async download manager
     pub fn new(max_concurrent: usize) -> Self {
-        // Get proxy-configured client
-        let client = crate::api::get_api_manager()
-            .map(|api_manager| api_manager.client().clone())
-            .unwrap_or_else(|_| reqwest::Client::new());
+        // Shares the proxy-configured client
+        let services = ServiceContext::shared();
+        crate::utils::bandwidth::BandwidthLimiter::global().set_pool_size(max_concurrent);
         
         Self {
-            audio_downloader: crate::downloader::audio::AudioDownloader::new_with_client(client),
+            audio_downloader: crate::downloader::audio::AudioDownloader::with_services(Arc::clone(&services)),
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            download_workers: max_concurrent,
             progress_sender: None,
+            batch_sender: None,
+            job_store: None,
+            archive: None,
+            force: false,
+            control: DownloadControl::new(),
+            services,
         }
     }
 
@@ -41,6 +79,38 @@ impl AsyncDownloadManager {
         self.progress_sender = Some(sender);
     }
 
+    /// Set the sender for batch totals, updated as tracks finish and bytes come in
+    pub fn set_batch_sender(&mut self, sender: mpsc::UnboundedSender<BatchEvent>) {
+        self.batch_sender = Some(sender);
+    }
+
+    /// Pause, resume and cancel every download of this manager through the control
+    pub fn set_control(&mut self, control: DownloadControl) {
+        self.audio_downloader.set_control(control.clone());
+        self.control = control;
+    }
+
+    /// Handle for pausing, resuming and cancelling this manager's downloads
+    #[allow(dead_code)]
+    pub fn control(&self) -> DownloadControl {
+        self.control.clone()
+    }
+
+    /// Record every download in a persistent queue so it can be resumed after a restart
+    pub fn set_job_store(&mut self, store: SharedJobStore) {
+        self.job_store = Some(store);
+    }
+
+    /// Skip tracks found in the download archive and record every finished download in it
+    pub fn set_archive(&mut self, archive: SharedArchive) {
+        self.archive = Some(archive);
+    }
+
+    /// Download every track again, ignoring the archive and completed jobs
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
     /// Download multiple tracks concurrently
     pub async fn download_tracks(
         &self,
@@ -48,123 +118,242 @@ impl AsyncDownloadManager {
         options: &DownloadOptions,
         config: &crate::config::Config,
     ) -> Result<Vec<DownloadTaskResult>> {
-        let mut handles: Vec<JoinHandle<DownloadTaskResult>> = Vec::new();
-
-        for track in tracks {
-            let semaphore = Arc::clone(&self.semaphore);
-            // Get proxy-configured client for each task
-            let client = crate::api::get_api_manager()
-                .map(|api_manager| api_manager.client().clone())
-                .unwrap_or_else(|_| reqwest::Client::new());
-            let audio_downloader = crate::downloader::audio::AudioDownloader::new_with_client(client);
-            let options = options.clone();
-            let progress_sender = self.progress_sender.clone();
-            let config = config.clone();
-
-            let handle = tokio::spawn(async move {
-                // Send "Queued" status when track is waiting for semaphore
-                if let Some(sender) = &progress_sender {
-                    let _ = sender.send(DownloadProgress {
-                        track_id: track.id.clone(),
-                        stage: crate::downloader::DownloadStage::Queued,
-                        progress: 0.0,
-                        message: "Queued for download...".to_string(),
-                    });
+        let jobs = tracks.into_iter()
+            .map(|track| (track, options.clone(), None))
+            .collect();
+        self.download_jobs(jobs, config).await
+    }
+
+    /// Download the tracks of a plan from the sources it chose, leaving out skipped entries.
+    /// Each track is written to its planned output path, and tracks whose existing file is still on disk are not downloaded again.
+    pub async fn download_plan(&self, plan: &DownloadPlan, config: &crate::config::Config) -> Result<Vec<DownloadTaskResult>> {
+        let mut results = Vec::new();
+        let mut jobs = Vec::new();
+        for entry in plan.entries.iter().filter(|entry| !entry.skip) {
+            let options = DownloadOptions {
+                output_path: Some(entry.output_path.clone()),
+                ..plan.options.clone()
+            };
+            let existing = entry.existing.as_ref().filter(|path| !self.force && path.exists());
+            if let Some(existing) = existing {
+                println!("⏭️ Already downloaded: {} - {} ({})", entry.track.artist, entry.track.title, existing.display());
+                results.push(DownloadTaskResult {
+                    track: entry.track.clone(),
+                    options,
+                    success: true,
+                    output_path: Some(existing.clone()),
+                    source_format: None,
+                    trim: None,
+                    error: None,
+                    cancelled: false,
+                    failure: None,
+                    loudness: None,
+                });
+                continue;
+            }
+
+            jobs.push((entry.track.clone(), options, entry.source.as_ref().map(PlannedSource::to_search_result)));
+        }
+        results.extend(self.download_jobs(jobs, config).await?);
+        Ok(results)
+    }
+
+    /// Download the tracks of a failure report again, with the options they failed with or `default_options`
+    pub async fn retry_failures(
+        &self,
+        report: &FailureReport,
+        default_options: &DownloadOptions,
+        config: &crate::config::Config,
+    ) -> Result<Vec<DownloadTaskResult>> {
+        let jobs = report.failures.iter()
+            .map(|failure| {
+                let options = failure.options.clone().unwrap_or_else(|| default_options.clone());
+                (failure.track.clone(), options, None)
+            })
+            .collect();
+        self.download_jobs(jobs, config).await
+    }
+
+    /// Run every unfinished job in the job store with the options it was queued with
+    pub async fn resume_jobs(&self, config: &crate::config::Config) -> Result<Vec<DownloadTaskResult>> {
+        let Some(store) = &self.job_store else {
+            return Ok(Vec::new());
+        };
+        let jobs: Vec<(TrackMetadata, DownloadOptions, Option<SearchResult>)> = store.lock()
+            .map_err(|e| SpotifyDownloaderError::Unknown(format!("Download queue unavailable: {}", e)))?
+            .unfinished()
+            .into_iter()
+            .map(|job| (job.track, job.options, None))
+            .collect();
+        println!("🔁 Resuming {} unfinished download(s)", jobs.len());
+        self.download_jobs(jobs, config).await
+    }
+
+    /// Download tracks through the pipeline, each with its own options and optionally a source chosen ahead of time
+    async fn download_jobs(
+        &self,
+        jobs: Vec<(TrackMetadata, DownloadOptions, Option<SearchResult>)>,
+        config: &crate::config::Config,
+    ) -> Result<Vec<DownloadTaskResult>> {
+        let mut results = Vec::new();
+
+        // Queue everything up front so an interrupted batch can be resumed as a whole
+        let mut queued = Vec::new();
+        for (track, options, source) in jobs {
+            let archived = if self.force { None } else { archive::check(self.archive.as_ref(), &track) };
+            if let Some(output_path) = archived {
+                println!("⏭️ Already in archive: {} - {} ({})", track.artist, track.title, output_path.display());
+                results.push(DownloadTaskResult {
+                    track,
+                    options,
+                    success: true,
+                    output_path: Some(output_path),
+                    source_format: None,
+                    trim: None,
+                    error: None,
+                    cancelled: false,
+                    failure: None,
+                    loudness: None,
+                });
+                continue;
+            }
+
+            let mut outcome = EnqueueOutcome::Queued;
+            let force = self.force;
+            job_store::record(self.job_store.as_ref(), |store| {
+                outcome = store.enqueue(&track, &options)?;
+                if force && matches!(outcome, EnqueueOutcome::AlreadyCompleted(_)) {
+                    store.mark_queued(&JobStore::job_id(&track, &options))?;
+                    outcome = EnqueueOutcome::Queued;
                 }
+                Ok(())
+            });
+            if let EnqueueOutcome::AlreadyCompleted(output_path) = outcome {
+                println!("⏭️ Already downloaded: {} - {}", track.artist, track.title);
+                results.push(DownloadTaskResult {
+                    track,
+                    options,
+                    success: true,
+                    output_path,
+                    source_format: None,
+                    trim: None,
+                    error: None,
+                    cancelled: false,
+                    failure: None,
+                    loudness: None,
+                });
+                continue;
+            }
+            queued.push((track, options, source));
+        }
 
-                let _permit = match semaphore.acquire().await {
-                    Ok(permit) => {
-                        // Send "Starting" status when track acquires semaphore and starts downloading
-                        if let Some(sender) = &progress_sender {
-                            let _ = sender.send(DownloadProgress {
-                                track_id: track.id.clone(),
-                                stage: crate::downloader::DownloadStage::SearchingSource,
-                                progress: 0.1,
-                                message: "Starting download...".to_string(),
-                            });
-                        }
-                        permit
-                    },
-                    Err(e) => {
-                        return DownloadTaskResult {
-                            track: track.clone(),
-                            success: false,
-                            output_path: None,
-                            error: Some(format!("Failed to acquire semaphore: {}", e)),
-                        };
-                    }
-                };
-
-                // Create a progress channel for this specific track
-                let (track_progress_tx, mut track_progress_rx) = mpsc::unbounded_channel::<DownloadProgress>();
-                let track_id = track.id.clone();
-                let progress_sender_clone = progress_sender.clone();
-
-                // Spawn progress monitoring task for this track
-                if let Some(global_sender) = progress_sender_clone {
-                    tokio::spawn(async move {
-                        while let Some(progress) = track_progress_rx.recv().await {
-                            let _ = global_sender.send(DownloadProgress {
-                                track_id: track_id.clone(),
-                                stage: progress.stage,
-                                progress: progress.progress,
-                                message: progress.message,
-                            });
-                        }
-                    });
+        // Events of every track are forwarded unchanged and folded into the batch totals
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel::<DownloadProgress>();
+        let progress_sender = self.progress_sender.clone();
+        let batch_sender = self.batch_sender.clone();
+        let mut batch = BatchProgress::new(queued.len());
+        tokio::spawn(async move {
+            while let Some(progress) = events_rx.recv().await {
+                if let (Some(batch_sender), Some(event)) = (&batch_sender, batch.apply(&progress)) {
+                    let _ = batch_sender.send(event);
+                }
+                if let Some(progress_sender) = &progress_sender {
+                    let _ = progress_sender.send(progress);
                 }
+            }
+        });
+
+        // Every track reports through its own channel so each new stage can be recorded in the job store
+        let mut pipeline_jobs = Vec::with_capacity(queued.len());
+        for (track, options, source) in queued {
+            let job_id = JobStore::job_id(&track, &options);
+            let (track_progress_tx, mut track_progress_rx) = mpsc::unbounded_channel::<DownloadProgress>();
+            let events_tx = events_tx.clone();
+            let stage_store = self.job_store.clone();
+            let stage_job_id = job_id.clone();
 
-                let result = {
-                    let mut audio_downloader = audio_downloader;
-                    audio_downloader
-                        .download_track(&track, &options, Some(track_progress_tx), &config)
-                        .await
-                };
-
-                match result {
-                    Ok(output_path) => DownloadTaskResult {
-                        track: track.clone(),
-                        success: true,
-                        output_path: Some(output_path),
-                        error: None,
-                    },
-                    Err(e) => DownloadTaskResult {
-                        track: track.clone(),
-                        success: false,
-                        output_path: None,
-                        error: Some(e.to_string()),
-                    },
+            tokio::spawn(async move {
+                while let Some(progress) = track_progress_rx.recv().await {
+                    job_store::record(stage_store.as_ref(), |store| store.update_stage(&stage_job_id, progress.stage()));
+                    let _ = events_tx.send(progress);
                 }
             });
 
-            handles.push(handle);
+            // Send "Queued" status while the track waits for the first stage
+            let _ = track_progress_tx.send(DownloadProgress::new(&track.id, DownloadEvent::Queued, 0.0, "Queued for download...".to_string()));
+
+            pipeline_jobs.push(PipelineJob {
+                track,
+                options,
+                job_id,
+                progress: Some(track_progress_tx),
+                source,
+                candidates: Vec::new(),
+                started_at: None,
+            });
         }
 
-        // Wait for all downloads to complete
-        let mut results = Vec::new();
-        for handle in handles {
-            match handle.await {
-                Ok(result) => results.push(result),
-                Err(e) => {
-                    // Task was cancelled or panicked
-                    results.push(DownloadTaskResult {
-                        track: TrackMetadata {
-                            title: "Unknown".to_string(),
-                            artist: "Unknown".to_string(),
-                            album: "Unknown".to_string(),
-                            ..Default::default()
-                        },
-                        success: false,
-                        output_path: None,
-                        error: Some(format!("Task failed: {}", e)),
-                    });
-                }
-            }
+        drop(events_tx);
+
+        let pipeline = DownloadPipeline::new(config.pipeline.clone(), self.download_workers, self.control.clone(), Arc::clone(&self.services))
+            .with_job_store(self.job_store.clone())
+            .with_archive(self.archive.clone());
+        results.extend(pipeline.run(pipeline_jobs, config).await);
+
+        if config.metadata_config.embed_replaygain {
+            self.apply_album_gain(&results).await;
         }
 
         Ok(results)
     }
 
+    /// Compute album ReplayGain for every album the batch downloaded completely, from the loudness measured while tagging.
+    /// A few tracks of an album, e.g. from a playlist, would give a gain that is wrong for the album, so they keep track gain only.
+    async fn apply_album_gain(&self, results: &[DownloadTaskResult]) {
+        type AlbumTracks<'a> = (Option<u32>, Vec<(&'a PathBuf, &'a LoudnessAnalysis)>);
+        let mut albums: std::collections::HashMap<(String, String), AlbumTracks> = std::collections::HashMap::new();
+        for result in results.iter().filter(|result| result.success) {
+            let (Some(path), Some(analysis)) = (&result.output_path, &result.loudness) else { continue };
+            if result.track.album.is_empty() {
+                continue;
+            }
+            let album_artist = result.track.album_artist.clone()
+                .unwrap_or_else(|| result.track.artist.clone());
+            let (total_tracks, tracks) = albums.entry((album_artist, result.track.album.clone())).or_default();
+            *total_tracks = total_tracks.or(result.track.total_tracks);
+            tracks.push((path, analysis));
+        }
+
+        let metadata_embedder = MetadataEmbedder::new();
+        for ((album_artist, album), (total_tracks, tracks)) in albums {
+            let Some(total_tracks) = total_tracks.filter(|total| *total > 1) else { continue };
+            if tracks.len() < total_tracks as usize {
+                println!(
+                    "💿 Skipping album gain for {} - {}: {} of {} tracks in this batch",
+                    album_artist, album, tracks.len(), total_tracks
+                );
+                continue;
+            }
+            println!("💿 Computing album gain for {} - {} ({} tracks)", album_artist, album, tracks.len());
+
+            let track_analyses: Vec<_> = tracks.iter().map(|(_, analysis)| (*analysis).clone()).collect();
+            let Some(album_analysis) = LoudnessAnalyzer::album_loudness(&track_analyses) else { continue };
+            println!(
+                "🔊 Album loudness: {:.2} LUFS, album gain: {:+.2} dB",
+                album_analysis.integrated_lufs,
+                album_analysis.gain_db()
+            );
+
+            for (path, analysis) in &tracks {
+                let replaygain = ReplayGainInfo::from_track(analysis).with_album(&album_analysis);
+                match metadata_embedder.embed_replaygain(path, &replaygain).await {
+                    Ok(()) => archive::record(self.archive.as_ref(), |archive| archive.refresh(path)),
+                    Err(e) => println!("⚠️ Failed to write album gain to {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+
     /// Download a single track (for backward compatibility)
     pub async fn download_single_track(
         &self,
@@ -187,15 +376,20 @@ impl AsyncDownloadManager {
 
 impl Clone for AsyncDownloadManager {
     fn clone(&self) -> Self {
-        // Get proxy-configured client for cloned instance
-        let client = crate::api::get_api_manager()
-            .map(|api_manager| api_manager.client().clone())
-            .unwrap_or_else(|_| reqwest::Client::new());
-        
+        let mut audio_downloader = self.audio_downloader.clone();
+        audio_downloader.set_control(self.control.clone());
+
         Self {
-            audio_downloader: crate::downloader::audio::AudioDownloader::new_with_client(client),
+            audio_downloader,
             semaphore: Arc::clone(&self.semaphore),
+            download_workers: self.download_workers,
             progress_sender: self.progress_sender.clone(),
+            batch_sender: self.batch_sender.clone(),
+            job_store: self.job_store.clone(),
+            archive: self.archive.clone(),
+            force: self.force,
+            control: self.control.clone(),
+            services: Arc::clone(&self.services),
         }
     }
 }
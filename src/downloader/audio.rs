@@ -1,24 +1,59 @@
-use crate::config::AudioFormat;
 use crate::downloader::{
-    DownloadOptions, DownloadProgress, DownloadStage, TrackMetadata,
+    AlbumMetadata, DownloadEvent, DownloadOptions, DownloadProgress, DownloadStage, TrackMetadata, TransferStats,
     youtube::YoutubeDownloader, soundcloud::SoundcloudDownloader, yt_dlp::YtDlpDownloader,
-    converter::AudioConverter, covers::CoverDownloader, metadata::MetadataEmbedder,
+    converter::{AudioConverter, ConversionMonitor, ConversionPlan, SourceCodec}, metadata::MetadataEmbedder,
+    api_wrapper::ApiWrapper, youtube::{DownloadedAudio, SearchResult},
+    format_selection::{FormatSelector, SelectedFormat},
+    loudness::{LoudnessAnalysis, LoudnessAnalyzer, ReplayGainInfo},
+    trim::{TrimPlanner, TrimReport},
+    album_split::AlbumSplitter,
+    control::DownloadControl,
+    services::{ServiceContext, SharedServices},
+    failure_report::SourceCandidate,
 };
 use crate::errors::{Result, SpotifyDownloaderError};
-use std::path::PathBuf;
-use std::collections::HashMap;
+use crate::config::{LossyToLosslessPolicy, Quality};
+use crate::utils::Utils;
+use crate::utils::retry::{RetryPolicy, RetryStage};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use reqwest::Client;
 
+/// A finished track download and the source stream it was made from
+#[derive(Debug, Clone)]
+pub struct TrackDownload {
+    pub path: PathBuf,
+    pub source_format: Option<SelectedFormat>,
+    pub trim: Option<TrimReport>,
+}
+
+/// Upload picked for a track and the source stream to fetch from it
+#[derive(Debug, Clone)]
+pub struct SourceMatch {
+    pub result: SearchResult,
+    pub selected_format: Option<SelectedFormat>,
+    /// Match confidence from 0.0 to 1.0
+    pub score: f32,
+}
+
+/// A converted track and what trimming removed before conversion
+#[derive(Debug, Clone)]
+pub struct TranscodedTrack {
+    pub path: PathBuf,
+    pub trim: Option<TrimReport>,
+}
+
 /// Main audio downloader that orchestrates different download strategies
 pub struct AudioDownloader {
     youtube_downloader: YoutubeDownloader,
     soundcloud_downloader: SoundcloudDownloader,
     ytdlp_downloader: YtDlpDownloader,
     converter: AudioConverter,
-    cover_downloader: CoverDownloader,
     metadata_embedder: MetadataEmbedder,
-    search_cache: HashMap<String, Vec<crate::downloader::youtube::SearchResult>>,
+    /// Clients and caches shared with every other downloader of the batch
+    services: SharedServices,
+    control: DownloadControl,
 }
 
 impl AudioDownloader {
@@ -29,25 +64,37 @@ impl AudioDownloader {
             soundcloud_downloader: SoundcloudDownloader::new(),
             ytdlp_downloader: YtDlpDownloader::new(),
             converter: AudioConverter::new(),
-            cover_downloader: CoverDownloader::new(),
             metadata_embedder: MetadataEmbedder::new(),
-            search_cache: HashMap::new(),
+            services: Arc::new(ServiceContext::new(Client::new())),
+            control: DownloadControl::new(),
         }
     }
 
     /// Create a new audio downloader with a custom HTTP client (for proxy support)
     pub fn new_with_client(client: Client) -> Self {
+        Self::with_services(Arc::new(ServiceContext::new(client)))
+    }
+
+    /// Create an audio downloader that shares clients and caches with other downloaders
+    pub fn with_services(services: SharedServices) -> Self {
         Self {
             youtube_downloader: YoutubeDownloader::new(),
             soundcloud_downloader: SoundcloudDownloader::new(),
             ytdlp_downloader: YtDlpDownloader::new(),
             converter: AudioConverter::new(),
-            cover_downloader: CoverDownloader::new_with_client(client),
             metadata_embedder: MetadataEmbedder::new(),
-            search_cache: HashMap::new(),
+            services,
+            control: DownloadControl::new(),
         }
     }
 
+    /// Pause and cancel this downloader's work, including its yt-dlp and ffmpeg processes
+    pub fn set_control(&mut self, control: DownloadControl) {
+        self.youtube_downloader.set_control(control.clone());
+        self.converter = AudioConverter::with_control(control.clone());
+        self.control = control;
+    }
+
     /// Download audio for a track with progress reporting
     pub async fn download_track(
         &mut self,
@@ -56,263 +103,357 @@ impl AudioDownloader {
         progress_sender: Option<mpsc::UnboundedSender<DownloadProgress>>,
         config: &crate::config::Config,
     ) -> Result<PathBuf> {
+        self.download_track_detailed(track, options, progress_sender, config)
+            .await
+            .map(|download| download.path)
+    }
+
+    /// Download audio for a track, also reporting which source stream was used
+    pub async fn download_track_detailed(
+        &mut self,
+        track: &TrackMetadata,
+        options: &DownloadOptions,
+        progress_sender: Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<TrackDownload> {
+        let failed = |stage: DownloadStage| {
+            let progress_sender = progress_sender.clone();
+            let track_id = track.id.clone();
+            move |e| Self::report_failure(&progress_sender, &track_id, stage, e)
+        };
+        let track = self.resolve_metadata(track, &progress_sender, config).await
+            .map_err(failed(DownloadStage::FetchingMetadata))?;
+        let source = self.find_source(&track, options, &progress_sender, config, &mut Vec::new()).await
+            .map_err(failed(DownloadStage::SearchingSource))?;
+        let downloaded = self.fetch_source(&track, &source, options, &progress_sender, config).await
+            .map_err(failed(DownloadStage::DownloadingAudio))?;
+        let transcoded = self.transcode(&track, &downloaded, options, &progress_sender, config).await
+            .map_err(failed(DownloadStage::ConvertingAudio))?;
+        self.finish_track(&track, &transcoded.path, options, &progress_sender, config).await
+            .map_err(failed(DownloadStage::EmbeddingMetadata))?;
+
+        Ok(TrackDownload {
+            path: transcoded.path,
+            source_format: downloaded.format,
+            trim: transcoded.trim,
+        })
+    }
+
+    /// Metadata stage: optionally enrich the track with Last.fm tags and matching hints
+    pub async fn resolve_metadata(
+        &self,
+        track: &TrackMetadata,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<TrackMetadata> {
+        self.control.checkpoint().await?;
         println!("🎵 Starting download for: {} - {}", track.artist, track.title);
-        
+
+        if !config.metadata_config.enrich_from_lastfm {
+            return Ok(track.clone());
+        }
         self.send_progress(
-            &progress_sender,
+            progress_sender,
+            &track.id,
+            DownloadStage::FetchingMetadata,
+            0.05,
+            "Fetching Last.fm metadata...".to_string(),
+        );
+        Ok(self.enrich_with_lastfm(track, config).await)
+    }
+
+    /// Search stage: find the best matching upload and pick its source stream
+    pub async fn find_source(
+        &self,
+        track: &TrackMetadata,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+        candidates: &mut Vec<SourceCandidate>,
+    ) -> Result<SourceMatch> {
+        self.control.checkpoint().await?;
+        self.send_progress(
+            progress_sender,
             &track.id,
             DownloadStage::SearchingSource,
             0.1,
             "Searching for audio source...".to_string(),
         );
 
+        let (best_match, score) = self.match_source(track, config, candidates).await?;
+        self.select_source(track, best_match, score, options, progress_sender, config).await
+    }
+
+    /// Search stage for a source chosen ahead of time, such as one from a download plan
+    pub async fn use_source(
+        &self,
+        track: &TrackMetadata,
+        source: SearchResult,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<SourceMatch> {
+        self.control.checkpoint().await?;
+        self.send_progress(
+            progress_sender,
+            &track.id,
+            DownloadStage::SearchingSource,
+            0.1,
+            "Using the planned audio source...".to_string(),
+        );
+
+        let score = Self::match_score(&source, track);
+        self.select_source(track, source, score, options, progress_sender, config).await
+    }
+
+    /// Search for a track and pick the best matching upload with its match score, without downloading anything.
+    /// Every search result is added to `candidates` with its score.
+    pub async fn match_source(
+        &self,
+        track: &TrackMetadata,
+        config: &crate::config::Config,
+        candidates: &mut Vec<SourceCandidate>,
+    ) -> Result<(SearchResult, f32)> {
         // Search for the track on different platforms (without album name for better results)
         let search_query = format!("{} {}", track.artist, track.title);
         println!("🔍 Searching for: {}", search_query);
         
         // Check cache first
-        let search_results = if let Some(cached_results) = self.search_cache.get(&search_query) {
+        let search_results = if let Some(cached_results) = self.services.cached_matches(&search_query) {
             println!("📋 Using cached search results");
-            cached_results.clone()
+            cached_results
         } else {
             // Try optimized search strategy: ytsearch1 -> ytsearch5 -> scsearch1 -> scsearch5
-            let search_results = self.youtube_downloader.search_optimized(&search_query, config).await;
+            let search_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Search);
+            let youtube_downloader = &self.youtube_downloader;
+            let search_results = Utils::retry_with_backoff("Search", &search_policy, &self.control.cancel_token(), || {
+                youtube_downloader.search_optimized(&search_query, config)
+            }).await;
             
             match search_results {
                 Ok(results) if !results.is_empty() => {
                     // Cache the results for future use
-                    self.search_cache.insert(search_query.clone(), results.clone());
+                    self.services.store_matches(&search_query, results.clone());
                     results
                 },
                 Ok(_) => {
                     // Search succeeded but returned no results
                     println!("❌ No results found for: {} - {}", track.artist, track.title);
-                    self.send_progress(
-                        &progress_sender,
-                        &track.id,
-                        DownloadStage::Error,
-                        0.0,
-                        "No results found for this track".to_string(),
-                    );
-                    return Err(SpotifyDownloaderError::Download("No results found".to_string()));
+                    return Err(SpotifyDownloaderError::NoMatch(format!("No results found for {}", search_query)));
                 }
                 Err(e) => {
-                    // Search failed
+                    // Search failed, keep the error so its classification survives
                     println!("❌ Search failed for: {} - {}: {}", track.artist, track.title, e);
-                    self.send_progress(
-                        &progress_sender,
-                        &track.id,
-                        DownloadStage::Error,
-                        0.0,
-                        format!("Search failed: {}", e),
-                    );
-                    return Err(SpotifyDownloaderError::Download(format!("Search failed: {}", e)));
+                    return Err(e);
                 }
             }
         };
         
-        if !search_results.is_empty() {
-            let best_match = &search_results[0]; // Take the first (best) result
-            println!("✅ Found YouTube source: {}", best_match.platform);
-            
-            self.send_progress(
-                &progress_sender,
-                &track.id,
-                DownloadStage::DownloadingAudio,
-                0.3,
-                format!("Found {} source, downloading...", best_match.platform),
-            );
+        let best_match = self.select_best_match(&search_results, track).clone();
+        let score = Self::match_score(&best_match, track);
+        candidates.extend(search_results.iter().map(|result| {
+            SourceCandidate::new(result, Some(Self::match_score(result, track)), result.url == best_match.url)
+        }));
+        println!("✅ Found {} source: {} (match {:.0}%)", best_match.platform, best_match.title, score * 100.0);
+        Ok((best_match, score))
+    }
 
-            let output_path = self.get_output_path(track, options);
-            
-            // Create progress callback for download
-            let progress_sender_clone = progress_sender.clone();
-            let track_id_clone = track.id.clone();
-            let progress_callback = Box::new(move |progress: f32| {
-                // Send progress updates during download
-                if let Some(sender) = &progress_sender_clone {
-                    let _ = sender.send(DownloadProgress {
-                        track_id: track_id_clone.clone(),
-                        stage: DownloadStage::DownloadingAudio,
-                        progress: 0.3 + (progress * 0.3), // 30% to 60%
-                        message: format!("Downloading... {:.1}%", progress * 100.0),
-                    });
-                }
-            });
+    /// Pick the source stream of the chosen upload and report the match
+    async fn select_source(
+        &self,
+        track: &TrackMetadata,
+        best_match: SearchResult,
+        score: f32,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<SourceMatch> {
+        // Pick the source stream from the formats yt-dlp offers, fetching them if the search had none
+        let formats = if best_match.formats.is_empty() {
+            let metadata_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Metadata);
+            let youtube_downloader = &self.youtube_downloader;
+            Utils::retry_with_backoff("Format lookup", &metadata_policy, &self.control.cancel_token(), || {
+                youtube_downloader.get_info(&best_match.url)
+            }).await
+                .ok()
+                .and_then(|info| info.formats)
+                .unwrap_or_default()
+        } else {
+            best_match.formats.clone()
+        };
+        let selected_format = FormatSelector::new(config.format_selection.clone())
+            .select(&formats, options.format);
+        match &selected_format {
+            Some(format) => println!("🎚️ Selected source format: {}", format),
+            None => println!("🎚️ No format list available, letting yt-dlp choose"),
+        }
+        // The codec is known now, so a refused lossy-to-lossless conversion fails before anything is downloaded
+        if matches!(config.conversion_config.lossy_to_lossless, LossyToLosslessPolicy::Refuse) {
+            let source_codec = selected_format.as_ref()
+                .and_then(|format| format.acodec.as_deref())
+                .and_then(SourceCodec::from_acodec);
+            Self::check_lossy_to_lossless(source_codec.as_ref(), options, config)?;
+        }
+        self.send_progress(
+            progress_sender,
+            &track.id,
+            DownloadEvent::Resolved {
+                source: best_match.platform.clone(),
+                title: best_match.title.clone(),
+                url: best_match.url.clone(),
+                score,
+            },
+            0.2,
+            format!("Found {} source: {}", best_match.platform, best_match.title),
+        );
 
-            self.youtube_downloader.download_audio(
-                &best_match.url,
+        Ok(SourceMatch { result: best_match, selected_format, score })
+    }
+
+    /// Download stage: fetch the source stream untouched so it is encoded at most once
+    pub async fn fetch_source(
+        &self,
+        track: &TrackMetadata,
+        source: &SourceMatch,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<DownloadedAudio> {
+        self.control.checkpoint().await?;
+        self.send_progress(
+            progress_sender,
+            &track.id,
+            DownloadStage::DownloadingAudio,
+            0.3,
+            format!("Found {} source ({:.0}% match), downloading...", source.result.platform, source.score * 100.0),
+        );
+
+        let output_path = self.get_output_path(track, options);
+        
+        // Create progress callback for download
+        let progress_sender_clone = progress_sender.clone();
+        let track_id_clone = track.id.clone();
+        let progress_callback = std::sync::Arc::new(move |stats: TransferStats| {
+            // Send progress updates during download
+            if let Some(sender) = &progress_sender_clone {
+                let progress = stats.fraction().unwrap_or(0.0);
+                let message = format!("Downloading... {:.1}% ({})", progress * 100.0, stats.summary());
+                // 30% to 60%
+                let _ = sender.send(DownloadProgress::new(&track_id_clone, DownloadEvent::Downloading(stats), 0.3 + (progress * 0.3), message));
+            }
+        });
+
+        let download_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Download);
+        let youtube_downloader = &self.youtube_downloader;
+        Utils::retry_with_backoff("Download", &download_policy, &self.control.cancel_token(), || {
+            youtube_downloader.download_source_audio(
+                &source.result.url,
                 &output_path,
-                options.format,
-                options.bitrate,
-                Some(progress_callback),
+                source.selected_format.as_ref(),
+                Some(Self::boxed_callback(&progress_callback)),
                 config,
-            ).await?;
+            )
+        }).await
+    }
 
+    /// Transcoding stage: trim silence and non-music sections, then convert to the requested format and quality
+    pub async fn transcode(
+        &self,
+        track: &TrackMetadata,
+        downloaded: &DownloadedAudio,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<TranscodedTrack> {
+        // Cut silence and non-music sections before anything gets encoded
+        Self::discard_on_cancel(self.control.checkpoint().await, &downloaded.path)?;
+        let trim_report = if options.trim.enabled {
             self.send_progress(
-                &progress_sender,
+                progress_sender,
                 &track.id,
-                DownloadStage::ConvertingAudio,
+                DownloadStage::TrimmingAudio,
                 0.6,
-                "Converting audio format...".to_string(),
+                "Trimming silence...".to_string(),
             );
+            Self::discard_on_cancel(self.trim_audio(track, downloaded, options, progress_sender, config).await, &downloaded.path)?
+        } else {
+            None
+        };
 
-            // Convert to desired format and bitrate
-            let converted_path = self.convert_audio(&output_path, options).await?;
-
-            // Download cover art and lyrics in parallel if requested (for embedding only)
-            let (cover_art_data, lyrics_data) = if options.download_cover || options.download_lyrics {
-                self.send_progress(
-                    &progress_sender,
-                    &track.id,
-                    DownloadStage::DownloadingCover,
-                    0.8,
-                    "Downloading cover art and lyrics for embedding...".to_string(),
-                );
-                
-                // Create futures for parallel execution
-                let cover_future = if options.download_cover {
-                    println!("🖼️ Downloading cover art for: {} - {}", track.artist, track.title);
-                    Some(self.cover_downloader.download_cover_art_data(
-                        track,
-                        options.cover_width,
-                        options.cover_height,
-                        &options.cover_format,
-                    ))
-                } else {
-                    println!("⏭️ Cover art download disabled in settings");
-                    None
-                };
-                
-                let lyrics_future = if options.download_lyrics {
-                    // Get proxy-configured client for lyrics downloader
-                    let client = crate::api::get_api_manager()
-                        .map(|api_manager| api_manager.client().clone())
-                        .unwrap_or_else(|_| reqwest::Client::new());
-                    let track_clone = track.clone();
-                    Some(async move {
-                        let lyrics_downloader = crate::lyrics::LyricsDownloader::new_with_client(client);
-                        lyrics_downloader.download_lyrics_for_embedding(&track_clone).await
-                    })
-                } else {
-                    None
-                };
-                
-                // Execute both futures in parallel
-                let (cover_result, lyrics_result) = tokio::join!(
-                    async {
-                        if let Some(future) = cover_future {
-                            match future.await {
-                                Ok(data) => {
-                                    println!("✅ Cover art downloaded successfully: {} bytes", data.len());
-                                    Some(data)
-                                },
-                                Err(e) => {
-                                    println!("❌ Failed to download cover art: {}", e);
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    },
-                    async {
-                        if let Some(future) = lyrics_future {
-                            match future.await {
-                                Ok(result) => {
-                                    println!("✅ Lyrics downloaded successfully for embedding");
-                                    Some(result)
-                                }
-                                Err(e) => {
-                                    println!("❌ Failed to download lyrics: {}", e);
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    }
-                );
-                
-                (cover_result, lyrics_result)
-            } else {
-                (None, None)
-            };
+        self.send_progress(
+            progress_sender,
+            &track.id,
+            DownloadEvent::Converting { percent: 0.0 },
+            0.6,
+            "Converting audio format...".to_string(),
+        );
 
-            // Embed metadata if requested
-            if options.embed_metadata {
-                self.send_progress(
-                    &progress_sender,
-                    &track.id,
-                    DownloadStage::EmbeddingMetadata,
-                    0.9,
-                    "Embedding metadata...".to_string(),
-                );
-                
-                self.metadata_embedder.embed_metadata(
-                    &converted_path,
-                    track,
-                    cover_art_data.as_ref(),
-                    lyrics_data.as_ref(),
-                    options,
-                ).await?;
-            }
+        // Convert to desired format and quality
+        Self::discard_on_cancel(self.control.checkpoint().await, &downloaded.path)?;
+        let converted_path = Self::discard_on_cancel(
+            self.convert_audio(track, downloaded, options, progress_sender, config).await,
+            &downloaded.path,
+        )?;
 
-            // Save cover art to covers/ folder if we have cover art data
-            if options.download_cover && cover_art_data.is_some() {
-                self.send_progress(
-                    &progress_sender,
-                    &track.id,
-                    DownloadStage::DownloadingCover,
-                    0.95,
-                    "Saving cover art to covers folder...".to_string(),
-                );
-                
-                // Save the cover art data we already downloaded
-                match self.save_cover_art_to_folder(
-                    track,
-                    &options.output_dir,
-                    cover_art_data.as_ref().unwrap(),
-                    &options.cover_format,
-                ).await {
-                    Ok(cover_path) => {
-                        println!("✅ Cover art saved to: {}", cover_path.display());
-                    }
-                    Err(e) => {
-                        println!("⚠️ Failed to save cover art to folder: {}", e);
-                        // Don't fail the entire download for this
-                    }
-                }
-            }
+        Ok(TranscodedTrack { path: converted_path, trim: trim_report })
+    }
 
-            println!("🎉 Download completed successfully: {} - {}", track.artist, track.title);
-            
+    /// Tagging stage: measure loudness, then embed metadata, lyrics and cover art.
+    /// Returns the loudness analysis so album gain can reuse it.
+    pub async fn finish_track(
+        &self,
+        track: &TrackMetadata,
+        path: &PathBuf,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<Option<LoudnessAnalysis>> {
+        // Measure loudness on the final file so ReplayGain matches what gets played
+        let loudness = if config.metadata_config.embed_replaygain {
             self.send_progress(
-                &progress_sender,
+                progress_sender,
                 &track.id,
-                DownloadStage::Completed,
-                1.0,
-                "Download completed successfully!".to_string(),
+                DownloadStage::AnalyzingLoudness,
+                0.7,
+                "Analyzing loudness...".to_string(),
             );
-
-            return Ok(converted_path);
+            self.analyze_loudness(path).await
         } else {
-            // No search results found
-            println!("❌ No search results found for: {} - {}", track.artist, track.title);
-            self.send_progress(
-                &progress_sender,
-                &track.id,
-                DownloadStage::Error,
-                0.0,
-                "No search results found for this track".to_string(),
-            );
-        }
+            None
+        };
+
+        self.control.checkpoint().await?;
+        self.tag_track(
+            track,
+            path,
+            options,
+            progress_sender,
+            loudness.as_ref().map(ReplayGainInfo::from_track),
+        ).await?;
+        self.send_progress(progress_sender, &track.id, DownloadEvent::Tagged, 0.98, "Tags written".to_string());
+
+        println!("🎉 Download completed successfully: {} - {}", track.artist, track.title);
+        
+        self.send_completed(progress_sender, &track.id, path);
+        Ok(loudness)
+    }
+
+    /// SoundCloud and plain yt-dlp fallbacks for a track the search stage can't match.
+    /// Not wired into the pipeline while the SoundCloud download is unfinished.
+    #[allow(dead_code)]
+    async fn download_from_fallback_sources(
+        &self,
+        track: &TrackMetadata,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+    ) -> Result<TrackDownload> {
+        let search_query = format!("{} {}", track.artist, track.title);
 
         // Try SoundCloud as fallback
         if let Ok(soundcloud_results) = self.soundcloud_downloader.search_tracks(&search_query).await {
             if !soundcloud_results.is_empty() {
                 self.send_progress(
-                    &progress_sender,
+                    progress_sender,
                     &track.id,
                     DownloadStage::DownloadingAudio,
                     0.3,
@@ -323,21 +464,21 @@ impl AudioDownloader {
                 // TODO: Implement SoundCloud download
                 
                 self.send_progress(
-                    &progress_sender,
+                    progress_sender,
                     &track.id,
                     DownloadStage::Completed,
                     1.0,
                     "Download completed successfully!".to_string(),
                 );
 
-                return Ok(output_path);
+                return Ok(TrackDownload { path: output_path, source_format: None, trim: None });
             }
         }
 
         // Try yt-dlp as final fallback
         if self.ytdlp_downloader.is_available().await {
             self.send_progress(
-                &progress_sender,
+                progress_sender,
                 &track.id,
                 DownloadStage::DownloadingAudio,
                 0.3,
@@ -345,36 +486,482 @@ impl AudioDownloader {
             );
 
             let output_path = self.get_output_path(track, options);
-            let format_str = match options.format {
-                AudioFormat::Mp3 => "mp3",
-                AudioFormat::M4a => "m4a",
-                AudioFormat::Flac => "flac",
-                AudioFormat::Wav => "wav",
-            };
+            let format_str = if options.quality == Quality::Original { "best" } else { options.format.yt_dlp_format() };
 
             self.ytdlp_downloader.download_audio(
                 &search_query,
                 &output_path,
                 format_str,
-                options.bitrate.as_u32(),
+                options.quality,
             ).await?;
 
             self.send_progress(
-                &progress_sender,
+                progress_sender,
                 &track.id,
                 DownloadStage::Completed,
                 1.0,
                 "Download completed successfully!".to_string(),
             );
 
-            return Ok(output_path);
+            return Ok(TrackDownload { path: output_path, source_format: None, trim: None });
         }
 
         Err(SpotifyDownloaderError::Download("No audio source found".to_string()))
     }
 
+    /// Download an album from a single full-album upload, cut into tagged tracks at its chapters
+    pub async fn download_album_split(
+        &mut self,
+        album: &AlbumMetadata,
+        options: &DownloadOptions,
+        progress_sender: Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<Vec<TrackDownload>> {
+        println!("💿 Starting album mode for: {} - {} ({} tracks)", album.artist, album.name, album.tracks.len());
+        if options.normalization.enabled || options.trim.enabled {
+            println!("⚠️ Normalization and trimming are not applied in album mode");
+        }
+
+        self.send_progress(
+            &progress_sender,
+            &album.id,
+            DownloadStage::SearchingSource,
+            0.1,
+            "Searching for a full album upload...".to_string(),
+        );
+
+        let album_secs: f64 = album.tracks.iter().map(|track| track.duration_ms as f64 / 1000.0).sum();
+        let splitter = AlbumSplitter::new(config.album_split.clone());
+        let cancel_token = self.control.cancel_token();
+        let youtube_downloader = &self.youtube_downloader;
+        let search_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Search);
+        let candidates = Utils::retry_with_backoff("Album search", &search_policy, &cancel_token, || {
+            youtube_downloader.search_full_album(&album.artist, &album.name, album_secs as u32, config)
+        }).await?;
+
+        // Take the first upload whose chapters (or length) line up with the track list
+        let metadata_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Metadata);
+        let mut source = None;
+        for candidate in candidates.iter().take(3) {
+            let info = match Utils::retry_with_backoff("Album lookup", &metadata_policy, &cancel_token, || {
+                youtube_downloader.get_info(&candidate.url)
+            }).await {
+                Err(e @ SpotifyDownloaderError::Cancelled(_)) => return Err(e),
+                Ok(info) => info,
+                Err(e) => {
+                    println!("⚠️ Skipping {}: {}", candidate.title, e);
+                    continue;
+                }
+            };
+            let duration_secs = info.duration.or(candidate.duration).unwrap_or(0) as f64;
+            match splitter.plan(AlbumSplitter::chapters_from_info(&info), &album.tracks, duration_secs) {
+                Ok(plan) => {
+                    println!("✅ Using {} ({}), split by {}", candidate.title, candidate.url, plan.source);
+                    source = Some((info, plan));
+                    break;
+                }
+                Err(e) => println!("⚠️ Skipping {}: {}", candidate.title, e),
+            }
+        }
+        let Some((info, mut plan)) = source else {
+            let error = SpotifyDownloaderError::NoMatch(format!("No full album upload matches {} - {}", album.artist, album.name));
+            return Err(Self::report_failure(&progress_sender, &album.id, DownloadStage::SearchingSource, error));
+        };
+
+        self.send_progress(
+            &progress_sender,
+            &album.id,
+            DownloadStage::DownloadingAudio,
+            0.2,
+            "Downloading full album...".to_string(),
+        );
+
+        let selected_format = FormatSelector::new(config.format_selection.clone())
+            .select(info.formats.as_deref().unwrap_or_default(), options.format);
+        if let Some(format) = &selected_format {
+            println!("🎚️ Selected source format: {}", format);
+        }
+
+        let progress_sender_clone = progress_sender.clone();
+        let album_id = album.id.clone();
+        let progress_callback = std::sync::Arc::new(move |stats: TransferStats| {
+            if let Some(sender) = &progress_sender_clone {
+                let progress = stats.fraction().unwrap_or(0.0);
+                let message = format!("Downloading album... {:.1}% ({})", progress * 100.0, stats.summary());
+                // 20% to 50%
+                let _ = sender.send(DownloadProgress::new(&album_id, DownloadEvent::Downloading(stats), 0.2 + (progress * 0.3), message));
+            }
+        });
+
+        let mut source_path = options.output_dir.join("temp");
+        source_path.push(format!("album_{}", sanitize_filename(&album.id)));
+        self.control.checkpoint().await?;
+        let download_policy = RetryPolicy::for_stage(&config.retry, RetryStage::Download);
+        let downloaded = Utils::retry_with_backoff("Album download", &download_policy, &cancel_token, || {
+            youtube_downloader.download_source_audio(
+                &info.webpage_url,
+                &source_path,
+                selected_format.as_ref(),
+                Some(Self::boxed_callback(&progress_callback)),
+                config,
+            )
+        }).await?;
+
+        let source_codec = downloaded.source_codec.clone().or_else(|| SourceCodec::probe(&downloaded.path));
+        if let Err(e) = Self::check_lossy_to_lossless(source_codec.as_ref(), options, config) {
+            let _ = std::fs::remove_file(&downloaded.path);
+            return Err(e);
+        }
+
+        let monitor = ConversionMonitor {
+            duration_ms: None,
+            timeout: config.conversion_config.timeout(),
+            progress_callback: None,
+        };
+
+        // Chapter and description timestamps are only accurate to the second, settle them in the gaps
+        if config.album_split.snap_window_secs > 0.0 {
+            self.send_progress(&progress_sender, &album.id, DownloadStage::TrimmingAudio, 0.5, "Finding track gaps...".to_string());
+            match self.converter.detect_silence(
+                &downloaded.path,
+                config.trim.silence_threshold_db,
+                config.trim.min_silence_secs,
+                &monitor,
+            ).await {
+                Ok((silences, _)) => splitter.snap_to_silence(&mut plan, &silences),
+                Err(e @ SpotifyDownloaderError::Cancelled(_)) => return Self::discard_on_cancel(Err(e), &downloaded.path),
+                Err(e) => println!("⚠️ Keeping unsnapped boundaries: {}", e),
+            }
+        }
+
+        for segment in &plan.segments {
+            let track = &album.tracks[segment.track_index];
+            println!("🎼 {:02}. {} {:.2}s–{:.2}s ({:.2}s, Spotify {:.2}s)",
+                     segment.track_index + 1, track.title, segment.start_secs, segment.end_secs,
+                     segment.duration_secs(), track.duration_ms as f64 / 1000.0);
+        }
+
+        // Cut every track before tagging so album gain can be computed over all of them
+        let mut track_paths = Vec::with_capacity(plan.segments.len());
+        for segment in &plan.segments {
+            let track = &album.tracks[segment.track_index];
+            Self::discard_on_cancel(self.control.checkpoint().await, &downloaded.path)?;
+            self.send_progress(
+                &progress_sender,
+                &track.id,
+                DownloadStage::ConvertingAudio,
+                0.6,
+                format!("Cutting track {} from the album...", segment.track_index + 1),
+            );
+
+            let mut output_path = self.get_output_path(track, options);
+            let segment_monitor = ConversionMonitor {
+                duration_ms: Some((segment.duration_secs() * 1000.0) as u64),
+                ..monitor
+            };
+            let result = if options.quality == Quality::Original {
+                // Stream copies can only cut on packet boundaries
+                let extension = downloaded.path.extension().and_then(|ext| ext.to_str()).unwrap_or("webm");
+                output_path.set_extension(extension);
+                self.converter.cut_audio(&downloaded.path, &output_path, segment.start_secs, segment.end_secs, &segment_monitor).await
+            } else {
+                self.converter.extract_segment(
+                    &downloaded.path,
+                    &output_path,
+                    segment,
+                    options.format,
+                    options.quality,
+                    &segment_monitor,
+                ).await
+            };
+            if let Err(e) = result {
+                let _ = std::fs::remove_file(&downloaded.path);
+                return Err(Self::report_failure(&progress_sender, &track.id, DownloadStage::ConvertingAudio, e));
+            }
+            track_paths.push(output_path);
+        }
+
+        if let Err(e) = std::fs::remove_file(&downloaded.path) {
+            println!("⚠️ Failed to remove album source {}: {}", downloaded.path.display(), e);
+        }
+
+        let analyses = if config.metadata_config.embed_replaygain {
+            let mut analyses = Vec::with_capacity(track_paths.len());
+            for path in &track_paths {
+                analyses.push(self.analyze_loudness(path).await);
+            }
+            analyses
+        } else {
+            vec![None; track_paths.len()]
+        };
+        let track_analyses: Vec<LoudnessAnalysis> = analyses.iter().flatten().cloned().collect();
+        let album_analysis = LoudnessAnalyzer::album_loudness(&track_analyses);
+
+        let mut downloads = Vec::with_capacity(track_paths.len());
+        for ((segment, path), analysis) in plan.segments.iter().zip(track_paths).zip(analyses) {
+            let track = &album.tracks[segment.track_index];
+            let replaygain = analysis.map(|analysis| {
+                let replaygain = ReplayGainInfo::from_track(&analysis);
+                match &album_analysis {
+                    Some(album_analysis) => replaygain.with_album(album_analysis),
+                    None => replaygain,
+                }
+            });
+            self.tag_track(track, &path, options, &progress_sender, replaygain).await?;
+            self.send_progress(&progress_sender, &track.id, DownloadEvent::Tagged, 0.98, "Tags written".to_string());
+            self.send_completed(&progress_sender, &track.id, &path);
+            downloads.push(TrackDownload {
+                path,
+                source_format: downloaded.format.clone(),
+                trim: None,
+            });
+        }
+
+        println!("🎉 Album split into {} tracks: {} - {}", downloads.len(), album.artist, album.name);
+        Ok(downloads)
+    }
+
+    /// Fetch cover art and lyrics, embed them with the track metadata and ReplayGain, and save the cover
+    async fn tag_track(
+        &self,
+        track: &TrackMetadata,
+        path: &PathBuf,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        replaygain: Option<ReplayGainInfo>,
+    ) -> Result<()> {
+        // Download cover art and lyrics in parallel if requested (for embedding only)
+        let (cover_art_data, lyrics_data) = if options.download_cover || options.download_lyrics {
+            self.send_progress(
+                progress_sender,
+                &track.id,
+                DownloadStage::DownloadingCover,
+                0.8,
+                "Downloading cover art and lyrics for embedding...".to_string(),
+            );
+            
+            // Create futures for parallel execution
+            let cover_future = if options.download_cover {
+                println!("🖼️ Downloading cover art for: {} - {}", track.artist, track.title);
+                Some(self.services.cover_art(
+                    track,
+                    options.cover_width,
+                    options.cover_height,
+                    &options.cover_format,
+                ))
+            } else {
+                println!("⏭️ Cover art download disabled in settings");
+                None
+            };
+            
+            let lyrics_future = if options.download_lyrics {
+                Some(self.services.lyrics(track))
+            } else {
+                None
+            };
+            
+            // Execute both futures in parallel
+            let (cover_result, lyrics_result) = tokio::join!(
+                async {
+                    if let Some(future) = cover_future {
+                        match future.await {
+                            Ok(data) => {
+                                println!("✅ Cover art downloaded successfully: {} bytes", data.len());
+                                Some(data)
+                            },
+                            Err(e) => {
+                                println!("❌ Failed to download cover art: {}", e);
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    }
+                },
+                async {
+                    if let Some(future) = lyrics_future {
+                        match future.await {
+                            Ok(result) => {
+                                println!("✅ Lyrics downloaded successfully for embedding");
+                                Some(result)
+                            }
+                            Err(e) => {
+                                println!("❌ Failed to download lyrics: {}", e);
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    }
+                }
+            );
+            
+            (cover_result, lyrics_result)
+        } else {
+            (None, None)
+        };
+
+        // Embed metadata if requested
+        if options.embed_metadata {
+            self.send_progress(
+                progress_sender,
+                &track.id,
+                DownloadStage::EmbeddingMetadata,
+                0.9,
+                "Embedding metadata...".to_string(),
+            );
+            
+            self.metadata_embedder.embed_metadata(
+                path,
+                track,
+                cover_art_data.as_deref(),
+                lyrics_data.as_deref(),
+                options,
+            ).await?;
+        }
+
+        if let Some(replaygain) = &replaygain {
+            if let Err(e) = self.metadata_embedder.embed_replaygain(
+                path,
+                replaygain,
+            ).await {
+                println!("⚠️ Failed to write ReplayGain tags: {}", e);
+            }
+        }
+
+        // Save cover art to covers/ folder if we have cover art data
+        if options.download_cover && cover_art_data.is_some() {
+            self.send_progress(
+                progress_sender,
+                &track.id,
+                DownloadStage::DownloadingCover,
+                0.95,
+                "Saving cover art to covers folder...".to_string(),
+            );
+            
+            // Save the cover art data we already downloaded
+            match self.save_cover_art_to_folder(
+                track,
+                &options.output_dir,
+                cover_art_data.as_ref().unwrap(),
+                &options.cover_format,
+            ).await {
+                Ok(cover_path) => {
+                    println!("✅ Cover art saved to: {}", cover_path.display());
+                }
+                Err(e) => {
+                    println!("⚠️ Failed to save cover art to folder: {}", e);
+                    // Don't fail the entire download for this
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run EBU R128 analysis on a converted file (best effort)
+    async fn analyze_loudness(&self, file_path: &PathBuf) -> Option<LoudnessAnalysis> {
+        match LoudnessAnalyzer::analyze_file(file_path).await {
+            Ok(analysis) => {
+                println!(
+                    "🔊 Integrated loudness: {:.2} LUFS, true peak: {:.6}",
+                    analysis.integrated_lufs, analysis.true_peak
+                );
+                Some(analysis)
+            }
+            Err(e) => {
+                println!("⚠️ Loudness analysis skipped: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Fill in missing genres, album and duration from Last.fm (best effort)
+    async fn enrich_with_lastfm(&self, track: &TrackMetadata, config: &crate::config::Config) -> TrackMetadata {
+        let mut enriched = track.clone();
+        let api_key = match ApiWrapper::lastfm_api_key(config) {
+            Ok(api_key) => api_key,
+            Err(e) => {
+                println!("⚠️ Last.fm enrichment skipped: {}", e);
+                return enriched;
+            }
+        };
+
+        match ApiWrapper::get_lastfm_track_info(&api_key, &track.artist, &track.title).await {
+            Ok(info) => {
+                if enriched.genres.is_empty() && !info.tags.is_empty() {
+                    println!("🏷️ Using Last.fm tags as genres: {}", info.tags.join(", "));
+                    enriched.genres = info.tags;
+                }
+                if enriched.album.is_empty() {
+                    if let Some(album) = info.album {
+                        println!("💿 Using Last.fm album hint: {}", album);
+                        enriched.album = album;
+                    }
+                }
+                if enriched.duration_ms == 0 {
+                    if let Some(duration) = info.duration {
+                        println!("⏱️ Using Last.fm duration hint: {}s", duration);
+                        enriched.duration_ms = duration * 1000;
+                    }
+                }
+            }
+            Err(e) => {
+                println!("⚠️ Last.fm enrichment skipped: {}", e);
+            }
+        }
+
+        enriched
+    }
+
+    /// Pick the search result that best fits the track's duration and album hints
+    fn select_best_match<'a>(&self, results: &'a [SearchResult], track: &TrackMetadata) -> &'a SearchResult {
+        let expected_secs = (track.duration_ms > 0).then(|| (track.duration_ms / 1000) as i64);
+        let album = track.album.to_lowercase();
+
+        results.iter()
+            .enumerate()
+            .min_by_key(|(index, result)| {
+                // Keep the original ranking as a tie breaker
+                let mut penalty = *index as i64;
+                if let (Some(expected), Some(actual)) = (expected_secs, result.duration) {
+                    penalty += (expected - actual as i64).abs() * 10;
+                }
+                if !album.is_empty() && result.title.to_lowercase().contains(&album) {
+                    penalty -= 5;
+                }
+                penalty
+            })
+            .map(|(_, result)| result)
+            .unwrap_or(&results[0])
+    }
+
+    /// Confidence from 0.0 to 1.0 that a search result is the track, mostly from how close the durations are
+    fn match_score(result: &SearchResult, track: &TrackMetadata) -> f32 {
+        let mut score = match (track.duration_ms > 0, result.duration) {
+            (true, Some(actual)) => {
+                let difference = ((track.duration_ms / 1000) as f32 - actual as f32).abs();
+                // Off by 30 seconds or more is as good as no match
+                1.0 - (difference / 30.0).min(1.0)
+            }
+            _ => 0.5,
+        };
+        let title = result.title.to_lowercase();
+        if title.contains(&track.title.to_lowercase()) {
+            score += 0.1;
+        }
+        if !track.album.is_empty() && title.contains(&track.album.to_lowercase()) {
+            score += 0.05;
+        }
+        score.min(1.0)
+    }
+
     /// Get the output path for a track
-    fn get_output_path(&self, track: &TrackMetadata, options: &DownloadOptions) -> PathBuf {
+    pub fn get_output_path(&self, track: &TrackMetadata, options: &DownloadOptions) -> PathBuf {
+        if let Some(output_path) = &options.output_path {
+            return output_path.clone();
+        }
+
         let mut path = options.output_dir.clone();
         
         // Create tracks directory
@@ -387,12 +974,7 @@ impl AudioDownloader {
             track.title
         );
         let sanitized_filename = sanitize_filename(&filename);
-        let extension = match options.format {
-            AudioFormat::Mp3 => "mp3",
-            AudioFormat::M4a => "m4a",
-            AudioFormat::Flac => "flac",
-            AudioFormat::Wav => "wav",
-        };
+        let extension = options.format.extension();
         
         path.push(format!("{}.{}", sanitized_filename, extension));
         path
@@ -400,28 +982,168 @@ impl AudioDownloader {
 
     // Removed separate folder creation functions - everything is now embedded in metadata
 
-    /// Convert audio to desired format and bitrate
-    async fn convert_audio(&self, input_path: &PathBuf, options: &DownloadOptions) -> Result<PathBuf> {
-        println!("Converting audio: {} to format {:?} at {} kbps", 
-                 input_path.display(), options.format, options.bitrate.as_u32());
-        
-        // Create converter
-        let converter = crate::downloader::converter::AudioConverter::new();
-        
-        // Check if conversion is needed
-        if !converter.needs_conversion(input_path, options.format, options.bitrate) {
+    /// Detect silence and excess intro/outro in the downloaded file and cut it in place.
+    /// Trimming problems are logged and the untrimmed file is kept.
+    async fn trim_audio(
+        &self,
+        track: &TrackMetadata,
+        downloaded: &DownloadedAudio,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<Option<TrimReport>> {
+        let input_path = &downloaded.path;
+        let monitor = ConversionMonitor {
+            duration_ms: Some(track.duration_ms as u64),
+            timeout: config.conversion_config.timeout(),
+            progress_callback: None,
+        };
+
+        let (silences, duration_secs) = match self.converter.detect_silence(
+            input_path,
+            options.trim.silence_threshold_db,
+            options.trim.min_silence_secs,
+            &monitor,
+        ).await {
+            Ok(detected) => detected,
+            Err(e @ SpotifyDownloaderError::Cancelled(_)) => return Err(e),
+            Err(e) => {
+                println!("⚠️ Skipping trimming: {}", e);
+                return Ok(None);
+            }
+        };
+
+        let reference_secs = (track.duration_ms > 0).then(|| track.duration_ms as f64 / 1000.0);
+        let report = TrimPlanner::new(options.trim.clone()).plan(&silences, duration_secs, reference_secs);
+        if report.is_empty() {
+            println!("✂️ Nothing to trim in {}", input_path.display());
+            return Ok(Some(report));
+        }
+
+        for cut in &report.cuts {
+            println!("✂️ Cutting {}", cut);
+        }
+
+        let extension = input_path.extension().and_then(|ext| ext.to_str()).unwrap_or("tmp");
+        let trimmed_path = input_path.with_extension(format!("trimmed.{}", extension));
+        if let Err(e) = self.converter.cut_audio(
+            input_path,
+            &trimmed_path,
+            report.keep_start_secs,
+            report.keep_end_secs,
+            &monitor,
+        ).await {
+            if let SpotifyDownloaderError::Cancelled(_) = e {
+                return Err(e);
+            }
+            println!("⚠️ Trimming failed, keeping the full file: {}", e);
+            return Ok(None);
+        }
+        std::fs::rename(&trimmed_path, input_path)
+            .map_err(|e| SpotifyDownloaderError::Conversion(format!("Failed to replace trimmed file: {}", e)))?;
+
+        println!("✂️ {} ({:.1}s → {:.1}s)", report.summary(), report.original_secs, report.kept_secs());
+        self.send_progress(
+            progress_sender,
+            &track.id,
+            DownloadStage::TrimmingAudio,
+            0.6,
+            format!("Trimming: {}", report.summary()),
+        );
+        Ok(Some(report))
+    }
+
+    /// Hand a shared progress callback to one download attempt
+    fn boxed_callback<F>(callback: &std::sync::Arc<F>) -> Box<dyn Fn(TransferStats) + Send + Sync>
+    where
+        F: Fn(TransferStats) + Send + Sync + 'static,
+    {
+        let callback = std::sync::Arc::clone(callback);
+        Box::new(move |stats| callback(stats))
+    }
+
+    /// Remove a downloaded source that a cancelled download leaves behind
+    fn discard_on_cancel<T>(result: Result<T>, source_path: &Path) -> Result<T> {
+        if let Err(SpotifyDownloaderError::Cancelled(_)) = &result {
+            if source_path.exists() {
+                let _ = std::fs::remove_file(source_path);
+                println!("🧹 Removed partial download {}", source_path.display());
+            }
+        }
+        result
+    }
+
+    /// A lossless container cannot restore what a lossy source already discarded, apply the configured policy
+    fn check_lossy_to_lossless(
+        source_codec: Option<&SourceCodec>,
+        options: &DownloadOptions,
+        config: &crate::config::Config,
+    ) -> Result<()> {
+        if let Some(codec) = source_codec.filter(|codec| options.format.is_lossless() && !codec.is_lossless()) {
+            let message = format!("{} output requested from a lossy {} source", options.format, codec);
+            match config.conversion_config.lossy_to_lossless {
+                LossyToLosslessPolicy::Allow => {}
+                LossyToLosslessPolicy::Warn => println!("⚠️ {}, the file will be larger but not better", message),
+                LossyToLosslessPolicy::Refuse => {
+                    return Err(SpotifyDownloaderError::Conversion(format!("Refusing {}", message)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert audio to desired format and quality, copying the stream when the codec already fits
+    async fn convert_audio(
+        &self,
+        track: &TrackMetadata,
+        downloaded: &DownloadedAudio,
+        options: &DownloadOptions,
+        progress_sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        config: &crate::config::Config,
+    ) -> Result<PathBuf> {
+        let input_path = &downloaded.path;
+        let source_codec = downloaded.source_codec.clone()
+            .or_else(|| SourceCodec::probe(input_path));
+
+        if let Some(codec) = &source_codec {
+            match downloaded.source_bitrate_kbps {
+                Some(kbps) => println!("🎧 Source stream: {} at {:.0} kbps", codec, kbps),
+                None => println!("🎧 Source stream: {}", codec),
+            }
+        }
+
+        if options.quality == Quality::Original {
+            if options.normalization.enabled {
+                println!("⚠️ Skipping loudness normalization, the original stream is kept untouched");
+            }
+            println!("Keeping original stream: {}", input_path.display());
+            return Ok(input_path.clone());
+        }
+
+        // Lossless output is meant as an archive copy, so it is never normalized
+        let normalize = options.normalization.enabled && !options.format.is_lossless();
+        if options.normalization.enabled && !normalize {
+            println!("Skipping loudness normalization for lossless {} output", options.format);
+        }
+
+        if let Err(e) = Self::check_lossy_to_lossless(source_codec.as_ref(), options, config) {
+            let _ = std::fs::remove_file(input_path);
+            return Err(e);
+        }
+
+        let mut plan = self.converter.plan_conversion(input_path, source_codec.as_ref(), options.format, options.quality);
+        if normalize {
+            // Applying a filter always means decoding and encoding again
+            plan = ConversionPlan::Transcode;
+        }
+        if plan == ConversionPlan::Keep {
             println!("No conversion needed, using original file");
             return Ok(input_path.clone());
         }
         
         // Create output path with correct extension
         let mut output_path = input_path.clone();
-        let extension = match options.format {
-            AudioFormat::Mp3 => "mp3",
-            AudioFormat::M4a => "m4a",
-            AudioFormat::Flac => "flac",
-            AudioFormat::Wav => "wav",
-        };
+        let extension = options.format.extension();
         
         // Replace extension
         if let Some(stem) = output_path.file_stem() {
@@ -429,9 +1151,9 @@ impl AudioDownloader {
         }
         
         // If input and output are the same, use a temporary file
+        let mut temp_dir = options.output_dir.clone();
+        temp_dir.push("temp");
         if input_path == &output_path {
-            let mut temp_dir = options.output_dir.clone();
-            temp_dir.push("temp");
             std::fs::create_dir_all(&temp_dir).ok(); // Create temp directory if it doesn't exist
             
             let temp_filename = format!("temp_convert_{}.{}", 
@@ -441,19 +1163,76 @@ impl AudioDownloader {
             println!("Using temporary file for conversion: {}", output_path.display());
         }
         
-        // Perform conversion
-        converter.convert_audio(input_path, &output_path, options.format, options.bitrate).await?;
+        // Loudnorm's analysis pass takes the first half of the conversion progress
+        let audio_filter = if normalize {
+            let measure_callback = |fraction: f32| {
+                self.send_progress(
+                    progress_sender,
+                    &track.id,
+                    DownloadStage::ConvertingAudio,
+                    0.6 + fraction * 0.05,
+                    format!("Measuring loudness... {:.0}%", fraction * 100.0),
+                );
+            };
+            let measure_monitor = ConversionMonitor {
+                duration_ms: Some(track.duration_ms as u64),
+                timeout: config.conversion_config.timeout(),
+                progress_callback: Some(&measure_callback),
+            };
+            match self.converter.measure_loudness(input_path, &options.normalization, &measure_monitor).await {
+                Ok(measurement) => {
+                    println!("🔊 Measured {:.1} LUFS, {:.1} dBTP, normalizing to {:.1} LUFS",
+                             measurement.input_i, measurement.input_tp, options.normalization.target_lufs);
+                    Some(measurement.second_pass_filter(&options.normalization))
+                }
+                Err(e @ SpotifyDownloaderError::Cancelled(_)) => return Err(e),
+                Err(e) => {
+                    println!("⚠️ Loudness measurement failed, converting without normalization: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let progress_start = if normalize { 0.65 } else { 0.6 };
+
+        // Report ffmpeg's progress within the 60% to 70% band
+        let progress_callback = |fraction: f32| {
+            self.send_progress(
+                progress_sender,
+                &track.id,
+                DownloadEvent::Converting { percent: fraction * 100.0 },
+                progress_start + fraction * (0.7 - progress_start),
+                format!("Converting audio... {:.0}%", fraction * 100.0),
+            );
+        };
+        let monitor = ConversionMonitor {
+            duration_ms: Some(track.duration_ms as u64),
+            timeout: config.conversion_config.timeout(),
+            progress_callback: Some(&progress_callback),
+        };
+
+        if plan == ConversionPlan::Remux {
+            println!("Converting audio: {} copying {} stream into {}", 
+                     input_path.display(), source_codec.as_ref().map(|codec| codec.to_string()).unwrap_or_default(), extension);
+            self.converter.remux_audio(input_path, &output_path, &monitor).await?;
+        } else {
+            println!("Converting audio: {} to format {:?} at {}", 
+                     input_path.display(), options.format, options.quality);
+            self.converter.convert_audio(input_path, &output_path, options.format, options.quality, audio_filter.as_deref(), &monitor).await?;
+        }
         
         // If we used a temporary file, replace the original
-        if input_path != &output_path {
-            let mut temp_dir = options.output_dir.clone();
-            temp_dir.push("temp");
-            if output_path.parent() == Some(temp_dir.as_path()) {
-                println!("Replacing original file with converted version");
-                std::fs::rename(&output_path, input_path)
-                    .map_err(|e| SpotifyDownloaderError::Conversion(format!("Failed to replace original file: {}", e)))?;
-                return Ok(input_path.clone());
-            }
+        if output_path.parent() == Some(temp_dir.as_path()) {
+            println!("Replacing original file with converted version");
+            std::fs::rename(&output_path, input_path)
+                .map_err(|e| SpotifyDownloaderError::Conversion(format!("Failed to replace original file: {}", e)))?;
+            return Ok(input_path.clone());
+        }
+
+        // The source file is no longer needed once the converted one exists
+        if let Err(e) = std::fs::remove_file(input_path) {
+            println!("⚠️ Failed to remove source file {}: {}", input_path.display(), e);
         }
         
         Ok(output_path)
@@ -509,31 +1288,56 @@ impl AudioDownloader {
         &self,
         sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
         track_id: &str,
-        stage: DownloadStage,
+        event: impl Into<DownloadEvent>,
         progress: f32,
         message: String,
     ) {
         if let Some(sender) = sender {
-            let _ = sender.send(DownloadProgress {
-                track_id: track_id.to_string(),
-                stage,
-                progress,
-                message,
-            });
+            let _ = sender.send(DownloadProgress::new(track_id, event, progress, message));
         }
     }
+
+    /// Report a finished file with its size
+    fn send_completed(&self, sender: &Option<mpsc::UnboundedSender<DownloadProgress>>, track_id: &str, path: &Path) {
+        let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        self.send_progress(
+            sender,
+            track_id,
+            DownloadEvent::Completed { path: path.to_path_buf(), size },
+            1.0,
+            "Download completed successfully!".to_string(),
+        );
+    }
+
+    /// Report that a track failed at a stage, handing the error back
+    pub fn report_failure(
+        sender: &Option<mpsc::UnboundedSender<DownloadProgress>>,
+        track_id: &str,
+        stage: DownloadStage,
+        error: SpotifyDownloaderError,
+    ) -> SpotifyDownloaderError {
+        if let Some(sender) = sender {
+            let event = DownloadEvent::Failed { stage, error_kind: error.kind() };
+            let _ = sender.send(DownloadProgress::new(track_id, event, 0.0, error.to_string()));
+        }
+        error
+    }
 }
 
 impl Clone for AudioDownloader {
     fn clone(&self) -> Self {
         Self {
-            youtube_downloader: YoutubeDownloader::new(),
+            youtube_downloader: {
+                let mut youtube_downloader = YoutubeDownloader::new();
+                youtube_downloader.set_control(self.control.clone());
+                youtube_downloader
+            },
             soundcloud_downloader: SoundcloudDownloader::new(),
             ytdlp_downloader: YtDlpDownloader::new(),
-            converter: AudioConverter::new(),
-            cover_downloader: CoverDownloader::new(),
+            converter: self.converter.clone(), // Keep the download control
             metadata_embedder: MetadataEmbedder::new(),
-            search_cache: HashMap::new(), // Start with empty cache for each instance
+            services: Arc::clone(&self.services), // Keep sharing caches with the original
+            control: self.control.clone(),
         }
     }
 }
@@ -0,0 +1,177 @@
+use crate::errors::{Result, SpotifyDownloaderError};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Pause, resume and cancel for a batch of downloads and the yt-dlp/ffmpeg processes they run.
+/// Clones share the same state, so one handle kept by the UI controls every task of the batch.
+#[derive(Debug, Clone)]
+pub struct DownloadControl {
+    cancel_token: CancellationToken,
+    paused: Arc<watch::Sender<bool>>,
+    /// Ids of running child processes
+    processes: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl DownloadControl {
+    /// Create a control that is running and not cancelled
+    pub fn new() -> Self {
+        Self {
+            cancel_token: CancellationToken::new(),
+            paused: Arc::new(watch::channel(false).0),
+            processes: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Token that is cancelled together with this control
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+
+    /// Stop admitting new work and suspend running child processes
+    pub fn pause(&self) {
+        if self.cancel_token.is_cancelled() || self.paused.send_replace(true) {
+            return;
+        }
+        let suspended = self.signal_all(ProcessSignal::Suspend);
+        println!("⏸️ Downloads paused ({} process(es) suspended)", suspended);
+    }
+
+    /// Continue suspended processes and admit new work again
+    pub fn resume(&self) {
+        if !self.paused.send_replace(false) {
+            return;
+        }
+        let resumed = self.signal_all(ProcessSignal::Continue);
+        println!("▶️ Downloads resumed ({} process(es) continued)", resumed);
+    }
+
+    /// Kill running child processes and stop every task at its next checkpoint
+    pub fn cancel(&self) {
+        if self.cancel_token.is_cancelled() {
+            return;
+        }
+        self.cancel_token.cancel();
+        // Wake tasks waiting for a resume so they can see the cancellation
+        self.paused.send_replace(false);
+        let killed = self.signal_all(ProcessSignal::Kill);
+        println!("🛑 Downloads cancelled ({} process(es) stopped)", killed);
+    }
+
+    /// Whether new work is on hold
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Whether the batch was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+
+    /// Checkpoint between stages: waits while paused and fails once cancelled
+    pub async fn checkpoint(&self) -> Result<()> {
+        let mut paused = self.paused.subscribe();
+        loop {
+            if self.cancel_token.is_cancelled() {
+                return Err(SpotifyDownloaderError::Cancelled("Download cancelled".to_string()));
+            }
+            if !*paused.borrow_and_update() {
+                return Ok(());
+            }
+            tokio::select! {
+                _ = self.cancel_token.cancelled() => {}
+                changed = paused.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start a command in a process group of its own, so pausing and cancelling also reach
+    /// the processes it runs, like the ffmpeg yt-dlp starts to extract audio
+    pub fn isolate(cmd: &mut tokio::process::Command) {
+        #[cfg(unix)]
+        cmd.process_group(0);
+        #[cfg(not(unix))]
+        let _ = cmd;
+    }
+
+    /// Track a running child process until the returned guard is dropped.
+    /// A process started while paused is suspended right away.
+    pub fn register_process(&self, pid: Option<u32>) -> ProcessGuard {
+        if let Some(pid) = pid {
+            if let Ok(mut processes) = self.processes.lock() {
+                processes.insert(pid);
+            }
+            if self.is_paused() {
+                ProcessSignal::Suspend.send(pid);
+            }
+        }
+        ProcessGuard {
+            processes: Arc::clone(&self.processes),
+            pid,
+        }
+    }
+
+    fn signal_all(&self, signal: ProcessSignal) -> usize {
+        let processes = match self.processes.lock() {
+            Ok(processes) => processes.clone(),
+            Err(_) => return 0,
+        };
+        processes.iter()
+            .filter(|pid| signal.send(**pid))
+            .count()
+    }
+}
+
+impl Default for DownloadControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes a child process from its control when it exits
+pub struct ProcessGuard {
+    processes: Arc<Mutex<HashSet<u32>>>,
+    pid: Option<u32>,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        if let (Some(pid), Ok(mut processes)) = (self.pid, self.processes.lock()) {
+            processes.remove(&pid);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ProcessSignal {
+    Suspend,
+    Continue,
+    Kill,
+}
+
+impl ProcessSignal {
+    /// Signal a child process and the processes it started, returning whether it was delivered.
+    /// Children started through `DownloadControl::isolate` lead their own group; others get the signal alone.
+    #[cfg(unix)]
+    fn send(self, pid: u32) -> bool {
+        let signal = match self {
+            ProcessSignal::Suspend => libc::SIGSTOP,
+            ProcessSignal::Continue => libc::SIGCONT,
+            ProcessSignal::Kill => libc::SIGKILL,
+        };
+        let pid = pid as libc::pid_t;
+        // SAFETY: kill(2) has no memory safety requirements
+        unsafe { libc::kill(-pid, signal) == 0 || libc::kill(pid, signal) == 0 }
+    }
+
+    /// Processes can't be suspended here, pausing only holds back new work
+    #[cfg(not(unix))]
+    fn send(self, _pid: u32) -> bool {
+        false
+    }
+}
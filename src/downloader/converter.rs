@@ -1,28 +1,214 @@
-use crate::config::{AudioFormat, Bitrate};
+use crate::config::{AudioFormat, Bitrate, NormalizationConfig, Quality};
+use crate::downloader::album_split::TrackSegment;
+use crate::downloader::trim::SilenceInterval;
 use crate::errors::{Result, SpotifyDownloaderError};
-use std::path::PathBuf;
+use lofty::file::AudioFile;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use symphonia::core::codecs::{self, CodecType};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use crate::downloader::control::DownloadControl;
 
-/// Audio converter (placeholder - would use FFmpeg in production)
+/// Codec of a downloaded audio stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceCodec {
+    Aac,
+    Opus,
+    Vorbis,
+    Mp3,
+    Flac,
+    Alac,
+    Pcm,
+    Other(String),
+}
+
+impl std::fmt::Display for SourceCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceCodec::Aac => write!(f, "AAC"),
+            SourceCodec::Opus => write!(f, "Opus"),
+            SourceCodec::Vorbis => write!(f, "Vorbis"),
+            SourceCodec::Mp3 => write!(f, "MP3"),
+            SourceCodec::Flac => write!(f, "FLAC"),
+            SourceCodec::Alac => write!(f, "ALAC"),
+            SourceCodec::Pcm => write!(f, "PCM"),
+            SourceCodec::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl SourceCodec {
+    /// Parse yt-dlp's acodec field, e.g. "mp4a.40.2" or "opus"
+    pub fn from_acodec(acodec: &str) -> Option<Self> {
+        let acodec = acodec.trim().to_lowercase();
+        let codec = match acodec.as_str() {
+            "" | "none" | "na" => return None,
+            name if name.starts_with("mp4a") || name == "aac" => SourceCodec::Aac,
+            "opus" => SourceCodec::Opus,
+            "vorbis" => SourceCodec::Vorbis,
+            "mp3" => SourceCodec::Mp3,
+            "flac" => SourceCodec::Flac,
+            "alac" => SourceCodec::Alac,
+            name if name.starts_with("pcm") => SourceCodec::Pcm,
+            name => SourceCodec::Other(name.to_string()),
+        };
+        Some(codec)
+    }
+
+    /// Detect the codec by probing the file with symphonia
+    pub fn probe(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .ok()?;
+        let codec = probed.format.default_track()?.codec_params.codec;
+        Some(Self::from_codec_type(codec))
+    }
+
+    fn from_codec_type(codec: CodecType) -> Self {
+        match codec {
+            codecs::CODEC_TYPE_AAC => SourceCodec::Aac,
+            codecs::CODEC_TYPE_OPUS => SourceCodec::Opus,
+            codecs::CODEC_TYPE_VORBIS => SourceCodec::Vorbis,
+            codecs::CODEC_TYPE_MP3 => SourceCodec::Mp3,
+            codecs::CODEC_TYPE_FLAC => SourceCodec::Flac,
+            codecs::CODEC_TYPE_ALAC => SourceCodec::Alac,
+            codecs::CODEC_TYPE_PCM_S16LE | codecs::CODEC_TYPE_PCM_S16BE
+            | codecs::CODEC_TYPE_PCM_S24LE | codecs::CODEC_TYPE_PCM_S24BE
+            | codecs::CODEC_TYPE_PCM_S32LE | codecs::CODEC_TYPE_PCM_F32LE => SourceCodec::Pcm,
+            other => SourceCodec::Other(format!("{}", other)),
+        }
+    }
+
+    /// Whether the codec preserves the original signal exactly
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, SourceCodec::Flac | SourceCodec::Alac | SourceCodec::Pcm)
+    }
+
+    /// Whether the stream can be copied into the format's container without re-encoding
+    pub fn fits(&self, format: AudioFormat) -> bool {
+        matches!(
+            (self, format),
+            (SourceCodec::Mp3, AudioFormat::Mp3)
+                | (SourceCodec::Aac, AudioFormat::M4a)
+                | (SourceCodec::Opus, AudioFormat::Opus)
+                | (SourceCodec::Opus, AudioFormat::Ogg)
+                | (SourceCodec::Vorbis, AudioFormat::Ogg)
+                | (SourceCodec::Flac, AudioFormat::Flac)
+                | (SourceCodec::Alac, AudioFormat::Alac)
+        )
+    }
+}
+
+/// How a downloaded file becomes the requested format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionPlan {
+    /// The file is already in the right codec and container
+    Keep,
+    /// Copy the stream into a new container
+    Remux,
+    /// Decode and encode with the target codec
+    Transcode,
+}
+
+/// First pass measurements from ffmpeg's loudnorm filter
+#[derive(Debug, Clone)]
+pub struct LoudnormMeasurement {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+impl LoudnormMeasurement {
+    /// Parse the JSON block loudnorm prints at the end of a run with print_format=json
+    fn parse(ffmpeg_output: &str) -> Option<Self> {
+        let start = ffmpeg_output.rfind('{')?;
+        let end = start + ffmpeg_output[start..].find('}')?;
+        let json: serde_json::Value = serde_json::from_str(&ffmpeg_output[start..=end]).ok()?;
+        // loudnorm reports every value as a string, silence comes out as "-inf"
+        let value = |key: &str| -> Option<f64> {
+            json.get(key)?.as_str()?.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+        };
+
+        Some(Self {
+            input_i: value("input_i")?,
+            input_tp: value("input_tp")?,
+            input_lra: value("input_lra")?,
+            input_thresh: value("input_thresh")?,
+            target_offset: value("target_offset")?,
+        })
+    }
+
+    /// Second pass filter that applies the targets using these measurements
+    pub fn second_pass_filter(&self, target: &NormalizationConfig) -> String {
+        format!(
+            "{}:measured_I={:.2}:measured_TP={:.2}:measured_LRA={:.2}:measured_thresh={:.2}:offset={:.2}:linear=true",
+            AudioConverter::loudnorm_targets(target),
+            self.input_i,
+            self.input_tp,
+            self.input_lra,
+            self.input_thresh,
+            self.target_offset,
+        )
+    }
+}
+
+/// Progress reporting and limits for one ffmpeg run
+#[derive(Default)]
+pub struct ConversionMonitor<'a> {
+    /// Expected output duration, the input is probed when not given
+    pub duration_ms: Option<u64>,
+    /// Kill ffmpeg if it runs longer than this
+    pub timeout: Option<Duration>,
+    /// Receives the completed fraction from 0.0 to 1.0
+    pub progress_callback: Option<&'a (dyn Fn(f32) + Send + Sync)>,
+}
+
+/// Audio converter built on FFmpeg
+#[derive(Clone)]
 pub struct AudioConverter {
-    // Configuration for audio conversion
+    control: DownloadControl,
 }
 
 impl AudioConverter {
     /// Create a new audio converter
     pub fn new() -> Self {
-        Self {}
+        Self {
+            control: DownloadControl::new(),
+        }
+    }
+
+    /// Create a converter whose ffmpeg runs are paused and cancelled through the control
+    pub fn with_control(control: DownloadControl) -> Self {
+        Self { control }
     }
 
-    /// Convert audio file to specified format and bitrate
+    /// Convert audio file to specified format and quality
     pub async fn convert_audio(
         &self,
         input_path: &PathBuf,
         output_path: &PathBuf,
         format: AudioFormat,
-        bitrate: Bitrate,
+        quality: Quality,
+        audio_filter: Option<&str>,
+        monitor: &ConversionMonitor<'_>,
     ) -> Result<()> {
         println!("Converting audio from {} to {}", input_path.display(), output_path.display());
-        println!("Format: {:?}, Bitrate: {} kbps", format, bitrate.as_u32());
+        println!("Format: {:?}, Quality: {}", format, quality);
 
         // Ensure output directory exists
         if let Some(parent) = output_path.parent() {
@@ -34,26 +220,28 @@ impl AudioConverter {
         self.check_ffmpeg_availability()?;
 
         // Build FFmpeg command with optimized settings
-        let mut cmd = tokio::process::Command::new("ffmpeg");
+        let mut cmd = Self::ffmpeg_command();
         
         // Input file
         cmd.arg("-i").arg(input_path);
         
-        // Audio codec and bitrate
-        let codec = match format {
-            AudioFormat::Mp3 => "libmp3lame",
-            AudioFormat::M4a => "aac",
-            AudioFormat::Flac => "flac",
-            AudioFormat::Wav => "pcm_s16le",
-        };
+        // Audio codec and quality
+        cmd.arg("-acodec").arg(format.ffmpeg_codec());
+        cmd.args(Self::quality_args(format, quality));
         
-        cmd.arg("-acodec").arg(codec);
-        cmd.arg("-b:a").arg(&format!("{}k", bitrate.as_u32()));
+        if let Some(filter) = audio_filter {
+            cmd.arg("-af").arg(filter);
+            // Filters like loudnorm resample internally, keep the source rate where the encoder allows it
+            let sample_rate = match format {
+                AudioFormat::Opus => 48000,
+                _ => Self::media_sample_rate(input_path).unwrap_or(48000),
+            };
+            cmd.arg("-ar").arg(sample_rate.to_string());
+        }
         
-        // Optimized quality settings for speed
+        // Optimized settings for speed
         match format {
             AudioFormat::Mp3 => {
-                cmd.arg("-q:a").arg("2"); // High quality but faster than 0
                 cmd.arg("-compression_level").arg("2"); // Faster compression
             },
             AudioFormat::M4a => {
@@ -64,13 +252,24 @@ impl AudioConverter {
             },
             AudioFormat::Wav => {
                 // No additional settings needed for WAV
+            },
+            AudioFormat::Opus => {
+                cmd.arg("-application").arg("audio"); // Tune for music rather than speech
+            },
+            AudioFormat::Ogg => {
+                // No additional settings needed for Vorbis
+            },
+            AudioFormat::Alac => {
+                cmd.arg("-vn"); // Keep only the audio stream in the MP4 container
+            },
+            AudioFormat::Aiff => {
+                // No additional settings needed for AIFF
             }
         }
         
         // Performance optimizations
         cmd.arg("-threads").arg("0"); // Use all available CPU cores
         cmd.arg("-loglevel").arg("error"); // Reduce logging overhead
-        cmd.arg("-stats"); // Show progress
         
         // Overwrite output file
         cmd.arg("-y");
@@ -79,15 +278,397 @@ impl AudioConverter {
         cmd.arg(output_path);
 
         // Execute the conversion asynchronously
-        let output = cmd.output().await
+        self.run_ffmpeg(cmd, input_path, Some(output_path), monitor).await
+            .map_err(|e| match e {
+                SpotifyDownloaderError::Conversion(message) => {
+                    SpotifyDownloaderError::Conversion(format!("FFmpeg conversion failed: {}", message))
+                }
+                other => other,
+            })
+            .map(|_| ())
+    }
+
+    /// Decide how to turn a source file into the requested format and quality
+    pub fn plan_conversion(
+        &self,
+        input_path: &Path,
+        source_codec: Option<&SourceCodec>,
+        format: AudioFormat,
+        quality: Quality,
+    ) -> ConversionPlan {
+        if quality == Quality::Original {
+            return ConversionPlan::Keep;
+        }
+
+        let input_ext = input_path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let same_container = input_ext == format.extension();
+
+        match source_codec {
+            // Re-encoding an already lossy stream only loses more, so copy it whenever the codec fits
+            Some(codec) if codec.fits(format) => {
+                if same_container { ConversionPlan::Keep } else { ConversionPlan::Remux }
+            }
+            // An unknown codec may not fit even when the extension does (e.g. AAC in .m4a for ALAC), so convert it
+            Some(_) | None => ConversionPlan::Transcode,
+        }
+    }
+
+    /// Copy the audio stream into the output container without re-encoding
+    pub async fn remux_audio(
+        &self,
+        input_path: &PathBuf,
+        output_path: &PathBuf,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<()> {
+        println!("Remuxing audio from {} to {}", input_path.display(), output_path.display());
+
+        self.check_ffmpeg_availability()?;
+
+        let mut cmd = Self::ffmpeg_command();
+        cmd.arg("-i").arg(input_path)
+            .arg("-vn") // Drop embedded thumbnails and video streams
+            .arg("-c:a").arg("copy")
+            .arg("-loglevel").arg("error")
+            .arg("-y")
+            .arg(output_path);
+
+        self.run_ffmpeg(cmd, input_path, Some(output_path), monitor).await
+            .map_err(|e| match e {
+                SpotifyDownloaderError::Conversion(message) => {
+                    SpotifyDownloaderError::Conversion(format!("FFmpeg remux failed: {}", message))
+                }
+                other => other,
+            })
+            .map(|_| ())
+    }
+
+    /// Run loudnorm's analysis pass and return its measurements for the second pass
+    pub async fn measure_loudness(
+        &self,
+        input_path: &PathBuf,
+        target: &NormalizationConfig,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<LoudnormMeasurement> {
+        println!("Measuring loudness of {}", input_path.display());
+
+        self.check_ffmpeg_availability()?;
+
+        let mut cmd = Self::ffmpeg_command();
+        cmd.arg("-hide_banner")
+            .arg("-i").arg(input_path)
+            .arg("-vn")
+            .arg("-af").arg(format!("{}:print_format=json", Self::loudnorm_targets(target)))
+            // loudnorm prints its measurements at info level
+            .arg("-loglevel").arg("info")
+            .arg("-f").arg("null")
+            .arg("-");
+
+        let output = self.run_ffmpeg(cmd, input_path, None, monitor).await
+            .map_err(|e| match e {
+                SpotifyDownloaderError::Conversion(message) => {
+                    SpotifyDownloaderError::Conversion(format!("FFmpeg loudness analysis failed: {}", message))
+                }
+                other => other,
+            })?;
+
+        LoudnormMeasurement::parse(&output)
+            .ok_or_else(|| SpotifyDownloaderError::Conversion("FFmpeg loudness analysis returned no measurements".to_string()))
+    }
+
+    /// Find silent stretches with ffmpeg's silencedetect filter, returning them with the file's duration in seconds
+    pub async fn detect_silence(
+        &self,
+        input_path: &PathBuf,
+        threshold_db: f64,
+        min_silence_secs: f64,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<(Vec<SilenceInterval>, f64)> {
+        println!("Detecting silence in {}", input_path.display());
+
+        self.check_ffmpeg_availability()?;
+
+        let mut cmd = Self::ffmpeg_command();
+        cmd.arg("-hide_banner")
+            .arg("-i").arg(input_path)
+            .arg("-vn")
+            .arg("-af").arg(format!("silencedetect=noise={:.1}dB:d={:.2}", threshold_db, min_silence_secs))
+            // silencedetect and the input duration are logged at info level
+            .arg("-loglevel").arg("info")
+            .arg("-f").arg("null")
+            .arg("-");
+
+        let output = self.run_ffmpeg(cmd, input_path, None, monitor).await
+            .map_err(|e| match e {
+                SpotifyDownloaderError::Conversion(message) => {
+                    SpotifyDownloaderError::Conversion(format!("FFmpeg silence detection failed: {}", message))
+                }
+                other => other,
+            })?;
+
+        let duration_secs = Self::media_duration_ms(input_path)
+            .map(|ms| ms as f64 / 1000.0)
+            .or_else(|| Self::parse_ffmpeg_duration(&output))
+            .ok_or_else(|| SpotifyDownloaderError::Conversion("Could not determine the audio duration".to_string()))?;
+
+        Ok((Self::parse_silences(&output, duration_secs), duration_secs))
+    }
+
+    /// Encode one album segment into its own file.
+    /// The cut is made on exact sample positions, so segments sharing a boundary play back gaplessly.
+    pub async fn extract_segment(
+        &self,
+        input_path: &PathBuf,
+        output_path: &PathBuf,
+        segment: &TrackSegment,
+        format: AudioFormat,
+        quality: Quality,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<()> {
+        let sample_rate = Self::media_sample_rate(input_path).unwrap_or(48000) as f64;
+        let start_sample = (segment.start_secs * sample_rate).round() as u64;
+        let end_sample = (segment.end_secs * sample_rate).round() as u64;
+        let filter = format!("atrim=start_sample={}:end_sample={},asetpts=PTS-STARTPTS", start_sample, end_sample);
+
+        self.convert_audio(input_path, output_path, format, quality, Some(&filter), monitor).await
+    }
+
+    /// Copy the section between two timestamps into a new file without re-encoding
+    pub async fn cut_audio(
+        &self,
+        input_path: &PathBuf,
+        output_path: &PathBuf,
+        start_secs: f64,
+        end_secs: f64,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<()> {
+        println!("Cutting {} to {:.2}s–{:.2}s", input_path.display(), start_secs, end_secs);
+
+        self.check_ffmpeg_availability()?;
+
+        let mut cmd = Self::ffmpeg_command();
+        cmd.arg("-i").arg(input_path)
+            .arg("-ss").arg(format!("{:.3}", start_secs))
+            .arg("-to").arg(format!("{:.3}", end_secs))
+            .arg("-vn")
+            .arg("-c:a").arg("copy")
+            .arg("-loglevel").arg("error")
+            .arg("-y")
+            .arg(output_path);
+
+        self.run_ffmpeg(cmd, input_path, Some(output_path), monitor).await
+            .map_err(|e| match e {
+                SpotifyDownloaderError::Conversion(message) => {
+                    SpotifyDownloaderError::Conversion(format!("FFmpeg cut failed: {}", message))
+                }
+                other => other,
+            })
+            .map(|_| ())
+    }
+
+    /// Collect silence_start/silence_end pairs from silencedetect's log output
+    fn parse_silences(ffmpeg_output: &str, duration_secs: f64) -> Vec<SilenceInterval> {
+        let value_after = |line: &str, key: &str| -> Option<f64> {
+            let rest = &line[line.find(key)? + key.len()..];
+            rest.split_whitespace().next()?.parse::<f64>().ok()
+        };
+
+        let mut silences = Vec::new();
+        let mut open_start = None;
+        for line in ffmpeg_output.lines() {
+            if let Some(start) = value_after(line, "silence_start:") {
+                open_start = Some(start.max(0.0));
+            } else if let Some(end) = value_after(line, "silence_end:") {
+                if let Some(start) = open_start.take() {
+                    silences.push(SilenceInterval { start_secs: start, end_secs: end.min(duration_secs) });
+                }
+            }
+        }
+        // Silence that runs into the end of the file has no silence_end line
+        if let Some(start) = open_start {
+            silences.push(SilenceInterval { start_secs: start, end_secs: duration_secs });
+        }
+        silences
+    }
+
+    /// Parse the "Duration: 00:03:21.45" line ffmpeg prints for its input
+    fn parse_ffmpeg_duration(ffmpeg_output: &str) -> Option<f64> {
+        let line = ffmpeg_output.lines().find(|line| line.trim_start().starts_with("Duration:"))?;
+        let timestamp = line.trim_start().trim_start_matches("Duration:").trim().split(',').next()?;
+        let mut parts = timestamp.split(':');
+        let hours = parts.next()?.parse::<f64>().ok()?;
+        let minutes = parts.next()?.parse::<f64>().ok()?;
+        let seconds = parts.next()?.parse::<f64>().ok()?;
+        Some(hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+
+    /// The loudnorm filter with the configured targets
+    fn loudnorm_targets(target: &NormalizationConfig) -> String {
+        format!(
+            "loudnorm=I={:.1}:TP={:.1}:LRA={:.1}",
+            target.target_lufs, target.true_peak_db, target.loudness_range
+        )
+    }
+
+    /// FFmpeg command that writes key=value progress to stdout instead of the stats line
+    fn ffmpeg_command() -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new("ffmpeg");
+        cmd.arg("-progress").arg("pipe:1").arg("-nostats");
+        cmd
+    }
+
+    /// Run ffmpeg with machine readable progress, stopping it on cancellation or timeout.
+    /// Returns ffmpeg's log output on success.
+    async fn run_ffmpeg(
+        &self,
+        mut cmd: tokio::process::Command,
+        input_path: &Path,
+        output_path: Option<&Path>,
+        monitor: &ConversionMonitor<'_>,
+    ) -> Result<String> {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let duration_ms = monitor.duration_ms
+            .filter(|ms| *ms > 0)
+            .or_else(|| Self::media_duration_ms(input_path));
+
+        // Hold off starting new work while paused
+        self.control.checkpoint().await?;
+        DownloadControl::isolate(&mut cmd);
+        let mut child = cmd.spawn()
             .map_err(|e| SpotifyDownloaderError::Conversion(format!("Failed to execute ffmpeg: {}", e)))?;
+        let _process = self.control.register_process(child.id());
+
+        // Drain stderr in the background so ffmpeg never blocks on a full pipe
+        let stderr_task = child.stderr.take().map(|mut stderr| {
+            tokio::spawn(async move {
+                let mut buffer = String::new();
+                let _ = stderr.read_to_string(&mut buffer).await;
+                buffer
+            })
+        });
 
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            return Err(SpotifyDownloaderError::Conversion(format!("FFmpeg conversion failed: {}", error_msg)));
+        // Without a timeout the deadline is simply never reached
+        let deadline = tokio::time::sleep(monitor.timeout.unwrap_or(Duration::from_secs(u32::MAX as u64)));
+        tokio::pin!(deadline);
+        let cancel_token = self.control.cancel_token();
+
+        let mut lines = child.stdout.take().map(|stdout| BufReader::new(stdout).lines());
+        let outcome = loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break Err(SpotifyDownloaderError::Cancelled("Conversion cancelled".to_string())),
+                _ = &mut deadline => {
+                    // A suspended ffmpeg makes no progress, so the timeout starts over after a pause
+                    if let (true, Some(timeout)) = (self.control.is_paused(), monitor.timeout) {
+                        deadline.as_mut().reset(tokio::time::Instant::now() + timeout);
+                        continue;
+                    }
+                    break Err(SpotifyDownloaderError::Conversion(format!(
+                        "timed out after {} s",
+                        monitor.timeout.map(|timeout| timeout.as_secs()).unwrap_or_default()
+                    )));
+                }
+                line = async {
+                    match lines.as_mut() {
+                        Some(lines) => lines.next_line().await,
+                        None => Ok(None),
+                    }
+                } => match line {
+                    Ok(Some(line)) => {
+                        if let (Some(fraction), Some(callback)) = (Self::progress_fraction(&line, duration_ms), monitor.progress_callback) {
+                            callback(fraction);
+                        }
+                    }
+                    // stdout closed, ffmpeg is exiting
+                    _ => break Ok(()),
+                },
+            }
+        };
+
+        let result = match outcome {
+            Ok(()) => {
+                let status = child.wait().await
+                    .map_err(|e| SpotifyDownloaderError::Conversion(format!("Failed to wait for ffmpeg: {}", e)))?;
+                let stderr = match stderr_task {
+                    Some(task) => task.await.unwrap_or_default(),
+                    None => String::new(),
+                };
+                if status.success() {
+                    Ok(stderr)
+                } else {
+                    Err(SpotifyDownloaderError::Conversion(stderr.trim().to_string()))
+                }
+            }
+            Err(e) => {
+                let _ = child.kill().await;
+                Err(e)
+            }
+        };
+
+        // Never leave a half written file behind
+        if let (Err(_), Some(output_path)) = (&result, output_path) {
+            let _ = tokio::fs::remove_file(output_path).await;
         }
+        result
+    }
+
+    /// Parse an "out_time_us=" line from ffmpeg's -progress output into a fraction of the duration
+    fn progress_fraction(line: &str, duration_ms: Option<u64>) -> Option<f32> {
+        let (key, value) = line.trim().split_once('=')?;
+        match key {
+            "progress" if value == "end" => Some(1.0),
+            // out_time_ms is also reported in microseconds by ffmpeg
+            "out_time_us" | "out_time_ms" => {
+                let out_time_us = value.parse::<u64>().ok()?;
+                let duration_us = duration_ms? * 1000;
+                Some((out_time_us as f64 / duration_us as f64).min(1.0) as f32)
+            }
+            _ => None,
+        }
+    }
+
+    /// Duration of a media file, when lofty can read it
+    fn media_duration_ms(path: &Path) -> Option<u64> {
+        let tagged_file = lofty::read_from_path(path).ok()?;
+        let duration_ms = tagged_file.properties().duration().as_millis() as u64;
+        Some(duration_ms)
+    }
 
-        Ok(())
+    /// Sample rate of a media file, when lofty can read it
+    fn media_sample_rate(path: &Path) -> Option<u32> {
+        lofty::read_from_path(path).ok()?.properties().sample_rate()
+    }
+
+    /// Rate control arguments for a format/quality pair
+    fn quality_args(format: AudioFormat, quality: Quality) -> Vec<String> {
+        // Lossless encoders have no rate control, original streams are never re-encoded
+        if format.is_lossless() || quality == Quality::Original {
+            return Vec::new();
+        }
+
+        match (format, quality) {
+            (AudioFormat::Opus, Quality::Cbr(kbps)) => vec![
+                "-vbr".to_string(), "off".to_string(),
+                "-b:a".to_string(), format!("{}k", kbps),
+            ],
+            (_, Quality::Cbr(kbps)) => vec!["-b:a".to_string(), format!("{}k", kbps)],
+            // LAME's native V0–V9 scale
+            (AudioFormat::Mp3, Quality::Vbr(level)) => vec!["-q:a".to_string(), level.to_string()],
+            // libvorbis quality runs from -1 to 10, higher is better
+            (AudioFormat::Ogg, Quality::Vbr(level)) => vec!["-q:a".to_string(), (9 - level.min(9) as i32).to_string()],
+            // Opus is VBR by default and takes a target bitrate
+            (AudioFormat::Opus, vbr) => vec![
+                "-vbr".to_string(), "on".to_string(),
+                "-b:a".to_string(), format!("{}k", vbr.target_kbps().unwrap_or(160)),
+            ],
+            // FFmpeg's native AAC encoder has no usable VBR mode, aim for the same average bitrate
+            (_, vbr) => vec!["-b:a".to_string(), format!("{}k", vbr.target_kbps().unwrap_or(192))],
+        }
     }
 
     /// Get supported input formats
@@ -99,6 +680,9 @@ impl AudioConverter {
             "wav".to_string(),
             "aac".to_string(),
             "ogg".to_string(),
+            "opus".to_string(),
+            "webm".to_string(),
+            "aiff".to_string(),
             "wma".to_string(),
         ]
     }
@@ -110,6 +694,10 @@ impl AudioConverter {
             AudioFormat::M4a,
             AudioFormat::Flac,
             AudioFormat::Wav,
+            AudioFormat::Opus,
+            AudioFormat::Ogg,
+            AudioFormat::Alac,
+            AudioFormat::Aiff,
         ]
     }
 
@@ -122,7 +710,7 @@ impl AudioConverter {
                 Bitrate::Kbps256,
                 Bitrate::Kbps320,
             ],
-            AudioFormat::M4a => vec![
+            AudioFormat::M4a | AudioFormat::Opus | AudioFormat::Ogg => vec![
                 Bitrate::Kbps128,
                 Bitrate::Kbps192,
                 Bitrate::Kbps256,
@@ -134,41 +722,12 @@ impl AudioConverter {
             AudioFormat::Wav => vec![
                 Bitrate::Kbps320, // WAV is lossless, but we'll use this as a placeholder
             ],
+            AudioFormat::Alac | AudioFormat::Aiff => vec![
+                Bitrate::Kbps320, // Lossless, placeholder like FLAC/WAV
+            ],
         }
     }
 
-    /// Check if conversion is needed
-    pub fn needs_conversion(&self, input_path: &PathBuf, format: AudioFormat, _bitrate: Bitrate) -> bool {
-        // Check if input file exists
-        if !input_path.exists() {
-            return false;
-        }
-
-        // Get file extension
-        let input_ext = input_path.extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        // Check if format matches
-        let target_ext = match format {
-            AudioFormat::Mp3 => "mp3",
-            AudioFormat::M4a => "m4a",
-            AudioFormat::Flac => "flac",
-            AudioFormat::Wav => "wav",
-        };
-
-        // If format doesn't match, conversion is needed
-        if input_ext != target_ext {
-            return true;
-        }
-
-        // For now, only convert if format doesn't match
-        // Bitrate conversion would require reading file metadata
-        // which is complex and not always necessary
-        false
-    }
-
     /// Get estimated output file size
     pub fn estimate_output_size(&self, _input_path: &PathBuf, _bitrate: Bitrate) -> Result<u64> {
         // TODO: Implement size estimation based on bitrate and duration
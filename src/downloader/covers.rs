@@ -204,7 +204,7 @@ impl CoverDownloader {
 
     /// Download image from URL
     async fn download_image(&self, url: &str) -> Result<Vec<u8>> {
-        let response = self.client.get(url).send().await?;
+        let mut response = self.client.get(url).send().await?;
 
         if !response.status().is_success() {
             return Err(SpotifyDownloaderError::CoverArt(
@@ -212,8 +212,14 @@ impl CoverDownloader {
             ));
         }
 
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+        // Read in chunks so cover art counts against the bandwidth caps
+        let throttle = crate::utils::bandwidth::TransferThrottle::new();
+        let mut bytes = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+        while let Some(chunk) = response.chunk().await? {
+            throttle.throttle(chunk.len() as u64).await;
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
     }
 
     /// Process and resize image
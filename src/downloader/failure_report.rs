@@ -0,0 +1,234 @@
+use crate::downloader::youtube::SearchResult;
+use crate::downloader::{DownloadOptions, DownloadStage, DownloadTaskResult, TrackMetadata};
+use crate::errors::{ErrorKind, Result, SpotifyDownloaderError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A search result that was considered as the source of a track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceCandidate {
+    pub url: String,
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub title: String,
+    /// Match confidence from 0.0 to 1.0; CSV reports don't keep it
+    pub score: Option<f32>,
+    /// The download went ahead with this source; the others were rejected
+    #[serde(default)]
+    pub chosen: bool,
+}
+
+impl SourceCandidate {
+    /// Candidate for a search result and the score it was given
+    pub fn new(result: &SearchResult, score: Option<f32>, chosen: bool) -> Self {
+        Self {
+            url: result.url.clone(),
+            platform: result.platform.clone(),
+            title: result.title.clone(),
+            score,
+            chosen,
+        }
+    }
+}
+
+/// Where and how a download failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureDetails {
+    /// Stage the download stopped at
+    pub stage: DownloadStage,
+    pub error_kind: ErrorKind,
+    /// Search results that were scored, including the rejected ones
+    #[serde(default)]
+    pub candidates: Vec<SourceCandidate>,
+    /// Unix timestamp of when the download started
+    pub started_at: Option<u64>,
+    /// Unix timestamp of when the download failed
+    pub failed_at: u64,
+}
+
+/// A track that failed, with what is needed to try it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureRecord {
+    pub track: TrackMetadata,
+    pub error: String,
+    /// Missing when the download failed before it reached the pipeline
+    pub details: Option<FailureDetails>,
+    /// Options the track was downloaded with; retries fall back to the current settings
+    #[serde(default)]
+    pub options: Option<DownloadOptions>,
+}
+
+/// One line of a CSV report, which keeps only the track fields needed to find it again
+#[derive(Debug, Serialize, Deserialize)]
+struct FailureRow {
+    spotify_url: String,
+    track_id: String,
+    artist: String,
+    title: String,
+    album: String,
+    duration_ms: u32,
+    isrc: Option<String>,
+    stage: Option<DownloadStage>,
+    error_kind: Option<ErrorKind>,
+    error: String,
+    /// Candidate URLs separated by spaces, the chosen one marked with a leading `*`
+    candidates: String,
+    started_at: Option<u64>,
+    failed_at: Option<u64>,
+}
+
+/// Failed tracks of a batch, exported as JSON or CSV and read back by `retry --from-report`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// Unix timestamp of when the report was made
+    pub created_at: u64,
+    pub failures: Vec<FailureRecord>,
+}
+
+impl FailureReport {
+    /// Empty report
+    pub fn new() -> Self {
+        Self {
+            created_at: now(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Report of the failed results of a batch, each with the options it was downloaded with
+    pub fn from_results(results: &[DownloadTaskResult]) -> Self {
+        let mut report = Self::new();
+        for result in results.iter().filter(|result| !result.success) {
+            report.failures.push(FailureRecord {
+                track: result.track.clone(),
+                error: result.error.clone().unwrap_or_else(|| "unknown error".to_string()),
+                details: result.failure.clone(),
+                options: Some(result.options.clone()),
+            });
+        }
+        report
+    }
+
+    /// No track failed
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Write the report as JSON or CSV, chosen by the file extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        match Self::extension(path).as_str() {
+            "json" => std::fs::write(path, serde_json::to_string_pretty(self)?)?,
+            "csv" => self.save_csv(path)?,
+            _ => return Err(Self::unsupported(path)),
+        }
+        Ok(())
+    }
+
+    /// Read a report written by `save`; CSV reports come back without download options
+    pub fn load(path: &Path) -> Result<Self> {
+        match Self::extension(path).as_str() {
+            "json" => Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+            "csv" => Self::load_csv(path),
+            _ => Err(Self::unsupported(path)),
+        }
+    }
+
+    fn save_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path)
+            .map_err(|e| SpotifyDownloaderError::Unknown(format!("Failed to create CSV: {}", e)))?;
+        for failure in &self.failures {
+            let track = &failure.track;
+            let details = failure.details.as_ref();
+            writer.serialize(FailureRow {
+                spotify_url: track.spotify_url.clone(),
+                track_id: track.id.clone(),
+                artist: track.artist.clone(),
+                title: track.title.clone(),
+                album: track.album.clone(),
+                duration_ms: track.duration_ms,
+                isrc: track.isrc.clone(),
+                stage: details.map(|details| details.stage.clone()),
+                error_kind: details.map(|details| details.error_kind),
+                error: failure.error.clone(),
+                candidates: details.map(|details| Self::join_candidates(&details.candidates)).unwrap_or_default(),
+                started_at: details.and_then(|details| details.started_at),
+                failed_at: details.map(|details| details.failed_at),
+            }).map_err(|e| SpotifyDownloaderError::Unknown(format!("Failed to write CSV: {}", e)))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn load_csv(path: &Path) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|e| SpotifyDownloaderError::CsvImport(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut report = Self::new();
+        for row in reader.deserialize::<FailureRow>() {
+            let row = row.map_err(|e| SpotifyDownloaderError::CsvImport(format!("Invalid failure report row: {}", e)))?;
+            let details = match (row.stage, row.error_kind) {
+                (Some(stage), Some(error_kind)) => Some(FailureDetails {
+                    stage,
+                    error_kind,
+                    candidates: Self::split_candidates(&row.candidates),
+                    started_at: row.started_at,
+                    failed_at: row.failed_at.unwrap_or(0),
+                }),
+                _ => None,
+            };
+            report.failures.push(FailureRecord {
+                track: TrackMetadata {
+                    id: row.track_id,
+                    title: row.title,
+                    artist: row.artist,
+                    album: row.album,
+                    duration_ms: row.duration_ms,
+                    spotify_url: row.spotify_url,
+                    isrc: row.isrc,
+                    ..Default::default()
+                },
+                error: row.error,
+                details,
+                options: None,
+            });
+        }
+        Ok(report)
+    }
+
+    fn join_candidates(candidates: &[SourceCandidate]) -> String {
+        candidates.iter()
+            .map(|candidate| if candidate.chosen { format!("*{}", candidate.url) } else { candidate.url.clone() })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn split_candidates(candidates: &str) -> Vec<SourceCandidate> {
+        candidates.split_whitespace()
+            .map(|url| SourceCandidate {
+                chosen: url.starts_with('*'),
+                url: url.trim_start_matches('*').to_string(),
+                platform: String::new(),
+                title: String::new(),
+                score: None,
+            })
+            .collect()
+    }
+
+    fn extension(path: &Path) -> String {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase()
+    }
+
+    fn unsupported(path: &Path) -> SpotifyDownloaderError {
+        SpotifyDownloaderError::InvalidFormat(format!("Unsupported report format: {} (use .json or .csv)", path.display()))
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,116 @@
+use crate::config::{AudioFormat, FormatSelectionConfig};
+use crate::downloader::converter::SourceCodec;
+use crate::downloader::youtube::YtDlpFormat;
+use serde::{Deserialize, Serialize};
+
+/// The yt-dlp format picked for a download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectedFormat {
+    pub format_id: String,
+    pub ext: String,
+    pub acodec: Option<String>,
+    pub abr: Option<f32>,
+    pub asr: Option<u32>,
+    pub format_note: Option<String>,
+}
+
+impl std::fmt::Display for SelectedFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}", self.format_id, self.acodec.as_deref().unwrap_or("unknown codec"))?;
+        if let Some(abr) = self.abr {
+            write!(f, ", {:.0} kbps", abr)?;
+        }
+        if let Some(asr) = self.asr {
+            write!(f, ", {} Hz", asr)?;
+        }
+        write!(f, ", {})", self.ext)
+    }
+}
+
+impl SelectedFormat {
+    /// Codec of the selected stream
+    pub fn codec(&self) -> Option<SourceCodec> {
+        self.acodec.as_deref().and_then(SourceCodec::from_acodec)
+    }
+}
+
+/// Ranks the formats yt-dlp offers for a video by the configured preferences
+pub struct FormatSelector {
+    config: FormatSelectionConfig,
+}
+
+impl FormatSelector {
+    /// Create a selector with the given preferences
+    pub fn new(config: FormatSelectionConfig) -> Self {
+        Self { config }
+    }
+
+    /// Pick the best audio-only format for the target output format
+    pub fn select(&self, formats: &[YtDlpFormat], target: AudioFormat) -> Option<SelectedFormat> {
+        let audio_formats: Vec<&YtDlpFormat> = formats.iter()
+            .filter(|format| Self::is_audio_only(format))
+            .collect();
+
+        // Each filter is dropped again if it would leave nothing to download
+        let mut candidates = audio_formats.clone();
+        if self.config.avoid_drc {
+            let without_drc: Vec<&YtDlpFormat> = candidates.iter()
+                .copied()
+                .filter(|format| !Self::is_drc(format))
+                .collect();
+            if !without_drc.is_empty() {
+                candidates = without_drc;
+            }
+        }
+
+        if self.config.min_abr_kbps > 0 {
+            let above_minimum: Vec<&YtDlpFormat> = candidates.iter()
+                .copied()
+                .filter(|format| format.abr.is_some_and(|abr| abr >= self.config.min_abr_kbps as f32))
+                .collect();
+            if above_minimum.is_empty() {
+                println!("⚠️ No audio format reaches {} kbps, using the best available", self.config.min_abr_kbps);
+            } else {
+                candidates = above_minimum;
+            }
+        }
+
+        candidates.into_iter()
+            .min_by(|a, b| {
+                self.rank(a, target).cmp(&self.rank(b, target))
+                    .then_with(|| b.abr.unwrap_or(0.0).total_cmp(&a.abr.unwrap_or(0.0)))
+            })
+            .map(|format| SelectedFormat {
+                format_id: format.format_id.clone(),
+                ext: format.ext.clone(),
+                acodec: format.acodec.clone(),
+                abr: format.abr,
+                asr: format.asr,
+                format_note: format.format_note.clone(),
+            })
+    }
+
+    /// Lower is better: streams that can be remuxed first, then the configured codec order
+    fn rank(&self, format: &YtDlpFormat, target: AudioFormat) -> (bool, usize) {
+        let codec = format.acodec.as_deref().and_then(SourceCodec::from_acodec);
+        let remuxable = codec.as_ref().is_some_and(|codec| codec.fits(target));
+        let codec_name = codec.map(|codec| codec.to_string().to_lowercase()).unwrap_or_default();
+        let preference = self.config.preferred_codecs.iter()
+            .position(|preferred| preferred.eq_ignore_ascii_case(&codec_name))
+            .unwrap_or(self.config.preferred_codecs.len());
+
+        (!(self.config.prefer_remuxable && remuxable), preference)
+    }
+
+    fn is_audio_only(format: &YtDlpFormat) -> bool {
+        let has_audio = format.acodec.as_deref().is_some_and(|acodec| acodec != "none");
+        let has_video = format.vcodec.as_deref().is_some_and(|vcodec| vcodec != "none");
+        has_audio && !has_video
+    }
+
+    /// YouTube marks dynamic range compressed variants with a "-drc" suffix
+    fn is_drc(format: &YtDlpFormat) -> bool {
+        format.format_id.contains("-drc")
+            || format.format_note.as_deref().is_some_and(|note| note.to_uppercase().contains("DRC"))
+    }
+}
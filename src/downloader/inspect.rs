@@ -0,0 +1,287 @@
+use crate::downloader::metadata::MetadataEmbedder;
+use crate::errors::{Result, SpotifyDownloaderError};
+use lofty::{
+    read_from_path,
+    file::{AudioFile, TaggedFileExt},
+    picture::PictureInformation,
+    tag::{ItemKey, ItemValue},
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Technical information about the audio stream
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct StreamInfo {
+    pub codec: Option<String>,
+    pub bitrate_kbps: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    pub bit_depth: Option<u8>,
+    pub duration_ms: u64,
+}
+
+/// An embedded picture
+#[derive(Debug, Clone, Serialize)]
+pub struct PictureInfo {
+    pub picture_type: String,
+    pub mime_type: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub size_bytes: usize,
+}
+
+/// Where lyrics were found for a file
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct LyricsInfo {
+    pub embedded_synced: bool,
+    pub embedded_unsynced: bool,
+    pub sidecar_lrc: Option<PathBuf>,
+    pub sidecar_txt: Option<PathBuf>,
+}
+
+impl LyricsInfo {
+    /// Short description for tables and console output
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.embedded_synced {
+            parts.push("synced");
+        }
+        if self.embedded_unsynced {
+            parts.push("unsynced");
+        }
+        if self.sidecar_lrc.is_some() {
+            parts.push("lrc file");
+        }
+        if self.sidecar_txt.is_some() {
+            parts.push("txt file");
+        }
+        if parts.is_empty() { "none".to_string() } else { parts.join(", ") }
+    }
+}
+
+/// Everything `inspect` reports about one audio file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileInspection {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub stream: StreamInfo,
+    pub tags: BTreeMap<String, String>,
+    pub pictures: Vec<PictureInfo>,
+    pub lyrics: LyricsInfo,
+}
+
+/// Reads tags, pictures, lyrics and stream properties from audio files
+pub struct TagInspector {
+    metadata_embedder: MetadataEmbedder,
+}
+
+impl TagInspector {
+    /// Create a new tag inspector
+    pub fn new() -> Self {
+        Self {
+            metadata_embedder: MetadataEmbedder::new(),
+        }
+    }
+
+    /// Inspect a single audio file
+    pub fn inspect_file(&self, file_path: &PathBuf) -> Result<FileInspection> {
+        let tagged_file = read_from_path(file_path)
+            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to read file with lofty: {}", e)))?;
+
+        let properties = tagged_file.properties();
+        let stream = StreamInfo {
+            codec: Self::probe_codec(file_path),
+            bitrate_kbps: properties.audio_bitrate().or(properties.overall_bitrate()),
+            sample_rate: properties.sample_rate(),
+            channels: properties.channels(),
+            bit_depth: properties.bit_depth(),
+            duration_ms: properties.duration().as_millis() as u64,
+        };
+
+        let pictures = tagged_file.tags().iter()
+            .flat_map(|tag| tag.pictures())
+            .map(|picture| {
+                let information = PictureInformation::from_picture(picture).ok();
+                PictureInfo {
+                    picture_type: format!("{:?}", picture.pic_type()),
+                    mime_type: picture.mime_type().map(|mime| mime.as_str().to_string()),
+                    width: information.as_ref().map(|info| info.width),
+                    height: information.as_ref().map(|info| info.height),
+                    size_bytes: picture.data().len(),
+                }
+            })
+            .collect();
+
+        let mut lyrics = LyricsInfo::default();
+        for item in tagged_file.tags().iter().flat_map(|tag| tag.items()) {
+            if let (ItemKey::Lyrics, ItemValue::Text(text)) = (item.key(), item.value()) {
+                // Lyrics embedded as LRC text start each line with a [mm:ss.xx] timestamp
+                if text.lines().any(Self::is_lrc_line) {
+                    lyrics.embedded_synced = true;
+                } else if !text.trim().is_empty() {
+                    lyrics.embedded_unsynced = true;
+                }
+            }
+        }
+        if let Ok(id3_tag) = id3::Tag::read_from_path(file_path) {
+            lyrics.embedded_synced |= id3_tag.synchronised_lyrics().next().is_some();
+            lyrics.embedded_unsynced |= id3_tag.lyrics().next().is_some();
+        }
+        let (sidecar_lrc, sidecar_txt) = Self::find_sidecar_lyrics(file_path);
+        lyrics.sidecar_lrc = sidecar_lrc;
+        lyrics.sidecar_txt = sidecar_txt;
+
+        Ok(FileInspection {
+            path: file_path.clone(),
+            file_size: std::fs::metadata(file_path)?.len(),
+            stream,
+            tags: self.metadata_embedder.read_tag_snapshot(file_path)?,
+            pictures,
+            lyrics,
+        })
+    }
+
+    /// Inspect a file or every audio file below a directory, skipping unreadable files
+    pub fn inspect_path(&self, path: &Path) -> Result<Vec<FileInspection>> {
+        let files = crate::downloader::retag::Retagger::collect_audio_files(path)?;
+        let mut inspections = Vec::new();
+        for file in files {
+            match self.inspect_file(&file) {
+                Ok(inspection) => inspections.push(inspection),
+                // stderr, so a skipped file doesn't break --json output
+                Err(e) => eprintln!("⚠️ Skipping {}: {}", file.display(), e),
+            }
+        }
+        Ok(inspections)
+    }
+
+    /// Export inspections as JSON or CSV, chosen by the output file extension
+    pub fn export(&self, inspections: &[FileInspection], output_path: &PathBuf) -> Result<()> {
+        let extension = output_path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        match extension.as_str() {
+            "json" => {
+                let json = serde_json::to_string_pretty(inspections)?;
+                std::fs::write(output_path, json)?;
+            }
+            "csv" => self.export_csv(inspections, output_path)?,
+            _ => {
+                return Err(SpotifyDownloaderError::InvalidFormat(
+                    format!("Unsupported export format: {} (use .json or .csv)", output_path.display())
+                ));
+            }
+        }
+
+        eprintln!("📄 Exported {} file(s) to {}", inspections.len(), output_path.display());
+        Ok(())
+    }
+
+    /// One row per file: fixed technical columns followed by every tag seen in the library
+    fn export_csv(&self, inspections: &[FileInspection], output_path: &PathBuf) -> Result<()> {
+        let tag_columns: BTreeSet<&String> = inspections.iter()
+            .flat_map(|inspection| inspection.tags.keys())
+            .collect();
+
+        let mut writer = csv::Writer::from_path(output_path)
+            .map_err(|e| SpotifyDownloaderError::Unknown(format!("Failed to create CSV: {}", e)))?;
+
+        let mut header = vec![
+            "path", "file_size", "codec", "bitrate_kbps", "sample_rate", "channels",
+            "bit_depth", "duration_ms", "pictures", "lyrics",
+        ];
+        header.extend(tag_columns.iter().map(|column| column.as_str()));
+        writer.write_record(&header)
+            .map_err(|e| SpotifyDownloaderError::Unknown(format!("Failed to write CSV: {}", e)))?;
+
+        for inspection in inspections {
+            let stream = &inspection.stream;
+            let mut row = vec![
+                inspection.path.display().to_string(),
+                inspection.file_size.to_string(),
+                stream.codec.clone().unwrap_or_default(),
+                Self::optional(stream.bitrate_kbps),
+                Self::optional(stream.sample_rate),
+                Self::optional(stream.channels),
+                Self::optional(stream.bit_depth),
+                stream.duration_ms.to_string(),
+                inspection.pictures.iter()
+                    .map(Self::describe_picture)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                inspection.lyrics.summary(),
+            ];
+            row.extend(tag_columns.iter().map(|column| {
+                inspection.tags.get(*column).cloned().unwrap_or_default()
+            }));
+            writer.write_record(&row)
+                .map_err(|e| SpotifyDownloaderError::Unknown(format!("Failed to write CSV: {}", e)))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Human readable picture description, e.g. "CoverFront 800x800 image/jpeg (81234 bytes)"
+    pub fn describe_picture(picture: &PictureInfo) -> String {
+        let dimensions = match (picture.width, picture.height) {
+            (Some(width), Some(height)) => format!(" {}x{}", width, height),
+            _ => String::new(),
+        };
+        format!(
+            "{}{} {} ({} bytes)",
+            picture.picture_type,
+            dimensions,
+            picture.mime_type.as_deref().unwrap_or("unknown"),
+            picture.size_bytes
+        )
+    }
+
+    fn optional<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    fn is_lrc_line(line: &str) -> bool {
+        let bytes = line.as_bytes();
+        bytes.len() > 6 && bytes[0] == b'[' && bytes[1].is_ascii_digit() && bytes[3] == b':'
+    }
+
+    /// Codec name as reported by symphonia
+    fn probe_codec(file_path: &PathBuf) -> Option<String> {
+        let file = File::open(file_path).ok()?;
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .ok()?;
+        let codec = probed.format.default_track()?.codec_params.codec;
+        symphonia::default::get_codecs()
+            .get_codec(codec)
+            .map(|descriptor| descriptor.short_name.to_string())
+    }
+
+    /// LRC/TXT files written to the lyrics/ folder next to the audio file
+    fn find_sidecar_lyrics(file_path: &Path) -> (Option<PathBuf>, Option<PathBuf>) {
+        let (Some(parent), Some(stem)) = (file_path.parent(), file_path.file_stem()) else {
+            return (None, None);
+        };
+        let lyrics_dir = parent.join("lyrics");
+        let stem = stem.to_string_lossy();
+
+        let lrc = lyrics_dir.join(format!("{}.lrc", stem));
+        let txt = lyrics_dir.join(format!("{}.txt", stem));
+        (lrc.exists().then_some(lrc), txt.exists().then_some(txt))
+    }
+}
@@ -0,0 +1,338 @@
+use crate::config::Config;
+use crate::downloader::{DownloadOptions, DownloadStage, TrackMetadata};
+use crate::errors::{Result, SpotifyDownloaderError};
+use crate::utils::fs::LogLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A job store shared between concurrent download tasks
+pub type SharedJobStore = Arc<Mutex<JobStore>>;
+
+/// Lifecycle of a queued download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobStatus::Queued => write!(f, "Queued"),
+            JobStatus::Running => write!(f, "Running"),
+            JobStatus::Completed => write!(f, "Completed"),
+            JobStatus::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+/// One track in the persistent download queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadJob {
+    pub id: String,
+    pub track: TrackMetadata,
+    pub options: DownloadOptions,
+    pub status: JobStatus,
+    /// Last stage the download reached
+    pub stage: Option<DownloadStage>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub output_path: Option<PathBuf>,
+    /// Unix timestamp of the last change
+    pub updated_at: u64,
+}
+
+impl DownloadJob {
+    /// Queued jobs and jobs that were running when the app stopped
+    pub fn is_unfinished(&self) -> bool {
+        matches!(self.status, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// What happened when a track was added to the queue
+#[derive(Debug, Clone)]
+pub enum EnqueueOutcome {
+    Queued,
+    AlreadyQueued,
+    AlreadyCompleted(Option<PathBuf>),
+}
+
+/// Durable download queue kept as an append-only JSON Lines log.
+/// Every change appends the job's full state; the last line for a job wins.
+/// The log is compacted to one line per job when it is opened while no other store has it open.
+pub struct JobStore {
+    path: PathBuf,
+    jobs: HashMap<String, DownloadJob>,
+    order: Vec<String>,
+    writer: BufWriter<File>,
+    lock: LogLock,
+}
+
+impl JobStore {
+    /// Location of the job log in the config directory
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("download_jobs.jsonl"))
+    }
+
+    /// Open the job log in the config directory
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    /// Open a job log, replaying it and compacting it when no other store has it open
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let lock = LogLock::acquire(path)?;
+        let compacted = lock.exclusively(|| {
+            let (order, jobs) = Self::read_log(path)?;
+            Self::write_snapshot(path, &order, &jobs)?;
+            Ok((order, jobs))
+        })?;
+        let (order, jobs) = match compacted {
+            Some(log) => log,
+            None => Self::read_log(path)?,
+        };
+        let writer = Self::open_writer(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            jobs,
+            order,
+            writer,
+            lock,
+        })
+    }
+
+    /// Replay a job log; the last line for a job wins
+    fn read_log(path: &Path) -> Result<(Vec<String>, HashMap<String, DownloadJob>)> {
+        let mut jobs: HashMap<String, DownloadJob> = HashMap::new();
+        let mut order = Vec::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (line_number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // A crash mid-write can leave a truncated last line behind
+                match serde_json::from_str::<DownloadJob>(&line) {
+                    Ok(job) => {
+                        if !jobs.contains_key(&job.id) {
+                            order.push(job.id.clone());
+                        }
+                        jobs.insert(job.id.clone(), job);
+                    }
+                    Err(e) => println!("⚠️ Skipping unreadable job on line {}: {}", line_number + 1, e),
+                }
+            }
+        }
+
+        Ok((order, jobs))
+    }
+
+    fn open_writer(path: &Path) -> Result<BufWriter<File>> {
+        Ok(BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+
+    /// Open the default job log for sharing between tasks
+    pub fn open_shared() -> Result<SharedJobStore> {
+        Ok(Arc::new(Mutex::new(Self::open_default()?)))
+    }
+
+    /// Stable job key: the same track in the same format and folder is the same job
+    pub fn job_id(track: &TrackMetadata, options: &DownloadOptions) -> String {
+        let track_key = if track.id.is_empty() {
+            format!("{} - {}", track.artist, track.title)
+        } else {
+            track.id.clone()
+        };
+        format!("{}|{}|{}", track_key, options.format.extension(), options.output_dir.display())
+    }
+
+    /// Add a track to the queue unless it is already queued or done
+    pub fn enqueue(&mut self, track: &TrackMetadata, options: &DownloadOptions) -> Result<EnqueueOutcome> {
+        let id = Self::job_id(track, options);
+        if let Some(job) = self.jobs.get(&id) {
+            return Ok(match job.status {
+                JobStatus::Completed => EnqueueOutcome::AlreadyCompleted(job.output_path.clone()),
+                JobStatus::Queued | JobStatus::Running => EnqueueOutcome::AlreadyQueued,
+                JobStatus::Failed => {
+                    self.update(&id, |job| {
+                        job.status = JobStatus::Queued;
+                        job.options = options.clone();
+                    })?;
+                    EnqueueOutcome::Queued
+                }
+            });
+        }
+
+        let job = DownloadJob {
+            id: id.clone(),
+            track: track.clone(),
+            options: options.clone(),
+            status: JobStatus::Queued,
+            stage: None,
+            attempts: 0,
+            last_error: None,
+            output_path: None,
+            updated_at: Self::now(),
+        };
+        self.append(&job)?;
+        self.order.push(id.clone());
+        self.jobs.insert(id, job);
+        Ok(EnqueueOutcome::Queued)
+    }
+
+    /// Record that a download attempt started
+    pub fn mark_running(&mut self, id: &str) -> Result<()> {
+        self.update(id, |job| {
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+        })
+    }
+
+    /// Record the stage a running download reached, skipping repeats of the same stage
+    pub fn update_stage(&mut self, id: &str, stage: DownloadStage) -> Result<()> {
+        if self.jobs.get(id).is_some_and(|job| job.stage.as_ref() == Some(&stage)) {
+            return Ok(());
+        }
+        self.update(id, |job| job.stage = Some(stage))
+    }
+
+    /// Put a download that was stopped part way back in the queue
+    pub fn mark_queued(&mut self, id: &str) -> Result<()> {
+        self.update(id, |job| job.status = JobStatus::Queued)
+    }
+
+    /// Record a finished download
+    pub fn mark_completed(&mut self, id: &str, output_path: &Path) -> Result<()> {
+        self.update(id, |job| {
+            job.status = JobStatus::Completed;
+            job.stage = Some(DownloadStage::Completed);
+            job.output_path = Some(output_path.to_path_buf());
+            job.last_error = None;
+        })
+    }
+
+    /// Record a failed download
+    pub fn mark_failed(&mut self, id: &str, error: &str) -> Result<()> {
+        self.update(id, |job| {
+            job.status = JobStatus::Failed;
+            job.last_error = Some(error.to_string());
+        })
+    }
+
+    /// Put every failed job back in the queue, returning how many there were
+    pub fn requeue_failed(&mut self) -> Result<usize> {
+        let failed: Vec<String> = self.order.iter()
+            .filter(|id| self.jobs.get(*id).is_some_and(|job| job.status == JobStatus::Failed))
+            .cloned()
+            .collect();
+        for id in &failed {
+            self.update(id, |job| job.status = JobStatus::Queued)?;
+        }
+        Ok(failed.len())
+    }
+
+    /// Jobs that still need to run, in the order they were queued
+    pub fn unfinished(&self) -> Vec<DownloadJob> {
+        self.jobs().filter(|job| job.is_unfinished()).cloned().collect()
+    }
+
+    /// All jobs in the order they were queued
+    pub fn jobs(&self) -> impl Iterator<Item = &DownloadJob> {
+        self.order.iter().filter_map(|id| self.jobs.get(id))
+    }
+
+    /// Drop finished jobs (or every job) from the log, returning how many were removed.
+    /// Fails while another store has the log open, since it would keep writing to the old file.
+    pub fn clear(&mut self, include_unfinished: bool) -> Result<usize> {
+        let path = &self.path;
+        let cleared = self.lock.exclusively(|| {
+            // Other stores may have appended since this one was opened
+            let (mut order, mut jobs) = Self::read_log(path)?;
+            let before = order.len();
+            order.retain(|id| {
+                let keep = !include_unfinished && jobs.get(id).is_some_and(|job| job.is_unfinished());
+                if !keep {
+                    jobs.remove(id);
+                }
+                keep
+            });
+            Self::write_snapshot(path, &order, &jobs)?;
+            Ok((before - order.len(), order, jobs))
+        })?;
+        self.writer = Self::open_writer(&self.path)?;
+
+        let (removed, order, jobs) = cleared.ok_or_else(|| SpotifyDownloaderError::Unknown(
+            "The download queue is in use by another download; try again once it finishes".to_string(),
+        ))?;
+        self.order = order;
+        self.jobs = jobs;
+        Ok(removed)
+    }
+
+    /// Path of the job log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn update(&mut self, id: &str, change: impl FnOnce(&mut DownloadJob)) -> Result<()> {
+        let job = self.jobs.get_mut(id)
+            .ok_or_else(|| SpotifyDownloaderError::Unknown(format!("Unknown download job: {}", id)))?;
+        change(job);
+        job.updated_at = Self::now();
+        let job = job.clone();
+        self.append(&job)
+    }
+
+    fn append(&mut self, job: &DownloadJob) -> Result<()> {
+        let line = serde_json::to_string(job)?;
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Rewrite the log with one line per job, replacing the old file atomically
+    fn write_snapshot(path: &Path, order: &[String], jobs: &HashMap<String, DownloadJob>) -> Result<()> {
+        let temp_path = path.with_extension("jsonl.tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            for job in order.iter().filter_map(|id| jobs.get(id)) {
+                writeln!(writer, "{}", serde_json::to_string(job)?)?;
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Apply a change to a shared store, logging instead of failing the download when it can't be saved
+pub fn record(store: Option<&SharedJobStore>, change: impl FnOnce(&mut JobStore) -> Result<()>) {
+    let Some(store) = store else { return };
+    match store.lock() {
+        Ok(mut store) => {
+            if let Err(e) = change(&mut store) {
+                println!("⚠️ Failed to update the download queue: {}", e);
+            }
+        }
+        Err(e) => println!("⚠️ Download queue unavailable: {}", e),
+    }
+}
@@ -0,0 +1,380 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::errors::{Result, SpotifyDownloaderError};
+
+/// ReplayGain 2.0 reference level in LUFS
+pub const REPLAYGAIN_REFERENCE_LUFS: f64 = -18.0;
+
+/// Absolute gating threshold from EBU R128 / ITU-R BS.1770
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Relative gating threshold below the ungated loudness
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// Oversampling factor used for true peak estimation
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+/// Taps per polyphase branch of the true peak interpolation filter
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Result of analysing a single audio file
+#[derive(Debug, Clone)]
+pub struct LoudnessAnalysis {
+    /// Integrated loudness in LUFS
+    pub integrated_lufs: f64,
+    /// True peak as a linear amplitude (1.0 = full scale)
+    pub true_peak: f64,
+    /// Channel-weighted mean square of every 400ms gating block, kept for album aggregation
+    block_powers: Vec<f64>,
+}
+
+impl LoudnessAnalysis {
+    /// Gain in dB needed to reach the ReplayGain reference level
+    pub fn gain_db(&self) -> f64 {
+        REPLAYGAIN_REFERENCE_LUFS - self.integrated_lufs
+    }
+}
+
+/// Track and (optionally) album ReplayGain values ready to be written as tags
+#[derive(Debug, Clone)]
+pub struct ReplayGainInfo {
+    pub track_gain_db: f64,
+    pub track_peak: f64,
+    pub album_gain_db: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+impl ReplayGainInfo {
+    /// Build track-only ReplayGain values from an analysis
+    pub fn from_track(analysis: &LoudnessAnalysis) -> Self {
+        Self {
+            track_gain_db: analysis.gain_db(),
+            track_peak: analysis.true_peak,
+            album_gain_db: None,
+            album_peak: None,
+        }
+    }
+
+    /// Attach album values computed from the whole album
+    pub fn with_album(mut self, album: &LoudnessAnalysis) -> Self {
+        self.album_gain_db = Some(album.gain_db());
+        self.album_peak = Some(album.true_peak);
+        self
+    }
+}
+
+/// EBU R128 loudness analyzer built on symphonia decoding
+pub struct LoudnessAnalyzer;
+
+impl LoudnessAnalyzer {
+    /// Decode a file and measure its integrated loudness and true peak
+    pub async fn analyze_file(file_path: &Path) -> Result<LoudnessAnalysis> {
+        let path = file_path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::analyze_file_blocking(&path))
+            .await
+            .map_err(|e| SpotifyDownloaderError::Loudness(format!("Loudness analysis task failed: {}", e)))?
+    }
+
+    /// Combine per-track analyses into a single album measurement
+    pub fn album_loudness(tracks: &[LoudnessAnalysis]) -> Option<LoudnessAnalysis> {
+        if tracks.is_empty() {
+            return None;
+        }
+
+        let block_powers: Vec<f64> = tracks.iter()
+            .flat_map(|track| track.block_powers.iter().copied())
+            .collect();
+        let true_peak = tracks.iter()
+            .map(|track| track.true_peak)
+            .fold(0.0, f64::max);
+
+        Some(LoudnessAnalysis {
+            integrated_lufs: gated_loudness(&block_powers),
+            true_peak,
+            block_powers,
+        })
+    }
+
+    fn analyze_file_blocking(file_path: &PathBuf) -> Result<LoudnessAnalysis> {
+        let file = File::open(file_path)
+            .map_err(|e| SpotifyDownloaderError::Loudness(format!("Failed to open file for loudness analysis: {}", e)))?;
+
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| SpotifyDownloaderError::Loudness(format!("Failed to probe file: {}", e)))?;
+
+        let mut format = probed.format;
+        let track = format.default_track()
+            .ok_or_else(|| SpotifyDownloaderError::Loudness("No audio track found".to_string()))?;
+        let track_id = track.id;
+
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| SpotifyDownloaderError::Loudness(format!("Unsupported codec: {}", e)))?;
+
+        let mut meter: Option<LoudnessMeter> = None;
+        let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(SymphoniaError::ResetRequired) => break,
+                Err(e) => return Err(SpotifyDownloaderError::Loudness(format!("Failed to read packet: {}", e))),
+            };
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // Skip corrupt packets rather than failing the whole analysis
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(SpotifyDownloaderError::Loudness(format!("Failed to decode audio: {}", e))),
+            };
+
+            let spec = *decoded.spec();
+            let buffer = sample_buffer.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, spec));
+            if buffer.capacity() < decoded.capacity() * spec.channels.count() {
+                *buffer = SampleBuffer::new(decoded.capacity() as u64, spec);
+            }
+            buffer.copy_interleaved_ref(decoded);
+
+            meter
+                .get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels.count()))
+                .process_interleaved(buffer.samples());
+        }
+
+        let meter = meter.ok_or_else(|| SpotifyDownloaderError::Loudness("No audio decoded".to_string()))?;
+        Ok(meter.finish())
+    }
+}
+
+/// Second-order IIR section in transposed direct form II
+#[derive(Clone)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    /// BS.1770 stage 1: high shelf modelling the acoustic effect of the head
+    fn high_shelf(sample_rate: f64) -> Self {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// BS.1770 stage 2: RLB high-pass filter
+    fn high_pass(sample_rate: f64) -> Self {
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+/// Streaming K-weighted loudness and true peak meter
+struct LoudnessMeter {
+    channels: usize,
+    channel_weights: Vec<f64>,
+    filters: Vec<(Biquad, Biquad)>,
+    /// Samples per 100ms step (a quarter of a gating block)
+    step_len: usize,
+    step_position: usize,
+    step_energy: Vec<f64>,
+    /// Weighted energy of the last completed steps, used to build overlapping blocks
+    recent_steps: Vec<f64>,
+    block_powers: Vec<f64>,
+    peak_filter: Vec<f64>,
+    peak_history: Vec<Vec<f64>>,
+    true_peak: f64,
+}
+
+impl LoudnessMeter {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        let rate = sample_rate as f64;
+        let channel_weights = (0..channels)
+            .map(|index| match (channels, index) {
+                // 5.1 layout: L R C LFE Ls Rs - LFE is ignored, surrounds are boosted
+                (6, 3) => 0.0,
+                (6, 4) | (6, 5) => 1.41,
+                _ => 1.0,
+            })
+            .collect();
+
+        Self {
+            channels,
+            channel_weights,
+            filters: (0..channels)
+                .map(|_| (Biquad::high_shelf(rate), Biquad::high_pass(rate)))
+                .collect(),
+            step_len: ((sample_rate / 10) as usize).max(1),
+            step_position: 0,
+            step_energy: vec![0.0; channels],
+            recent_steps: Vec::with_capacity(4),
+            block_powers: Vec::new(),
+            peak_filter: interpolation_filter(),
+            peak_history: vec![vec![0.0; TRUE_PEAK_TAPS]; channels],
+            true_peak: 0.0,
+        }
+    }
+
+    fn process_interleaved(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let sample = sample as f64;
+                self.track_true_peak(channel, sample);
+
+                let (shelf, high_pass) = &mut self.filters[channel];
+                let weighted = high_pass.process(shelf.process(sample));
+                self.step_energy[channel] += weighted * weighted;
+            }
+
+            self.step_position += 1;
+            if self.step_position == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// Close a 100ms step and emit a 400ms block once four steps are available
+    fn finish_step(&mut self) {
+        let weighted_energy: f64 = self.step_energy.iter()
+            .zip(&self.channel_weights)
+            .map(|(energy, weight)| energy * weight)
+            .sum();
+
+        if self.recent_steps.len() == 4 {
+            self.recent_steps.remove(0);
+        }
+        self.recent_steps.push(weighted_energy);
+
+        if self.recent_steps.len() == 4 {
+            let block_len = (self.step_len * 4) as f64;
+            self.block_powers.push(self.recent_steps.iter().sum::<f64>() / block_len);
+        }
+
+        self.step_energy.iter_mut().for_each(|energy| *energy = 0.0);
+        self.step_position = 0;
+    }
+
+    /// Estimate inter-sample peaks with a polyphase windowed-sinc interpolator
+    fn track_true_peak(&mut self, channel: usize, sample: f64) {
+        let history = &mut self.peak_history[channel];
+        history.rotate_right(1);
+        history[0] = sample;
+
+        self.true_peak = self.true_peak.max(sample.abs());
+        for phase in 1..TRUE_PEAK_OVERSAMPLING {
+            let interpolated: f64 = history.iter()
+                .enumerate()
+                .map(|(tap, value)| value * self.peak_filter[tap * TRUE_PEAK_OVERSAMPLING + phase])
+                .sum();
+            self.true_peak = self.true_peak.max(interpolated.abs());
+        }
+    }
+
+    fn finish(self) -> LoudnessAnalysis {
+        LoudnessAnalysis {
+            integrated_lufs: gated_loudness(&self.block_powers),
+            true_peak: self.true_peak,
+            block_powers: self.block_powers,
+        }
+    }
+}
+
+/// Windowed-sinc low-pass filter for true peak oversampling, laid out as interleaved phases
+fn interpolation_filter() -> Vec<f64> {
+    let length = TRUE_PEAK_TAPS * TRUE_PEAK_OVERSAMPLING;
+    let center = (length - 1) as f64 / 2.0;
+
+    (0..length)
+        .map(|n| {
+            let x = (n as f64 - center) / TRUE_PEAK_OVERSAMPLING as f64;
+            let sinc = if x.abs() < 1e-9 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / (length - 1) as f64).cos();
+            sinc * window
+        })
+        .collect()
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Apply the absolute and relative gates to block powers and return integrated loudness
+fn gated_loudness(block_powers: &[f64]) -> f64 {
+    let absolute_gated: Vec<f64> = block_powers.iter()
+        .copied()
+        .filter(|power| power_to_lufs(*power) > ABSOLUTE_GATE_LUFS)
+        .collect();
+
+    if absolute_gated.is_empty() {
+        return ABSOLUTE_GATE_LUFS;
+    }
+
+    let ungated_mean = absolute_gated.iter().sum::<f64>() / absolute_gated.len() as f64;
+    let relative_gate = power_to_lufs(ungated_mean) + RELATIVE_GATE_LU;
+
+    let relative_gated: Vec<f64> = absolute_gated.into_iter()
+        .filter(|power| power_to_lufs(*power) > relative_gate)
+        .collect();
+
+    if relative_gated.is_empty() {
+        return power_to_lufs(ungated_mean);
+    }
+
+    power_to_lufs(relative_gated.iter().sum::<f64>() / relative_gated.len() as f64)
+}
@@ -4,24 +4,28 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use std::fs::File;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // For metadata writing
 use id3::{Tag, TagLike, Version, frame::Picture as Id3Picture};
 use lofty::{
     read_from_path, 
     file::{AudioFile, TaggedFileExt}, 
-    tag::{Tag as LoftyTag, TagType, ItemKey}, 
-    picture::Picture as LoftyPicture,
+    tag::{Tag as LoftyTag, TagType, ItemKey, ItemValue}, 
+    picture::{Picture as LoftyPicture, PictureType},
     config::WriteOptions
 };
 
 use crate::errors::SpotifyDownloaderError;
 use crate::downloader::{DownloadOptions, TrackMetadata};
+use crate::downloader::loudness::ReplayGainInfo;
 use crate::lyrics::LyricsResult;
 
 type Result<T> = std::result::Result<T, SpotifyDownloaderError>;
 
+/// Custom tag holding the Spotify track ID, used to re-identify files later
+pub const SPOTIFY_TRACK_ID_TAG: &str = "SPOTIFY_TRACK_ID";
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct JsMetadataResult {
     pub success: bool,
@@ -139,6 +143,9 @@ impl MetadataEmbedder {
             album_cover_url: None,
             composer,
             comment,
+            audio_features: None,
+            isrc: None,
+            total_tracks: None,
         })
     }
 
@@ -169,14 +176,23 @@ impl MetadataEmbedder {
             .to_lowercase();
         
         match extension.as_str() {
-            "mp3" => {
-                self.embed_mp3_metadata(file_path, track, cover_art_data, lyrics_data, options).await?;
+            "mp3" | "aiff" | "aif" => {
+                self.embed_id3_metadata(file_path, track, cover_art_data, lyrics_data, options).await?;
             }
             "m4a" | "mp4" => {
                 // Use lofty for M4A/MP4 with separate lyrics folder (like FLAC/WAV)
                 self.embed_with_lyrics_folder(file_path, track, cover_art_data, lyrics_data, options).await?;
             }
-            "flac" | "wav" => {
+            "wav" => {
+                // RIFF INFO has no custom fields, so WAV gets an ID3 chunk like AIFF to keep the SPOTIFY_* tags
+                self.embed_id3_metadata(file_path, track, cover_art_data, lyrics_data, options).await?;
+                self.write_lyrics_files(file_path, track, lyrics_data).await?;
+            }
+            "flac" => {
+                self.embed_with_lyrics_folder(file_path, track, cover_art_data, lyrics_data, options).await?;
+            }
+            "ogg" | "opus" => {
+                // Vorbis comments carry the cover as METADATA_BLOCK_PICTURE and lyrics as LYRICS
                 self.embed_with_lyrics_folder(file_path, track, cover_art_data, lyrics_data, options).await?;
             }
             _ => {
@@ -188,16 +204,16 @@ impl MetadataEmbedder {
         Ok(())
     }
 
-    /// Embed metadata for MP3 files using id3 crate
-    async fn embed_mp3_metadata(
+    /// Embed metadata for MP3, AIFF and WAV files using id3 crate (it detects the AIFF and WAV ID3 chunks itself)
+    async fn embed_id3_metadata(
         &self,
         file_path: &PathBuf,
         track: &TrackMetadata,
         cover_art_data: Option<&Vec<u8>>,
         lyrics_data: Option<&LyricsResult>,
-        _options: &DownloadOptions,
+        options: &DownloadOptions,
     ) -> Result<()> {
-        println!("📝 Embedding MP3 metadata using id3");
+        println!("📝 Embedding ID3 metadata using id3");
         
         // Read existing tag or create new one
         let mut tag = match Tag::read_from_path(file_path) {
@@ -245,6 +261,35 @@ impl MetadataEmbedder {
                 text: self.format_metadata_string(comment),
             })));
         }
+        if let Some(spotify_id) = track.spotify_track_id() {
+            tag.remove_extended_text(Some(SPOTIFY_TRACK_ID_TAG), None);
+            tag.add_frame(id3::frame::ExtendedText {
+                description: SPOTIFY_TRACK_ID_TAG.to_string(),
+                value: spotify_id,
+            });
+        }
+        if let Some(isrc) = &track.isrc {
+            tag.set_text("TSRC", isrc.clone());
+        }
+        if let Some(features) = &track.audio_features {
+            if options.embed_bpm {
+                if let Some(bpm) = features.bpm() {
+                    tag.set_text("TBPM", bpm.to_string());
+                }
+                if let Some(key) = features.key_name(options.key_notation) {
+                    tag.set_text("TKEY", key);
+                }
+            }
+            if options.embed_audio_features {
+                for (description, value) in features.custom_tags() {
+                    tag.remove_extended_text(Some(description), None);
+                    tag.add_frame(id3::frame::ExtendedText {
+                        description: description.to_string(),
+                        value,
+                    });
+                }
+            }
+        }
         
         // Add cover art first
         if let Some(cover_data) = cover_art_data {
@@ -260,7 +305,7 @@ impl MetadataEmbedder {
         // Add lyrics - prioritize synced over unsynced
         if let Some(lyrics) = lyrics_data {
             if let Some(synced) = &lyrics.synced {
-                println!("📝 Adding synced lyrics to MP3: {} lines", synced.lines.len());
+                println!("📝 Adding synced lyrics to ID3 tag: {} lines", synced.lines.len());
                 // Add synced lyrics (SYLT) - proper ID3v2.4 SYLT frame
                 let sync_lyrics = id3::frame::SynchronisedLyrics {
                     lang: "eng".to_string(),
@@ -272,9 +317,9 @@ impl MetadataEmbedder {
                     description: "Synced Lyrics".to_string(),
                 };
                 tag.add_frame(id3::frame::Frame::with_content("SYLT", id3::frame::Content::SynchronisedLyrics(sync_lyrics)));
-                println!("✅ Synced lyrics added to MP3");
+                println!("✅ Synced lyrics added to ID3 tag");
             } else if let Some(unsynced) = &lyrics.unsynced {
-                println!("📝 Adding unsynced lyrics to MP3: {} characters", unsynced.text.len());
+                println!("📝 Adding unsynced lyrics to ID3 tag: {} characters", unsynced.text.len());
                 // Add unsynced lyrics (USLT) - proper ID3v2.4 USLT frame
                 let lyrics_frame = id3::frame::Lyrics {
                     lang: "eng".to_string(),
@@ -282,21 +327,21 @@ impl MetadataEmbedder {
                     text: unsynced.text.clone(),
                 };
                 tag.add_frame(id3::frame::Frame::with_content("USLT", id3::frame::Content::Lyrics(lyrics_frame)));
-                println!("✅ Unsynced lyrics added to MP3");
+                println!("✅ Unsynced lyrics added to ID3 tag");
             }
         } else {
-            println!("⚠️ No lyrics data provided for MP3 embedding");
+            println!("⚠️ No lyrics data provided for ID3 embedding");
         }
         
         // Write the tag
         tag.write_to_path(file_path, Version::Id3v24)
-            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to write MP3 metadata: {}", e)))?;
+            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to write ID3 metadata: {}", e)))?;
         
         Ok(())
     }
 
 
-    /// Embed metadata for FLAC/WAV/M4A/MP4 files with separate lyrics folder
+    /// Embed metadata for FLAC/M4A/MP4/OGG/OPUS files with separate lyrics folder
     async fn embed_with_lyrics_folder(
         &self,
         file_path: &PathBuf,
@@ -305,12 +350,22 @@ impl MetadataEmbedder {
         lyrics_data: Option<&LyricsResult>,
         options: &DownloadOptions,
     ) -> Result<()> {
-        println!("📝 Embedding FLAC/WAV/M4A/MP4 metadata with lyrics folder");
+        println!("📝 Embedding FLAC/M4A/MP4/OGG/OPUS metadata with lyrics folder");
         
         // First embed basic metadata AND lyrics using lofty
         self.embed_lofty_metadata(file_path, track, cover_art_data, lyrics_data, options).await?;
         
         // Also create lyrics folder and save LRC/TXT file if we have lyrics
+        self.write_lyrics_files(file_path, track, lyrics_data).await
+    }
+
+    /// Save lyrics next to the file in the lyrics folder, as LRC when synced and TXT otherwise
+    async fn write_lyrics_files(
+        &self,
+        file_path: &PathBuf,
+        track: &TrackMetadata,
+        lyrics_data: Option<&LyricsResult>,
+    ) -> Result<()> {
         if let Some(lyrics) = lyrics_data {
             if let Some(synced) = &lyrics.synced {
                 self.create_lyrics_folder_and_lrc(file_path, track, synced).await?;
@@ -449,7 +504,7 @@ impl MetadataEmbedder {
         track: &TrackMetadata,
         cover_art_data: Option<&Vec<u8>>,
         lyrics_data: Option<&LyricsResult>,
-        _options: &DownloadOptions,
+        options: &DownloadOptions,
     ) -> Result<()> {
         println!("📝 Embedding metadata using lofty");
         
@@ -464,9 +519,9 @@ impl MetadataEmbedder {
             .to_lowercase();
         
         let tag_type = match extension.as_str() {
-            "flac" | "ogg" => TagType::VorbisComments,
+            "flac" | "ogg" | "opus" => TagType::VorbisComments,
             "mp4" | "m4a" => TagType::Mp4Ilst,
-            "aac" | "wav" | "aiff" | "mpc" => TagType::Id3v2, // Formats that support ID3v2
+            "aac" | "wav" | "aiff" | "aif" | "mpc" => TagType::Id3v2, // Formats that support ID3v2
             _ => TagType::VorbisComments, // Default fallback
         };
         
@@ -513,10 +568,36 @@ impl MetadataEmbedder {
         if let Some(comment) = &track.comment {
             tag.insert_text(ItemKey::Comment, self.format_metadata_string(comment));
         }
+        if let Some(spotify_id) = track.spotify_track_id() {
+            tag.insert_text(Self::custom_item_key(tag.tag_type(), SPOTIFY_TRACK_ID_TAG), spotify_id);
+        }
+        if let Some(isrc) = &track.isrc {
+            tag.insert_text(ItemKey::Isrc, isrc.clone());
+        }
+        if let Some(features) = &track.audio_features {
+            let tag_type = tag.tag_type();
+            if options.embed_bpm {
+                if let Some(bpm) = features.bpm() {
+                    // Vorbis comments have no integer BPM field, MP4 and ID3 expect one
+                    let bpm_key = if tag_type == TagType::VorbisComments { ItemKey::Bpm } else { ItemKey::IntegerBpm };
+                    tag.insert_text(bpm_key, bpm.to_string());
+                }
+                if let Some(key) = features.key_name(options.key_notation) {
+                    tag.insert_text(ItemKey::InitialKey, key);
+                }
+            }
+            if options.embed_audio_features {
+                for (name, value) in features.custom_tags() {
+                    tag.insert_text(Self::custom_item_key(tag_type, name), value);
+                }
+            }
+        }
         
         // Add cover art
         if let Some(cover_data) = cover_art_data {
-            if let Ok(picture) = LoftyPicture::from_reader(&mut cover_data.as_slice()) {
+            if let Ok(mut picture) = LoftyPicture::from_reader(&mut cover_data.as_slice()) {
+                // Players look for the front cover type, especially in Vorbis comments
+                picture.set_pic_type(PictureType::CoverFront);
                 tag.set_picture(0, picture);
             }
         }
@@ -550,6 +631,190 @@ impl MetadataEmbedder {
         Ok(())
     }
 
+    /// Read the Spotify track ID embedded by a previous download, if any
+    pub fn read_spotify_track_id(&self, file_path: &PathBuf) -> Option<String> {
+        let tagged_file = read_from_path(file_path).ok()?;
+        let spotify_id = tagged_file.tags().iter()
+            .flat_map(|tag| tag.items())
+            .find_map(|item| match (item.key(), item.value()) {
+                (ItemKey::Unknown(key), ItemValue::Text(value)) if key.ends_with(SPOTIFY_TRACK_ID_TAG) => {
+                    Some(value.clone())
+                }
+                _ => None,
+            });
+        spotify_id
+    }
+
+    /// Read the ISRC tag of a file, if any
+    pub fn read_isrc(&self, file_path: &PathBuf) -> Option<String> {
+        let tagged_file = read_from_path(file_path).ok()?;
+        let isrc = tagged_file.tags().iter()
+            .find_map(|tag| tag.get_string(&ItemKey::Isrc).map(|value| value.to_string()));
+        isrc
+    }
+
+    /// Read the playing time of a file in milliseconds, if it can be determined
+    pub fn read_duration_ms(&self, file_path: &PathBuf) -> Option<u32> {
+        let tagged_file = read_from_path(file_path).ok()?;
+        let duration_ms = tagged_file.properties().duration().as_millis() as u32;
+        (duration_ms > 0).then_some(duration_ms)
+    }
+
+    /// Read all tag values of a file as display strings, keyed by tag name.
+    /// Values of the primary tag use the plain name; other tags (e.g. ID3v1 or APE next to ID3v2)
+    /// are prefixed with their tag type, like "Id3v1:TrackTitle".
+    pub fn read_tag_snapshot(&self, file_path: &PathBuf) -> Result<BTreeMap<String, String>> {
+        let tagged_file = read_from_path(file_path)
+            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to read file with lofty: {}", e)))?;
+
+        let mut snapshot = BTreeMap::new();
+        let primary_type = tagged_file.primary_tag()
+            .or_else(|| tagged_file.first_tag())
+            .map(|tag| tag.tag_type());
+        for tag in tagged_file.tags() {
+            let prefix = if Some(tag.tag_type()) == primary_type {
+                String::new()
+            } else {
+                format!("{:?}:", tag.tag_type())
+            };
+            Self::snapshot_tag(tag, &prefix, &mut snapshot);
+        }
+        Ok(snapshot)
+    }
+
+    fn snapshot_tag(tag: &LoftyTag, prefix: &str, snapshot: &mut BTreeMap<String, String>) {
+        for item in tag.items() {
+            let name = match item.key() {
+                ItemKey::Unknown(key) => format!("{}{}", prefix, key),
+                key => format!("{}{:?}", prefix, key),
+            };
+            let value = match item.value() {
+                // Lyrics are too long to diff line by line, a summary is enough
+                ItemValue::Text(text) if matches!(item.key(), ItemKey::Lyrics) => {
+                    format!("<{} characters>", text.len())
+                }
+                ItemValue::Text(text) | ItemValue::Locator(text) => text.clone(),
+                ItemValue::Binary(data) => format!("<{} bytes>", data.len()),
+            };
+            snapshot.entry(name)
+                .and_modify(|existing: &mut String| {
+                    existing.push_str("; ");
+                    existing.push_str(&value);
+                })
+                .or_insert(value);
+        }
+
+        if let Some(picture) = tag.pictures().first() {
+            snapshot.insert(
+                format!("{}Pictures", prefix),
+                format!("{} ({} bytes)", tag.picture_count(), picture.data().len()),
+            );
+        }
+    }
+
+    /// Build a custom tag key: freeform atom for MP4, TXXX description or Vorbis field otherwise
+    fn custom_item_key(tag_type: TagType, name: &str) -> ItemKey {
+        match tag_type {
+            TagType::Mp4Ilst => ItemKey::Unknown(format!("----:com.apple.iTunes:{}", name)),
+            _ => ItemKey::Unknown(name.to_string()),
+        }
+    }
+
+    /// Write ReplayGain track (and album, when known) values to the file's tags
+    pub async fn embed_replaygain(&self, file_path: &PathBuf, replaygain: &ReplayGainInfo) -> Result<()> {
+        println!(
+            "🔊 Writing ReplayGain: track {:+.2} dB, peak {:.6}",
+            replaygain.track_gain_db, replaygain.track_peak
+        );
+
+        let extension = file_path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let values = Self::replaygain_values(replaygain);
+
+        if matches!(extension.as_str(), "mp3" | "aiff" | "aif" | "wav") {
+            // ID3 stores ReplayGain as TXXX frames keyed by description
+            let mut tag = Tag::read_from_path(file_path).unwrap_or_else(|_| Tag::new());
+            for (description, value) in &values {
+                tag.remove_extended_text(Some(description), None);
+                tag.add_frame(id3::frame::ExtendedText {
+                    description: description.to_string(),
+                    value: value.clone(),
+                });
+            }
+            tag.write_to_path(file_path, Version::Id3v24)
+                .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to write ReplayGain to ID3: {}", e)))?;
+            return Ok(());
+        }
+
+        let mut tagged_file = read_from_path(file_path)
+            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to read file with lofty: {}", e)))?;
+
+        let tag_type = tagged_file.primary_tag_type();
+        let tag = if let Some(tag) = tagged_file.primary_tag_mut() {
+            tag
+        } else {
+            tagged_file.insert_tag(LoftyTag::new(tag_type));
+            tagged_file.primary_tag_mut().unwrap()
+        };
+
+        // Lofty maps these to REPLAYGAIN_* comments, TXXX frames or ----:com.apple.iTunes atoms
+        for (description, value) in values {
+            let key = match description {
+                "REPLAYGAIN_TRACK_GAIN" => ItemKey::ReplayGainTrackGain,
+                "REPLAYGAIN_TRACK_PEAK" => ItemKey::ReplayGainTrackPeak,
+                "REPLAYGAIN_ALBUM_GAIN" => ItemKey::ReplayGainAlbumGain,
+                _ => ItemKey::ReplayGainAlbumPeak,
+            };
+            tag.insert_text(key, value);
+        }
+
+        if tag_type == TagType::Mp4Ilst {
+            // iTunes ignores ReplayGain and reads Sound Check data instead
+            let gain = replaygain.album_gain_db.unwrap_or(replaygain.track_gain_db);
+            let peak = replaygain.album_peak.unwrap_or(replaygain.track_peak);
+            tag.insert_text(
+                ItemKey::Unknown("----:com.apple.iTunes:iTunNORM".to_string()),
+                Self::itunnorm_value(gain, peak),
+            );
+        }
+
+        tagged_file.save_to_path(file_path, WriteOptions::default())
+            .map_err(|e| SpotifyDownloaderError::Metadata(format!("Failed to save ReplayGain with lofty: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Format ReplayGain values as (tag name, value) pairs
+    fn replaygain_values(replaygain: &ReplayGainInfo) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("REPLAYGAIN_TRACK_GAIN", format!("{:.2} dB", replaygain.track_gain_db)),
+            ("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", replaygain.track_peak)),
+        ];
+        if let Some(album_gain) = replaygain.album_gain_db {
+            values.push(("REPLAYGAIN_ALBUM_GAIN", format!("{:.2} dB", album_gain)));
+        }
+        if let Some(album_peak) = replaygain.album_peak {
+            values.push(("REPLAYGAIN_ALBUM_PEAK", format!("{:.6}", album_peak)));
+        }
+        values
+    }
+
+    /// Build an iTunNORM Sound Check string from a gain in dB and a linear peak
+    fn itunnorm_value(gain_db: f64, peak: f64) -> String {
+        let scale = 10f64.powf(-gain_db / 10.0);
+        let milliwatt = (1000.0 * scale).round().clamp(0.0, 65534.0) as u32;
+        let two_and_half_milliwatt = (2500.0 * scale).round().clamp(0.0, 65534.0) as u32;
+        let peak_sample = (peak * 32768.0).round().clamp(0.0, 65535.0) as u32;
+
+        format!(
+            " {:08X} {:08X} {:08X} {:08X} 00024CA8 00024CA8 {:08X} {:08X} 00024CA8 00024CA8",
+            milliwatt, milliwatt, two_and_half_milliwatt, two_and_half_milliwatt, peak_sample, peak_sample
+        )
+    }
+
     /// Verify metadata embedding
     pub async fn verify_metadata_embedding(
         &self,
@@ -5,15 +5,29 @@ pub mod soundcloud;
 pub mod yt_dlp;
 pub mod audio;
 pub mod converter;
+pub mod format_selection;
 pub mod covers;
 pub mod metadata;
+pub mod loudness;
+pub mod trim;
+pub mod album_split;
+pub mod job_store;
+pub mod archive;
+pub mod plan;
+pub mod failure_report;
+pub mod control;
+pub mod services;
+pub mod pipeline;
+pub mod retag;
+pub mod inspect;
 pub mod api_wrapper;
 pub mod async_manager;
 
 pub use audio::AudioDownloader;
 pub use async_manager::{AsyncDownloadManager, DownloadTaskResult};
 
-use crate::config::{AudioFormat, Bitrate};
+use crate::config::{AudioFormat, Bitrate, KeyNotation, NormalizationConfig, Quality, TrimConfig};
+use crate::errors::ErrorKind;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -37,6 +51,92 @@ pub struct TrackMetadata {
     // Additional fields for UI compatibility
     pub composer: Option<String>,
     pub comment: Option<String>,
+    /// Spotify audio features (tempo, key, energy...) when the source provides them
+    #[serde(default)]
+    pub audio_features: Option<AudioFeatures>,
+    /// International Standard Recording Code, when the source provides it
+    #[serde(default)]
+    pub isrc: Option<String>,
+    /// Number of tracks on the album, when the source provides it
+    #[serde(default)]
+    pub total_tracks: Option<u32>,
+}
+
+impl TrackMetadata {
+    /// Spotify track ID taken from the track URL, if this track came from Spotify
+    pub fn spotify_track_id(&self) -> Option<String> {
+        let (_, rest) = self.spotify_url.split_once("open.spotify.com/track/")?;
+        let id: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        if id.is_empty() { None } else { Some(id) }
+    }
+}
+
+/// Spotify audio features for a track
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AudioFeatures {
+    pub tempo: f32,
+    /// Pitch class (0 = C, 1 = C#/Db, ...), -1 when no key was detected
+    pub key: i32,
+    /// 1 for major, 0 for minor
+    pub mode: u32,
+    pub time_signature: u32,
+    pub loudness: f32,
+    pub energy: f32,
+    pub danceability: f32,
+    pub valence: f32,
+    pub acousticness: f32,
+    pub instrumentalness: f32,
+    pub liveness: f32,
+    pub speechiness: f32,
+}
+
+impl AudioFeatures {
+    /// Tempo rounded to a whole BPM, if known
+    pub fn bpm(&self) -> Option<u32> {
+        if self.tempo > 0.0 {
+            Some(self.tempo.round() as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Musical key in the requested notation, if known
+    pub fn key_name(&self, notation: KeyNotation) -> Option<String> {
+        if !(0..12).contains(&self.key) {
+            return None;
+        }
+        let pitch_class = self.key as usize;
+        let minor = self.mode == 0;
+
+        match notation {
+            KeyNotation::Standard => {
+                const NAMES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+                Some(format!("{}{}", NAMES[pitch_class], if minor { "m" } else { "" }))
+            }
+            KeyNotation::Camelot => {
+                // Each step up the circle of fifths (7 semitones) moves one position on the wheel
+                let offset = if minor { 4 } else { 7 };
+                let number = (pitch_class * 7 + offset) % 12 + 1;
+                Some(format!("{}{}", number, if minor { "A" } else { "B" }))
+            }
+        }
+    }
+
+    /// Remaining features as (tag name, value) pairs for custom tags
+    pub fn custom_tags(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("SPOTIFY_ENERGY", format!("{:.3}", self.energy)),
+            ("SPOTIFY_DANCEABILITY", format!("{:.3}", self.danceability)),
+            ("SPOTIFY_VALENCE", format!("{:.3}", self.valence)),
+            ("SPOTIFY_ACOUSTICNESS", format!("{:.3}", self.acousticness)),
+            ("SPOTIFY_INSTRUMENTALNESS", format!("{:.3}", self.instrumentalness)),
+            ("SPOTIFY_LIVENESS", format!("{:.3}", self.liveness)),
+            ("SPOTIFY_SPEECHINESS", format!("{:.3}", self.speechiness)),
+            ("SPOTIFY_LOUDNESS", format!("{:.2}", self.loudness)),
+            ("SPOTIFY_TEMPO", format!("{:.3}", self.tempo)),
+            ("SPOTIFY_TIME_SIGNATURE", self.time_signature.to_string()),
+        ]
+    }
 }
 
 /// Album metadata from Spotify
@@ -77,18 +177,251 @@ pub struct ImageInfo {
 #[derive(Debug, Clone)]
 pub struct DownloadProgress {
     pub track_id: String,
-    pub stage: DownloadStage,
+    pub event: DownloadEvent,
     pub progress: f32, // 0.0 to 1.0
     pub message: String,
 }
 
+impl DownloadProgress {
+    /// Progress update carrying an event
+    pub fn new(track_id: &str, event: impl Into<DownloadEvent>, progress: f32, message: String) -> Self {
+        Self {
+            track_id: track_id.to_string(),
+            event: event.into(),
+            progress,
+            message,
+        }
+    }
+
+    /// Stage the track is in
+    pub fn stage(&self) -> DownloadStage {
+        self.event.stage()
+    }
+
+    /// Transfer statistics while the source is downloading
+    pub fn transfer(&self) -> Option<&TransferStats> {
+        match &self.event {
+            DownloadEvent::Downloading(stats) => Some(stats),
+            _ => None,
+        }
+    }
+}
+
+/// What happened to a track, as reported by the downloader
 #[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    /// Waiting for the first stage
+    Queued,
+    /// Entered a stage that has nothing more to report than its name
+    Stage(DownloadStage),
+    /// A source was chosen for the track
+    Resolved {
+        /// Platform of the source, such as YouTube
+        source: String,
+        title: String,
+        url: String,
+        /// Match confidence from 0.0 to 1.0
+        score: f32,
+    },
+    /// Bytes of the source are coming in
+    Downloading(TransferStats),
+    /// ffmpeg is converting the source, percent from 0 to 100
+    Converting { percent: f32 },
+    /// Metadata, cover art and lyrics were written to the file
+    Tagged,
+    /// The file is finished
+    Completed { path: PathBuf, size: u64 },
+    /// The download stopped at a stage
+    Failed { stage: DownloadStage, error_kind: ErrorKind },
+}
+
+impl DownloadEvent {
+    /// Stage this event belongs to
+    pub fn stage(&self) -> DownloadStage {
+        match self {
+            DownloadEvent::Queued => DownloadStage::Queued,
+            DownloadEvent::Stage(stage) => stage.clone(),
+            DownloadEvent::Resolved { .. } => DownloadStage::SearchingSource,
+            DownloadEvent::Downloading(_) => DownloadStage::DownloadingAudio,
+            DownloadEvent::Converting { .. } => DownloadStage::ConvertingAudio,
+            DownloadEvent::Tagged => DownloadStage::EmbeddingMetadata,
+            DownloadEvent::Completed { .. } => DownloadStage::Completed,
+            DownloadEvent::Failed { .. } => DownloadStage::Error,
+        }
+    }
+
+    /// The track left the download, successfully or not
+    pub fn is_final(&self) -> bool {
+        matches!(self, DownloadEvent::Completed { .. } | DownloadEvent::Failed { .. })
+    }
+}
+
+impl From<DownloadStage> for DownloadEvent {
+    fn from(stage: DownloadStage) -> Self {
+        match stage {
+            DownloadStage::Queued => DownloadEvent::Queued,
+            stage => DownloadEvent::Stage(stage),
+        }
+    }
+}
+
+/// Change in a whole batch, derived from the events of its tracks
+#[derive(Debug, Clone)]
+pub enum BatchEvent {
+    /// A track completed or failed
+    TrackFinished {
+        success: bool,
+        completed: usize,
+        failed: usize,
+        total: usize,
+    },
+    /// Combined transfer of the downloads that are running
+    Transfer {
+        downloaded_bytes: u64,
+        speed_bytes_per_sec: f64,
+        eta_secs: Option<u64>,
+    },
+}
+
+/// Running totals of a batch, built by folding in the events of its tracks
+#[derive(Debug, Clone, Default)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    /// Size of the finished files
+    pub completed_bytes: u64,
+    /// Latest transfer statistics of each running download
+    transfers: std::collections::HashMap<String, TransferStats>,
+    finished: std::collections::HashSet<String>,
+}
+
+impl BatchProgress {
+    /// Totals for a batch of `total` tracks
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            ..Default::default()
+        }
+    }
+
+    /// Fold in a track event, returning the batch event it causes
+    pub fn apply(&mut self, progress: &DownloadProgress) -> Option<BatchEvent> {
+        match &progress.event {
+            DownloadEvent::Downloading(stats) => {
+                self.transfers.insert(progress.track_id.clone(), stats.clone());
+                Some(BatchEvent::Transfer {
+                    downloaded_bytes: self.downloaded_bytes(),
+                    speed_bytes_per_sec: self.speed_bytes_per_sec(),
+                    eta_secs: self.eta_secs(),
+                })
+            }
+            event if event.is_final() => {
+                self.transfers.remove(&progress.track_id);
+                // Tracks report once, but a retried track must not count twice
+                if !self.finished.insert(progress.track_id.clone()) {
+                    return None;
+                }
+                let success = match event {
+                    DownloadEvent::Completed { size, .. } => {
+                        self.completed_bytes += size;
+                        true
+                    }
+                    _ => false,
+                };
+                if success {
+                    self.completed += 1;
+                } else {
+                    self.failed += 1;
+                }
+                Some(BatchEvent::TrackFinished {
+                    success,
+                    completed: self.completed,
+                    failed: self.failed,
+                    total: self.total,
+                })
+            }
+            _ => {
+                // A download that moved on to converting no longer adds to the speed
+                self.transfers.remove(&progress.track_id);
+                None
+            }
+        }
+    }
+
+    /// Bytes received by the running downloads
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.transfers.values().map(|stats| stats.downloaded_bytes).sum()
+    }
+
+    /// Combined speed of the running downloads
+    pub fn speed_bytes_per_sec(&self) -> f64 {
+        self.transfers.values().filter_map(|stats| stats.speed_bytes_per_sec).sum()
+    }
+
+    /// Time until the running downloads are done, if their sizes are known
+    pub fn eta_secs(&self) -> Option<u64> {
+        let remaining: u64 = self.transfers.values()
+            .map(|stats| stats.total_bytes.map(|total| total.saturating_sub(stats.downloaded_bytes)))
+            .sum::<Option<u64>>()?;
+        let speed = self.speed_bytes_per_sec();
+        (speed > 0.0).then(|| (remaining as f64 / speed).ceil() as u64)
+    }
+}
+
+/// Live transfer statistics reported by yt-dlp while downloading
+#[derive(Debug, Clone, Default)]
+pub struct TransferStats {
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub speed_bytes_per_sec: Option<f64>,
+    pub eta_secs: Option<u64>,
+    pub fragment_index: Option<u32>,
+    pub fragment_count: Option<u32>,
+}
+
+impl TransferStats {
+    /// Fraction of the download completed, from bytes or fragments
+    pub fn fraction(&self) -> Option<f32> {
+        match (self.total_bytes, self.fragment_index, self.fragment_count) {
+            (Some(total), _, _) if total > 0 => Some((self.downloaded_bytes as f64 / total as f64).min(1.0) as f32),
+            (_, Some(index), Some(count)) if count > 0 => Some((index as f32 / count as f32).min(1.0)),
+            _ => None,
+        }
+    }
+
+    /// One-line description such as "3.2/8.1 MiB at 1.4 MiB/s, ETA 0:04"
+    pub fn summary(&self) -> String {
+        let mut summary = match self.total_bytes {
+            Some(total) => format!("{}/{}", Self::format_mib(self.downloaded_bytes), Self::format_mib(total)),
+            None => Self::format_mib(self.downloaded_bytes),
+        };
+        if let Some(speed) = self.speed_bytes_per_sec {
+            summary.push_str(&format!(" at {}/s", Self::format_mib(speed as u64)));
+        }
+        if let Some(eta) = self.eta_secs {
+            summary.push_str(&format!(", ETA {}:{:02}", eta / 60, eta % 60));
+        }
+        if let (Some(index), Some(count)) = (self.fragment_index, self.fragment_count) {
+            summary.push_str(&format!(", fragment {}/{}", index, count));
+        }
+        summary
+    }
+
+    fn format_mib(bytes: u64) -> String {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DownloadStage {
     Queued,
     FetchingMetadata,
     SearchingSource,
     DownloadingAudio,
+    TrimmingAudio,
     ConvertingAudio,
+    AnalyzingLoudness,
     DownloadingCover,
     DownloadingLyrics,
     EmbeddingMetadata,
@@ -103,7 +436,9 @@ impl std::fmt::Display for DownloadStage {
             DownloadStage::FetchingMetadata => write!(f, "Fetching Metadata"),
             DownloadStage::SearchingSource => write!(f, "Searching Source"),
             DownloadStage::DownloadingAudio => write!(f, "Downloading Audio"),
+            DownloadStage::TrimmingAudio => write!(f, "Trimming Audio"),
             DownloadStage::ConvertingAudio => write!(f, "Converting Audio"),
+            DownloadStage::AnalyzingLoudness => write!(f, "Analyzing Loudness"),
             DownloadStage::DownloadingCover => write!(f, "Downloading Cover"),
             DownloadStage::DownloadingLyrics => write!(f, "Downloading Lyrics"),
             DownloadStage::EmbeddingMetadata => write!(f, "Embedding Metadata"),
@@ -114,10 +449,11 @@ impl std::fmt::Display for DownloadStage {
 }
 
 /// Download options
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadOptions {
     pub format: AudioFormat,
     pub bitrate: Bitrate,
+    pub quality: Quality,
     pub output_dir: PathBuf,
     pub download_lyrics: bool,
     pub download_cover: bool,
@@ -136,6 +472,16 @@ pub struct DownloadOptions {
     pub embed_album_artist: bool,
     pub embed_composer: bool,
     pub embed_comment: bool,
+    pub embed_bpm: bool,
+    pub embed_audio_features: bool,
+    pub key_notation: KeyNotation,
+    /// Loudness normalization applied while converting
+    pub normalization: NormalizationConfig,
+    /// Silence and intro/outro trimming applied after download
+    pub trim: TrimConfig,
+    /// File to write instead of the one named after the track, as set in a download plan
+    #[serde(default)]
+    pub output_path: Option<PathBuf>,
 }
 
 impl Default for DownloadOptions {
@@ -143,6 +489,7 @@ impl Default for DownloadOptions {
         Self {
             format: AudioFormat::Mp3,
             bitrate: Bitrate::Kbps320,
+            quality: Quality::Cbr(320),
             output_dir: dirs::audio_dir()
                 .unwrap_or_else(|| dirs::home_dir().unwrap().join("Music"))
                 .join("Spotify Downloads"),
@@ -163,6 +510,12 @@ impl Default for DownloadOptions {
             embed_album_artist: true,
             embed_composer: true,
             embed_comment: true,
+            embed_bpm: true,
+            embed_audio_features: true,
+            key_notation: KeyNotation::Standard,
+            normalization: NormalizationConfig::default(),
+            trim: TrimConfig::default(),
+            output_path: None,
         }
     }
 }
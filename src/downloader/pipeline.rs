@@ -0,0 +1,313 @@
+use crate::config::{Config, PipelineConfig};
+use crate::downloader::archive::{self, SharedArchive};
+use crate::downloader::audio::{AudioDownloader, SourceMatch, TranscodedTrack};
+use crate::downloader::control::DownloadControl;
+use crate::downloader::failure_report::{self, FailureDetails, SourceCandidate};
+use crate::downloader::format_selection::SelectedFormat;
+use crate::downloader::job_store::{self, SharedJobStore};
+use crate::downloader::loudness::LoudnessAnalysis;
+use crate::downloader::services::SharedServices;
+use crate::downloader::youtube::{DownloadedAudio, SearchResult};
+use crate::downloader::{DownloadOptions, DownloadProgress, DownloadStage, DownloadTaskResult, TrackMetadata};
+use crate::errors::{Result, SpotifyDownloaderError};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+/// A track entering the pipeline
+pub struct PipelineJob {
+    pub track: TrackMetadata,
+    pub options: DownloadOptions,
+    /// Key of the track in the job store
+    pub job_id: String,
+    /// Progress of this track, closed once it leaves the pipeline
+    pub progress: Option<mpsc::UnboundedSender<DownloadProgress>>,
+    /// Upload chosen ahead of time, e.g. in a download plan, used instead of searching
+    pub source: Option<SearchResult>,
+    /// Search results scored so far, for the failure report
+    pub candidates: Vec<SourceCandidate>,
+    /// Unix timestamp of when the first stage picked the track up
+    pub started_at: Option<u64>,
+}
+
+/// A job and what the stages before the current one produced
+struct StageItem<T> {
+    /// Position in the batch, so results come back in the order the tracks were given
+    index: usize,
+    job: PipelineJob,
+    payload: T,
+}
+
+type StageQueue<T> = Arc<Mutex<mpsc::Receiver<StageItem<T>>>>;
+
+/// Download pipeline split into metadata, search, download, transcoding and tagging stages.
+/// Each stage has its own pool of workers and a bounded queue in front of it, so ffmpeg
+/// conversions don't hold network slots and a slow stage holds back the ones feeding it.
+pub struct DownloadPipeline {
+    pools: PipelineConfig,
+    download_workers: usize,
+    control: DownloadControl,
+    services: SharedServices,
+    job_store: Option<SharedJobStore>,
+    archive: Option<SharedArchive>,
+}
+
+/// What every worker needs to report a track that leaves the pipeline
+#[derive(Clone)]
+struct StageContext {
+    config: Arc<Config>,
+    control: DownloadControl,
+    services: SharedServices,
+    job_store: Option<SharedJobStore>,
+    archive: Option<SharedArchive>,
+    results: mpsc::UnboundedSender<(usize, DownloadTaskResult)>,
+}
+
+impl DownloadPipeline {
+    /// Create a pipeline with the configured pools and `download_workers` concurrent network downloads.
+    /// Every worker shares the clients and caches of `services`.
+    pub fn new(pools: PipelineConfig, download_workers: usize, control: DownloadControl, services: SharedServices) -> Self {
+        Self {
+            pools,
+            download_workers,
+            control,
+            services,
+            job_store: None,
+            archive: None,
+        }
+    }
+
+    /// Record stage changes and results in a persistent queue
+    pub fn with_job_store(mut self, job_store: Option<SharedJobStore>) -> Self {
+        self.job_store = job_store;
+        self
+    }
+
+    /// Record finished downloads in the download archive
+    pub fn with_archive(mut self, archive: Option<SharedArchive>) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    /// Run every job through all stages, returning results in the order the jobs were given
+    pub async fn run(&self, jobs: Vec<PipelineJob>, config: &Config) -> Vec<DownloadTaskResult> {
+        let total = jobs.len();
+        let capacity = self.pools.queue_capacity.max(1);
+        let (results_tx, mut results_rx) = mpsc::unbounded_channel();
+        let context = StageContext {
+            config: Arc::new(config.clone()),
+            control: self.control.clone(),
+            services: Arc::clone(&self.services),
+            job_store: self.job_store.clone(),
+            archive: self.archive.clone(),
+            results: results_tx,
+        };
+
+        let (metadata_tx, metadata_rx) = mpsc::channel::<StageItem<()>>(capacity);
+        let (search_tx, search_rx) = mpsc::channel::<StageItem<()>>(capacity);
+        let (download_tx, download_rx) = mpsc::channel::<StageItem<SourceMatch>>(capacity);
+        let (transcode_tx, transcode_rx) = mpsc::channel::<StageItem<DownloadedAudio>>(capacity);
+        let (tagging_tx, tagging_rx) = mpsc::channel::<StageItem<(TranscodedTrack, Option<SelectedFormat>)>>(capacity);
+
+        let metadata_queue = Arc::new(Mutex::new(metadata_rx));
+        for _ in 0..self.pools.metadata_workers.max(1) {
+            tokio::spawn(metadata_worker(context.clone(), Arc::clone(&metadata_queue), search_tx.clone()));
+        }
+        let search_queue = Arc::new(Mutex::new(search_rx));
+        for _ in 0..self.pools.search_workers.max(1) {
+            tokio::spawn(search_worker(context.clone(), Arc::clone(&search_queue), download_tx.clone()));
+        }
+        let download_queue = Arc::new(Mutex::new(download_rx));
+        for _ in 0..self.download_workers.max(1) {
+            tokio::spawn(download_worker(context.clone(), Arc::clone(&download_queue), transcode_tx.clone()));
+        }
+        let transcode_queue = Arc::new(Mutex::new(transcode_rx));
+        for _ in 0..self.pools.transcode_workers.max(1) {
+            tokio::spawn(transcode_worker(context.clone(), Arc::clone(&transcode_queue), tagging_tx.clone()));
+        }
+        let tagging_queue = Arc::new(Mutex::new(tagging_rx));
+        for _ in 0..self.pools.tagging_workers.max(1) {
+            tokio::spawn(tagging_worker(context.clone(), Arc::clone(&tagging_queue)));
+        }
+        // Workers hold the only senders now, so each stage shuts down once the one before it is done
+        drop((search_tx, download_tx, transcode_tx, tagging_tx));
+        drop(context);
+
+        let mut tracks = Vec::with_capacity(total);
+        let mut feed = Vec::with_capacity(total);
+        for (index, job) in jobs.into_iter().enumerate() {
+            tracks.push((job.track.clone(), job.options.clone()));
+            feed.push(StageItem { index, job, payload: () });
+        }
+        tokio::spawn(async move {
+            for item in feed {
+                if metadata_tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut results: Vec<Option<DownloadTaskResult>> = vec![None; total];
+        while let Some((index, result)) = results_rx.recv().await {
+            results[index] = Some(result);
+        }
+
+        // A worker that panicked loses its track, report it instead of dropping it silently
+        results.into_iter()
+            .zip(tracks)
+            .map(|(result, (track, options))| result.unwrap_or_else(|| DownloadTaskResult {
+                track,
+                options,
+                success: false,
+                output_path: None,
+                source_format: None,
+                trim: None,
+                error: Some("Download pipeline worker stopped unexpectedly".to_string()),
+                cancelled: false,
+                failure: None,
+                loudness: None,
+            }))
+            .collect()
+    }
+}
+
+impl StageContext {
+    /// Worker-owned downloader that follows the batch's pause and cancel
+    fn downloader(&self) -> AudioDownloader {
+        let mut downloader = AudioDownloader::with_services(Arc::clone(&self.services));
+        downloader.set_control(self.control.clone());
+        downloader
+    }
+
+    /// Pass an item on to the next stage, or report its failure at `stage`
+    async fn forward<T, U>(&self, stage: DownloadStage, item: StageItem<T>, result: Result<U>, next: &mpsc::Sender<StageItem<U>>) {
+        match result {
+            Ok(payload) => {
+                let index = item.index;
+                let forwarded = next.send(StageItem { index, job: item.job, payload }).await;
+                if let Err(mpsc::error::SendError(item)) = forwarded {
+                    self.fail(stage, item.index, item.job, SpotifyDownloaderError::Unknown("Download pipeline stopped".to_string()));
+                }
+            }
+            Err(e) => self.fail(stage, item.index, item.job, e),
+        }
+    }
+
+    fn fail(&self, stage: DownloadStage, index: usize, job: PipelineJob, error: SpotifyDownloaderError) {
+        let error = AudioDownloader::report_failure(&job.progress, &job.track.id, stage.clone(), error);
+        let failure = FailureDetails {
+            stage,
+            error_kind: error.kind(),
+            candidates: job.candidates,
+            started_at: job.started_at,
+            failed_at: failure_report::now(),
+        };
+        match &error {
+            // A cancelled download goes back in the queue so it can be resumed
+            SpotifyDownloaderError::Cancelled(_) => job_store::record(self.job_store.as_ref(), |store| store.mark_queued(&job.job_id)),
+            e => job_store::record(self.job_store.as_ref(), |store| store.mark_failed(&job.job_id, &e.to_string())),
+        }
+        let _ = self.results.send((index, DownloadTaskResult {
+            track: job.track,
+            options: job.options,
+            success: false,
+            output_path: None,
+            source_format: None,
+            trim: None,
+            cancelled: matches!(error, SpotifyDownloaderError::Cancelled(_)),
+            error: Some(error.to_string()),
+            failure: Some(failure),
+            loudness: None,
+        }));
+    }
+
+    fn complete(
+        &self,
+        index: usize,
+        job: PipelineJob,
+        transcoded: TranscodedTrack,
+        source_format: Option<SelectedFormat>,
+        loudness: Option<LoudnessAnalysis>,
+    ) {
+        job_store::record(self.job_store.as_ref(), |store| store.mark_completed(&job.job_id, &transcoded.path));
+        archive::record(self.archive.as_ref(), |archive| archive.record(&job.track, &transcoded.path));
+        let _ = self.results.send((index, DownloadTaskResult {
+            track: job.track,
+            options: job.options,
+            success: true,
+            output_path: Some(transcoded.path),
+            source_format,
+            trim: transcoded.trim,
+            error: None,
+            cancelled: false,
+            failure: None,
+            loudness,
+        }));
+    }
+}
+
+async fn next_item<T>(queue: &StageQueue<T>) -> Option<StageItem<T>> {
+    queue.lock().await.recv().await
+}
+
+async fn metadata_worker(context: StageContext, queue: StageQueue<()>, next: mpsc::Sender<StageItem<()>>) {
+    let downloader = context.downloader();
+    while let Some(mut item) = next_item(&queue).await {
+        job_store::record(context.job_store.as_ref(), |store| store.mark_running(&item.job.job_id));
+        item.job.started_at = Some(failure_report::now());
+        let result = downloader.resolve_metadata(&item.job.track, &item.job.progress, &context.config).await
+            .map(|track| item.job.track = track);
+        context.forward(DownloadStage::FetchingMetadata, item, result, &next).await;
+    }
+}
+
+async fn search_worker(context: StageContext, queue: StageQueue<()>, next: mpsc::Sender<StageItem<SourceMatch>>) {
+    let downloader = context.downloader();
+    while let Some(mut item) = next_item(&queue).await {
+        let job = &item.job;
+        let mut candidates = Vec::new();
+        let result = match &job.source {
+            Some(source) => {
+                let result = downloader.use_source(&job.track, source.clone(), &job.options, &job.progress, &context.config).await;
+                candidates.push(SourceCandidate::new(source, result.as_ref().ok().map(|found| found.score), true));
+                result
+            }
+            None => downloader.find_source(&job.track, &job.options, &job.progress, &context.config, &mut candidates).await,
+        };
+        item.job.candidates.extend(candidates);
+        context.forward(DownloadStage::SearchingSource, item, result, &next).await;
+    }
+}
+
+async fn download_worker(context: StageContext, queue: StageQueue<SourceMatch>, next: mpsc::Sender<StageItem<DownloadedAudio>>) {
+    let downloader = context.downloader();
+    while let Some(item) = next_item(&queue).await {
+        let job = &item.job;
+        let result = downloader.fetch_source(&job.track, &item.payload, &job.options, &job.progress, &context.config).await;
+        context.forward(DownloadStage::DownloadingAudio, item, result, &next).await;
+    }
+}
+
+async fn transcode_worker(
+    context: StageContext,
+    queue: StageQueue<DownloadedAudio>,
+    next: mpsc::Sender<StageItem<(TranscodedTrack, Option<SelectedFormat>)>>,
+) {
+    let downloader = context.downloader();
+    while let Some(item) = next_item(&queue).await {
+        let job = &item.job;
+        let result = downloader.transcode(&job.track, &item.payload, &job.options, &job.progress, &context.config).await
+            .map(|transcoded| (transcoded, item.payload.format.clone()));
+        context.forward(DownloadStage::ConvertingAudio, item, result, &next).await;
+    }
+}
+
+async fn tagging_worker(context: StageContext, queue: StageQueue<(TranscodedTrack, Option<SelectedFormat>)>) {
+    let downloader = context.downloader();
+    while let Some(item) = next_item(&queue).await {
+        let StageItem { index, job, payload: (transcoded, source_format) } = item;
+        match downloader.finish_track(&job.track, &transcoded.path, &job.options, &job.progress, &context.config).await {
+            Ok(loudness) => context.complete(index, job, transcoded, source_format, loudness),
+            Err(e) => context.fail(DownloadStage::EmbeddingMetadata, index, job, e),
+        }
+    }
+}
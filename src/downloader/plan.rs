@@ -0,0 +1,214 @@
+use crate::config::Config;
+use crate::downloader::archive::{self, SharedArchive};
+use crate::downloader::audio::AudioDownloader;
+use crate::downloader::services::ServiceContext;
+use crate::downloader::youtube::SearchResult;
+use crate::downloader::{DownloadOptions, TrackMetadata};
+use crate::errors::Result;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Upload picked for a track in a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedSource {
+    pub platform: String,
+    pub title: String,
+    /// Page to download from; replace it to use a different upload
+    pub url: String,
+    pub duration_secs: Option<u32>,
+}
+
+impl PlannedSource {
+    fn from_search_result(result: &SearchResult) -> Self {
+        Self {
+            platform: result.platform.clone(),
+            title: result.title.clone(),
+            url: result.url.clone(),
+            duration_secs: result.duration,
+        }
+    }
+
+    /// Search result to download from; its formats are looked up again when the download starts
+    pub fn to_search_result(&self) -> SearchResult {
+        SearchResult {
+            title: self.title.clone(),
+            url: self.url.clone(),
+            duration: self.duration_secs,
+            uploader: None,
+            view_count: 0,
+            platform: self.platform.clone(),
+            thumbnail: None,
+            formats: Vec::new(),
+        }
+    }
+}
+
+/// What a batch would do for one track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub track: TrackMetadata,
+    /// None when nothing matched; the real run then searches again
+    pub source: Option<PlannedSource>,
+    /// Match confidence from 0.0 to 1.0
+    pub score: Option<f32>,
+    /// Source duration minus the track duration, in seconds
+    pub duration_delta_secs: Option<i64>,
+    /// Where the file will be written; edit it to write somewhere else
+    pub output_path: PathBuf,
+    /// File of this track that is already on disk, from the archive or at the output path.
+    /// The real run keeps it instead of downloading again; clear it to download anyway.
+    pub existing: Option<PathBuf>,
+    /// Leave the track out of the real run
+    #[serde(default)]
+    pub skip: bool,
+    /// Why matching failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Dry run of a batch: metadata and matching for every track, with no audio downloaded.
+/// Saved as JSON so it can be reviewed and edited before it is run for real.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    /// Unix timestamp of when the plan was made
+    pub created_at: u64,
+    pub options: DownloadOptions,
+    pub entries: Vec<PlanEntry>,
+}
+
+impl DownloadPlan {
+    /// Resolve metadata and find a source for every track, searching with the configured number of search workers
+    pub async fn build(
+        tracks: Vec<TrackMetadata>,
+        options: DownloadOptions,
+        archive: Option<&SharedArchive>,
+        config: &Config,
+    ) -> Self {
+        let services = ServiceContext::shared();
+        let total = tracks.len();
+        let entries = stream::iter(tracks.into_iter().enumerate())
+            .map(|(index, track)| {
+                let downloader = AudioDownloader::with_services(Arc::clone(&services));
+                let options = &options;
+                async move {
+                    println!("🔎 Planning {}/{}: {} - {}", index + 1, total, track.artist, track.title);
+                    Self::plan_track(&downloader, track, options, archive, config).await
+                }
+            })
+            .buffered(config.pipeline.search_workers.max(1))
+            .collect()
+            .await;
+
+        Self {
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            options,
+            entries,
+        }
+    }
+
+    async fn plan_track(
+        downloader: &AudioDownloader,
+        track: TrackMetadata,
+        options: &DownloadOptions,
+        archive: Option<&SharedArchive>,
+        config: &Config,
+    ) -> PlanEntry {
+        let track = downloader.resolve_metadata(&track, &None, config).await.unwrap_or(track);
+        let output_path = downloader.get_output_path(&track, options);
+        let existing = archive::check(archive, &track)
+            .or_else(|| output_path.exists().then(|| output_path.clone()));
+
+        let (source, score, duration_delta_secs, error) = match downloader.match_source(&track, config, &mut Vec::new()).await {
+            Ok((result, score)) => {
+                let duration_delta_secs = match (result.duration, track.duration_ms) {
+                    (Some(duration), expected_ms) if expected_ms > 0 => Some(duration as i64 - (expected_ms / 1000) as i64),
+                    _ => None,
+                };
+                (Some(PlannedSource::from_search_result(&result)), Some(score), duration_delta_secs, None)
+            }
+            Err(e) => (None, None, None, Some(e.to_string())),
+        };
+
+        PlanEntry {
+            track,
+            source,
+            score,
+            duration_delta_secs,
+            output_path,
+            existing,
+            skip: false,
+            error,
+        }
+    }
+
+    /// Read a plan written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Write the plan as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Print every entry and a summary
+    pub fn print(&self) {
+        for (index, entry) in self.entries.iter().enumerate() {
+            let marker = if entry.skip {
+                "⏸️"
+            } else if entry.existing.is_some() {
+                "⏭️"
+            } else if entry.source.is_some() {
+                "✓"
+            } else {
+                "✗"
+            };
+            println!("{} {:>3}. {} - {}", marker, index + 1, entry.track.artist, entry.track.title);
+            match &entry.source {
+                Some(source) => {
+                    let score = entry.score
+                        .map(|score| format!("{:.0}% match", score * 100.0))
+                        .unwrap_or_else(|| "chosen by hand".to_string());
+                    let delta = entry.duration_delta_secs
+                        .map(|delta| format!(", {:+}s", delta))
+                        .unwrap_or_default();
+                    println!(
+                        "       source: {} \"{}\" ({}{}) {}",
+                        source.platform,
+                        source.title,
+                        score,
+                        delta,
+                        source.url,
+                    );
+                }
+                None => println!("       source: none ({})", entry.error.as_deref().unwrap_or("not searched")),
+            }
+            println!("       output: {}", entry.output_path.display());
+            if let Some(existing) = &entry.existing {
+                println!("       already downloaded: {}", existing.display());
+            }
+        }
+
+        let matched = self.entries.iter().filter(|entry| entry.source.is_some()).count();
+        let existing = self.entries.iter().filter(|entry| entry.existing.is_some()).count();
+        let skipped = self.entries.iter().filter(|entry| entry.skip).count();
+        println!(
+            "\n📋 {} track(s): {} matched, {} without a source, {} already downloaded, {} skipped",
+            self.entries.len(),
+            matched,
+            self.entries.len() - matched,
+            existing,
+            skipped,
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::downloader::{
+    DownloadOptions, TrackMetadata,
+    api_wrapper::ApiWrapper, covers::CoverDownloader, metadata::MetadataEmbedder,
+};
+use crate::errors::{Result, SpotifyDownloaderError};
+use crate::lyrics::LyricsDownloader;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File extensions the retagger will touch
+const AUDIO_EXTENSIONS: [&str; 9] = ["mp3", "m4a", "mp4", "flac", "wav", "aiff", "aif", "ogg", "opus"];
+
+/// Largest difference in seconds between a file and a track for their durations to match
+const DURATION_TOLERANCE_SECS: u32 = 2;
+
+/// Lowest score a Spotify search result needs before its tags are written to a file
+const MIN_SEARCH_SCORE: f32 = 0.8;
+
+/// A tag value that differs between the old and the new tags
+#[derive(Debug, Clone)]
+pub struct TagChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Outcome of retagging a single file
+#[derive(Debug, Clone)]
+pub struct RetagResult {
+    pub path: PathBuf,
+    pub track: Option<TrackMetadata>,
+    pub changes: Vec<TagChange>,
+    pub error: Option<String>,
+}
+
+/// Re-runs cover, lyrics and metadata embedding on files that are already downloaded
+pub struct Retagger {
+    metadata_embedder: MetadataEmbedder,
+    cover_downloader: CoverDownloader,
+    lyrics_downloader: LyricsDownloader,
+    options: DownloadOptions,
+}
+
+impl Retagger {
+    /// Create a retagger that embeds according to the given options
+    pub fn new(options: DownloadOptions) -> Self {
+        // Get proxy-configured client
+        let client = crate::api::get_api_manager()
+            .map(|api_manager| api_manager.client().clone())
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            metadata_embedder: MetadataEmbedder::new(),
+            cover_downloader: CoverDownloader::new_with_client(client.clone()),
+            lyrics_downloader: LyricsDownloader::new_with_client(client),
+            options,
+        }
+    }
+
+    /// Retag a file or every audio file below a directory
+    pub async fn retag_path(&self, path: &Path, url: Option<&str>, dry_run: bool) -> Result<Vec<RetagResult>> {
+        let files = Self::collect_audio_files(path)?;
+        println!("🔎 Found {} audio file(s) to retag", files.len());
+
+        let candidates = match url {
+            Some(url) => Some(Self::fetch_candidates(url).await?),
+            None => None,
+        };
+
+        let mut results = Vec::new();
+        for file in files {
+            let track = match &candidates {
+                // A single track only stands for the file itself, never for a whole directory
+                Some(tracks) if tracks.len() == 1 && path.is_file() => Ok(tracks[0].clone()),
+                Some(tracks) => self.match_track(&file, tracks),
+                None => self.identify_track(&file).await,
+            };
+
+            let result = match track {
+                Ok(track) => match self.retag_file(&file, &track, dry_run).await {
+                    Ok(changes) => RetagResult { path: file, track: Some(track), changes, error: None },
+                    Err(e) => RetagResult { path: file, track: Some(track), changes: Vec::new(), error: Some(e.to_string()) },
+                },
+                Err(e) => RetagResult { path: file, track: None, changes: Vec::new(), error: Some(e.to_string()) },
+            };
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// List audio files at a path, recursing into directories
+    pub fn collect_audio_files(path: &Path) -> Result<Vec<PathBuf>> {
+        if !path.exists() {
+            return Err(SpotifyDownloaderError::FileNotFound(path.display().to_string()));
+        }
+
+        if path.is_file() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() {
+                files.extend(Self::collect_audio_files(&entry_path)?);
+            } else if Self::is_audio_file(&entry_path) {
+                files.push(entry_path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn is_audio_file(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            .unwrap_or(false)
+    }
+
+    /// Resolve a Spotify URL into the tracks it refers to
+    async fn fetch_candidates(url: &str) -> Result<Vec<TrackMetadata>> {
+        if url.contains("/album/") {
+            Ok(ApiWrapper::get_spotify_album_metadata(url).await?.tracks)
+        } else if url.contains("/playlist/") {
+            Ok(ApiWrapper::get_spotify_playlist_metadata(url).await?.tracks)
+        } else if url.contains("/track/") {
+            Ok(vec![ApiWrapper::get_spotify_track_metadata(url).await?])
+        } else {
+            Err(SpotifyDownloaderError::InvalidUrl(format!("Unsupported URL type: {}", url)))
+        }
+    }
+
+    /// Look up the track from a SPOTIFY_TRACK_ID tag written by an earlier download,
+    /// or search Spotify with the file's ISRC, artist and title when it has none
+    async fn identify_track(&self, file: &PathBuf) -> Result<TrackMetadata> {
+        match self.metadata_embedder.read_spotify_track_id(file) {
+            Some(spotify_id) => {
+                ApiWrapper::get_spotify_track_metadata(&format!("https://open.spotify.com/track/{}", spotify_id)).await
+            }
+            None => self.search_track(file).await,
+        }
+    }
+
+    /// Find the file's track on Spotify, accepting only a confident match
+    async fn search_track(&self, file: &PathBuf) -> Result<TrackMetadata> {
+        let snapshot = self.metadata_embedder.read_tag_snapshot(file).unwrap_or_default();
+        let title = snapshot.get("TrackTitle").cloned().unwrap_or_default();
+        let artist = snapshot.get("TrackArtist").cloned().unwrap_or_default();
+        let isrc = self.metadata_embedder.read_isrc(file);
+        let duration_ms = self.metadata_embedder.read_duration_ms(file);
+
+        let query = match (&isrc, title.is_empty() || artist.is_empty()) {
+            (Some(isrc), _) => format!("isrc:{}", isrc),
+            (None, false) => format!("track:{} artist:{}", title, artist),
+            (None, true) => {
+                return Err(SpotifyDownloaderError::Metadata(
+                    "No Spotify URL given, no embedded SPOTIFY_TRACK_ID and no artist/title tags to search with".to_string()
+                ));
+            }
+        };
+        println!("🔎 Searching Spotify for {}", query);
+
+        let results = ApiWrapper::search_spotify_tracks(&query, 5).await?;
+        let (track, score) = results.into_iter()
+            .map(|track| {
+                let score = Self::search_score(&track, &title, &artist, isrc.as_deref(), duration_ms);
+                (track, score)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .ok_or_else(|| SpotifyDownloaderError::Metadata(format!("No Spotify results for {}", query)))?;
+
+        if score < MIN_SEARCH_SCORE {
+            return Err(SpotifyDownloaderError::Metadata(format!(
+                "Best Spotify match {} - {} is too uncertain ({:.0}%), pass --url to retag this file",
+                track.artist, track.title, score * 100.0
+            )));
+        }
+        println!("✓ Matched {} - {} ({:.0}%)", track.artist, track.title, score * 100.0);
+        Ok(track)
+    }
+
+    /// Confidence from 0.0 to 1.0 that a search result is the file's track: a matching ISRC settles it,
+    /// otherwise title, artist and duration are weighed
+    fn search_score(track: &TrackMetadata, title: &str, artist: &str, isrc: Option<&str>, duration_ms: Option<u32>) -> f32 {
+        if let (Some(isrc), Some(track_isrc)) = (isrc, &track.isrc) {
+            if isrc.eq_ignore_ascii_case(track_isrc) {
+                return 1.0;
+            }
+        }
+
+        let (title, track_title) = (Self::normalize(title), Self::normalize(&track.title));
+        let title_score = if title == track_title {
+            1.0
+        } else if !title.is_empty() && !track_title.is_empty() && (title.contains(&track_title) || track_title.contains(&title)) {
+            0.7
+        } else {
+            0.0
+        };
+        let artist = Self::normalize(artist);
+        let artist_score = if !artist.is_empty() && track.artist.split(", ").any(|name| artist.contains(&Self::normalize(name))) {
+            1.0
+        } else {
+            0.0
+        };
+        let duration_score = match duration_ms {
+            Some(duration_ms) if Self::durations_match(duration_ms, track.duration_ms) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        title_score * 0.5 + artist_score * 0.3 + duration_score * 0.2
+    }
+
+    fn durations_match(a_ms: u32, b_ms: u32) -> bool {
+        b_ms > 0 && (a_ms / 1000).abs_diff(b_ms / 1000) <= DURATION_TOLERANCE_SECS
+    }
+
+    /// Pick the album/playlist track whose title best matches the file's title tag or name,
+    /// or else the only track whose duration matches the file
+    fn match_track(&self, file: &PathBuf, tracks: &[TrackMetadata]) -> Result<TrackMetadata> {
+        let existing_title = self.metadata_embedder.read_tag_snapshot(file).ok()
+            .and_then(|snapshot| snapshot.get("TrackTitle").cloned());
+        let file_stem = file.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let haystacks: Vec<String> = existing_title.into_iter()
+            .chain(std::iter::once(file_stem))
+            .map(|text| Self::normalize(&text))
+            .collect();
+
+        let by_title = tracks.iter()
+            .filter(|track| {
+                let title = Self::normalize(&track.title);
+                !title.is_empty() && haystacks.iter().any(|haystack| haystack.contains(&title))
+            })
+            .max_by_key(|track| track.title.len());
+        if let Some(track) = by_title {
+            return Ok(track.clone());
+        }
+
+        let duration_ms = self.metadata_embedder.read_duration_ms(file);
+        let mut by_duration = tracks.iter()
+            .filter(|track| duration_ms.is_some_and(|duration_ms| Self::durations_match(duration_ms, track.duration_ms)));
+        match (by_duration.next(), by_duration.next()) {
+            (Some(track), None) => Ok(track.clone()),
+            _ => Err(SpotifyDownloaderError::Metadata("No track matches the file's title or duration".to_string())),
+        }
+    }
+
+    fn normalize(text: &str) -> String {
+        text.to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect()
+    }
+
+    /// Embed fresh tags into a file (or a scratch copy for dry runs) and return what changed
+    async fn retag_file(&self, file: &PathBuf, track: &TrackMetadata, dry_run: bool) -> Result<Vec<TagChange>> {
+        println!("🏷️ Retagging {} as {} - {}", file.display(), track.artist, track.title);
+
+        let old_tags = self.metadata_embedder.read_tag_snapshot(file)?;
+
+        let cover_art_data = if self.options.download_cover {
+            match self.cover_downloader.download_cover_art_data(
+                track,
+                self.options.cover_width,
+                self.options.cover_height,
+                &self.options.cover_format,
+            ).await {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    println!("⚠️ Cover art unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let lyrics_data = if self.options.download_lyrics {
+            match self.lyrics_downloader.download_lyrics_for_embedding(track).await {
+                Ok(lyrics) => Some(lyrics),
+                Err(e) => {
+                    println!("⚠️ Lyrics unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Dry runs embed into a scratch copy so the original stays untouched
+        let scratch_dir = std::env::temp_dir().join(format!("spotify-downloader-retag-{}", uuid::Uuid::new_v4()));
+        let target = if dry_run {
+            std::fs::create_dir_all(&scratch_dir)?;
+            let scratch_file = scratch_dir.join(file.file_name().unwrap_or_default());
+            std::fs::copy(file, &scratch_file)?;
+            scratch_file
+        } else {
+            file.clone()
+        };
+
+        let embed_result = self.metadata_embedder.embed_metadata(
+            &target,
+            track,
+            cover_art_data.as_ref(),
+            lyrics_data.as_ref(),
+            &self.options,
+        ).await;
+        let new_tags = embed_result.and_then(|_| self.metadata_embedder.read_tag_snapshot(&target));
+
+        if dry_run {
+            let _ = std::fs::remove_dir_all(&scratch_dir);
+        }
+
+        Ok(Self::diff_tags(&old_tags, &new_tags?))
+    }
+
+    /// Compare two tag snapshots
+    fn diff_tags(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<TagChange> {
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        keys.into_iter()
+            .filter(|key| old.get(*key) != new.get(*key))
+            .map(|key| TagChange {
+                key: key.clone(),
+                old: old.get(key).cloned(),
+                new: new.get(key).cloned(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,123 @@
+use crate::downloader::covers::CoverDownloader;
+use crate::downloader::youtube::SearchResult;
+use crate::downloader::TrackMetadata;
+use crate::errors::{Result, SpotifyDownloaderError};
+use crate::lyrics::{LyricsDownloader, LyricsResult};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Services shared by every download task of a batch
+pub type SharedServices = Arc<ServiceContext>;
+
+/// Outcome of a lookup, kept so tracks of the same album don't repeat a search that can't succeed either.
+/// Transient failures are not kept, the next track (or retry) asks again.
+type CachedLookup<T> = Arc<OnceCell<std::result::Result<Arc<T>, String>>>;
+
+/// Cover art and lyrics clients plus the match, cover art and lyrics caches, shared between concurrent tasks.
+/// Lookups for the same key that run at the same time wait for one request instead of each sending their own.
+pub struct ServiceContext {
+    cover_downloader: CoverDownloader,
+    lyrics_downloader: LyricsDownloader,
+    match_cache: Mutex<HashMap<String, Vec<SearchResult>>>,
+    cover_cache: Mutex<HashMap<String, CachedLookup<Vec<u8>>>>,
+    lyrics_cache: Mutex<HashMap<String, CachedLookup<LyricsResult>>>,
+}
+
+impl ServiceContext {
+    /// Create services around an HTTP client
+    pub fn new(client: Client) -> Self {
+        Self {
+            cover_downloader: CoverDownloader::new_with_client(client.clone()),
+            lyrics_downloader: LyricsDownloader::new_with_client(client),
+            match_cache: Mutex::new(HashMap::new()),
+            cover_cache: Mutex::new(HashMap::new()),
+            lyrics_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Shared services using the proxy-configured client of the API manager
+    pub fn shared() -> SharedServices {
+        let client = crate::api::get_api_manager()
+            .map(|api_manager| api_manager.client().clone())
+            .unwrap_or_else(|_| Client::new());
+        Arc::new(Self::new(client))
+    }
+
+    /// Search results found earlier for a query
+    pub fn cached_matches(&self, query: &str) -> Option<Vec<SearchResult>> {
+        self.match_cache.lock().ok()?.get(query).cloned()
+    }
+
+    /// Remember the search results for a query
+    pub fn store_matches(&self, query: &str, results: Vec<SearchResult>) {
+        if let Ok(mut cache) = self.match_cache.lock() {
+            cache.insert(query.to_string(), results);
+        }
+    }
+
+    /// Processed cover art for a track, fetched once per cover URL or album and size
+    pub async fn cover_art(&self, track: &TrackMetadata, width: u32, height: u32, format: &str) -> Result<Arc<Vec<u8>>> {
+        let source = match track.album_cover_url.as_deref().filter(|url| !url.is_empty()) {
+            Some(url) => format!("url:{}", url),
+            None if !track.album.is_empty() => {
+                let album_artist = track.album_artist.as_deref().unwrap_or(&track.artist);
+                format!("album:{}|{}", album_artist.to_lowercase(), track.album.to_lowercase())
+            }
+            None => format!("track:{}|{}", track.artist.to_lowercase(), track.title.to_lowercase()),
+        };
+        let key = format!("{}|{}x{}|{}", source, width, height, format.to_lowercase());
+
+        let cell = Self::cell(&self.cover_cache, key)?;
+        Self::lookup(&cell, SpotifyDownloaderError::CoverArt, || {
+            self.cover_downloader.download_cover_art_data(track, width, height, format)
+        }).await
+    }
+
+    /// Lyrics for a track, fetched once per track
+    pub async fn lyrics(&self, track: &TrackMetadata) -> Result<Arc<LyricsResult>> {
+        let key = track.spotify_track_id()
+            .unwrap_or_else(|| format!("{}|{}|{}", track.artist.to_lowercase(), track.title.to_lowercase(), track.duration_ms / 1000));
+
+        let cell = Self::cell(&self.lyrics_cache, key)?;
+        Self::lookup(&cell, SpotifyDownloaderError::Lyrics, || {
+            self.lyrics_downloader.download_lyrics_for_embedding(track)
+        }).await
+    }
+
+    /// Run a lookup once per cell. The caller that ran it gets its own error back;
+    /// later callers get a permanent failure again as `cached_error`.
+    async fn lookup<T, F, Fut>(
+        cell: &CachedLookup<T>,
+        cached_error: fn(String) -> SpotifyDownloaderError,
+        fetch: F,
+    ) -> Result<Arc<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut failure = None;
+        let outcome = cell.get_or_try_init(|| async {
+            match fetch().await {
+                Ok(value) => Ok(Ok(Arc::new(value))),
+                Err(e) if e.is_transient() => Err(e),
+                Err(e) => {
+                    let message = e.to_string();
+                    failure = Some(e);
+                    Ok(Err(message))
+                }
+            }
+        }).await?;
+        match outcome {
+            Ok(value) => Ok(Arc::clone(value)),
+            Err(message) => Err(failure.unwrap_or_else(|| cached_error(message.clone()))),
+        }
+    }
+
+    fn cell<T>(cache: &Mutex<HashMap<String, CachedLookup<T>>>, key: String) -> Result<CachedLookup<T>> {
+        let mut cache = cache.lock()
+            .map_err(|e| SpotifyDownloaderError::Unknown(format!("Service cache unavailable: {}", e)))?;
+        Ok(Arc::clone(cache.entry(key).or_default()))
+    }
+}
@@ -113,7 +113,7 @@ impl SoundcloudDownloader {
             .ok_or_else(|| SpotifyDownloaderError::Soundcloud("No stream URL available".to_string()))?;
         
         // Download the audio stream
-        let response = self.client
+        let mut response = self.client
             .get(&stream_url)
             .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
             .send()
@@ -131,14 +131,17 @@ impl SoundcloudDownloader {
             .await
             .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to create output file: {}", e)))?;
         
-        // Get the response bytes and write to file
+        // Stream the response to the file within the bandwidth caps
         use tokio::io::AsyncWriteExt;
         
-        let bytes = response.bytes().await
-            .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to get response bytes: {}", e)))?;
-        
-        file.write_all(&bytes).await
-            .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to write to file: {}", e)))?;
+        let throttle = crate::utils::bandwidth::TransferThrottle::new();
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to get response bytes: {}", e)))?
+        {
+            throttle.throttle(chunk.len() as u64).await;
+            file.write_all(&chunk).await
+                .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to write to file: {}", e)))?;
+        }
         
         file.flush().await
             .map_err(|e| SpotifyDownloaderError::Soundcloud(format!("Failed to flush file: {}", e)))?;
@@ -1,4 +1,4 @@
-use crate::downloader::{TrackMetadata, AlbumMetadata, PlaylistMetadata, ImageInfo};
+use crate::downloader::{TrackMetadata, AlbumMetadata, PlaylistMetadata, ImageInfo, AudioFeatures};
 use crate::errors::{Result, SpotifyDownloaderError};
 use reqwest::Client;
 use serde::Deserialize;
@@ -30,6 +30,13 @@ struct SpotifyTrackResponse {
     duration_ms: u32,
     external_urls: HashMap<String, String>,
     preview_url: Option<String>,
+    #[serde(default)]
+    external_ids: Option<SpotifyExternalIds>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotifyExternalIds {
+    isrc: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,6 +58,22 @@ struct SpotifyAlbum {
     external_urls: HashMap<String, String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct SpotifyAudioFeaturesResponse {
+    tempo: f32,
+    key: i32,
+    mode: u32,
+    time_signature: u32,
+    loudness: f32,
+    energy: f32,
+    danceability: f32,
+    valence: f32,
+    acousticness: f32,
+    instrumentalness: f32,
+    liveness: f32,
+    speechiness: f32,
+}
+
 #[derive(Debug, Deserialize)]
 struct SpotifyImage {
     url: String,
@@ -110,10 +133,7 @@ impl SpotifyClient {
         println!("Authentication response status: {}", status);
         
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SpotifyDownloaderError::Spotify(
-                format!("Authentication failed: {} - {}", status, error_text)
-            ));
+            return Err(SpotifyDownloaderError::from_response("Spotify authentication failed", response).await);
         }
 
         let token_response: SpotifyTokenResponse = response.json().await?;
@@ -152,10 +172,7 @@ impl SpotifyClient {
         let status = response.status();
         println!("Track API response status: {}", status);
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SpotifyDownloaderError::Spotify(
-                format!("Failed to fetch track: {} - {}", status, error_text)
-            ));
+            return Err(SpotifyDownloaderError::from_response("Failed to fetch Spotify track", response).await);
         }
 
         println!("Parsing track data...");
@@ -167,6 +184,8 @@ impl SpotifyClient {
             .max_by_key(|img| img.width)
             .map(|img| img.url.clone());
 
+        let audio_features = self.get_audio_features(&spotify_track.id).await;
+
         Ok(TrackMetadata {
             id: spotify_track.id,
             title: spotify_track.name,
@@ -190,6 +209,42 @@ impl SpotifyClient {
             album_cover_url,
             composer: None,
             comment: None,
+            audio_features,
+            isrc: spotify_track.external_ids.and_then(|ids| ids.isrc),
+            total_tracks: Some(spotify_track.album.total_tracks),
+        })
+    }
+
+    /// Fetch audio features for a track (best effort, the endpoint is unavailable to newer apps)
+    async fn get_audio_features(&self, track_id: &str) -> Option<AudioFeatures> {
+        let access_token = self.access_token.as_ref()?;
+        let response = self
+            .client
+            .get(&format!("https://api.spotify.com/v1/audio-features/{}", track_id))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .send()
+            .await
+            .ok()?;
+
+        if !response.status().is_success() {
+            println!("⚠️ Audio features unavailable: {}", response.status());
+            return None;
+        }
+
+        let features: SpotifyAudioFeaturesResponse = response.json().await.ok()?;
+        Some(AudioFeatures {
+            tempo: features.tempo,
+            key: features.key,
+            mode: features.mode,
+            time_signature: features.time_signature,
+            loudness: features.loudness,
+            energy: features.energy,
+            danceability: features.danceability,
+            valence: features.valence,
+            acousticness: features.acousticness,
+            instrumentalness: features.instrumentalness,
+            liveness: features.liveness,
+            speechiness: features.speechiness,
         })
     }
 
@@ -208,23 +263,28 @@ impl SpotifyClient {
             .await?;
 
         if !response.status().is_success() {
-            return Err(SpotifyDownloaderError::Spotify(
-                format!("Failed to fetch album: {}", response.status())
-            ));
+            return Err(SpotifyDownloaderError::from_response("Failed to fetch Spotify album", response).await);
         }
 
         let spotify_album: SpotifyAlbum = response.json().await?;
         
-        // Fetch tracks for the album
-        let tracks = self.fetch_album_tracks(&spotify_album.id).await?;
+        // Fetch tracks for the album; album track objects don't carry the album, so fill it in
+        let album_artist = spotify_album.artists.iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let mut tracks = self.fetch_album_tracks(&spotify_album.id).await?;
+        for track in &mut tracks {
+            track.album = spotify_album.name.clone();
+            track.album_artist = Some(album_artist.clone());
+            track.release_date = Some(spotify_album.release_date.clone());
+            track.total_tracks = Some(spotify_album.total_tracks);
+        }
 
         Ok(AlbumMetadata {
             id: spotify_album.id,
             name: spotify_album.name,
-            artist: spotify_album.artists.iter()
-                .map(|a| a.name.as_str())
-                .collect::<Vec<&str>>()
-                .join(", "),
+            artist: album_artist,
             release_date: spotify_album.release_date,
             total_tracks: spotify_album.total_tracks,
             images: spotify_album.images.into_iter().map(|img| ImageInfo {
@@ -252,9 +312,7 @@ impl SpotifyClient {
             .await?;
 
         if !response.status().is_success() {
-            return Err(SpotifyDownloaderError::Spotify(
-                format!("Failed to fetch playlist: {}", response.status())
-            ));
+            return Err(SpotifyDownloaderError::from_response("Failed to fetch Spotify playlist", response).await);
         }
 
         let playlist_response: serde_json::Value = response.json().await?;
@@ -351,9 +409,8 @@ impl SpotifyClient {
                 .await?;
             
             if !response.status().is_success() {
-                return Err(SpotifyDownloaderError::Spotify(
-                    format!("Failed to fetch album tracks page {}: {}", page_count, response.status())
-                ));
+                let context = format!("Failed to fetch album tracks page {}", page_count);
+                return Err(SpotifyDownloaderError::from_response(&context, response).await);
             }
             
             let tracks_response: serde_json::Value = response.json().await?;
@@ -384,6 +441,35 @@ impl SpotifyClient {
         Ok(all_tracks)
     }
     
+    /// Search Spotify tracks, e.g. with `isrc:<code>` or `track:<title> artist:<artist>`
+    pub async fn search_tracks(&mut self, query: &str, limit: u32) -> Result<Vec<TrackMetadata>> {
+        self.ensure_authenticated().await?;
+        let access_token = self.access_token.as_ref().unwrap();
+        
+        let response = self
+            .client
+            .get("https://api.spotify.com/v1/search")
+            .query(&[("q", query), ("type", "track"), ("limit", &limit.to_string())])
+            .header("Authorization", format!("Bearer {}", access_token))
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            return Err(SpotifyDownloaderError::from_response("Failed to search Spotify tracks", response).await);
+        }
+        
+        let search_response: serde_json::Value = response.json().await?;
+        let items = search_response.get("tracks")
+            .and_then(|t| t.get("items"))
+            .and_then(|i| i.as_array())
+            .map_or(&[][..], |v| v);
+        
+        Ok(items.iter()
+            .filter(|item| !item.is_null())
+            .filter_map(|item| self.parse_track_from_playlist_item(item).ok())
+            .collect())
+    }
+    
     /// Fetch tracks for a playlist
     async fn fetch_playlist_tracks(&mut self, playlist_id: &str) -> Result<Vec<TrackMetadata>> {
         self.ensure_authenticated().await?;
@@ -405,9 +491,8 @@ impl SpotifyClient {
                 .await?;
             
             if !response.status().is_success() {
-                return Err(SpotifyDownloaderError::Spotify(
-                    format!("Failed to fetch playlist tracks page {}: {}", page_count, response.status())
-                ));
+                let context = format!("Failed to fetch playlist tracks page {}", page_count);
+                return Err(SpotifyDownloaderError::from_response(&context, response).await);
             }
             
             let tracks_response: serde_json::Value = response.json().await?;
@@ -490,6 +575,9 @@ impl SpotifyClient {
             album_cover_url: None,
             composer: None,
             comment: None,
+            audio_features: None,
+            isrc: None,
+            total_tracks: None,
         })
     }
     
@@ -546,6 +634,16 @@ impl SpotifyClient {
             .and_then(|d| d.as_u64())
             .unwrap_or(0) as u32;
         
+        let total_tracks = track_data.get("album")
+            .and_then(|a| a.get("total_tracks"))
+            .and_then(|t| t.as_u64())
+            .map(|t| t as u32);
+        
+        let isrc = track_data.get("external_ids")
+            .and_then(|e| e.get("isrc"))
+            .and_then(|i| i.as_str())
+            .map(|s| s.to_string());
+        
         let preview_url = track_data.get("preview_url")
             .and_then(|p| p.as_str())
             .map(|s| s.to_string());
@@ -576,6 +674,9 @@ impl SpotifyClient {
             album_cover_url: None,
             composer: None,
             comment: None,
+            audio_features: None,
+            isrc,
+            total_tracks,
         })
     }
 }
@@ -0,0 +1,189 @@
+use crate::config::TrimConfig;
+
+/// Silences shorter than this from either end of the file still count as touching it
+const EDGE_TOLERANCE_SECS: f64 = 0.05;
+
+/// A silent stretch reported by ffmpeg's silencedetect filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceInterval {
+    pub start_secs: f64,
+    pub end_secs: f64,
+}
+
+/// Why a section was cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimReason {
+    LeadingSilence,
+    TrailingSilence,
+    ExcessIntro,
+    ExcessOutro,
+}
+
+impl std::fmt::Display for TrimReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrimReason::LeadingSilence => write!(f, "leading silence"),
+            TrimReason::TrailingSilence => write!(f, "trailing silence"),
+            TrimReason::ExcessIntro => write!(f, "intro beyond the Spotify duration"),
+            TrimReason::ExcessOutro => write!(f, "outro beyond the Spotify duration"),
+        }
+    }
+}
+
+/// A section removed from the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrimCut {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub reason: TrimReason,
+}
+
+impl TrimCut {
+    /// Length of the removed section
+    pub fn duration_secs(&self) -> f64 {
+        self.end_secs - self.start_secs
+    }
+}
+
+impl std::fmt::Display for TrimCut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.2}s–{:.2}s ({:.2}s {})",
+            self.start_secs, self.end_secs, self.duration_secs(), self.reason
+        )
+    }
+}
+
+/// What the trimmer decided to keep and cut for one file
+#[derive(Debug, Clone)]
+pub struct TrimReport {
+    pub original_secs: f64,
+    pub keep_start_secs: f64,
+    pub keep_end_secs: f64,
+    pub cuts: Vec<TrimCut>,
+}
+
+impl TrimReport {
+    /// Length of the audio that is kept
+    pub fn kept_secs(&self) -> f64 {
+        self.keep_end_secs - self.keep_start_secs
+    }
+
+    /// Total length of everything cut
+    pub fn trimmed_secs(&self) -> f64 {
+        self.cuts.iter().map(TrimCut::duration_secs).sum()
+    }
+
+    /// Whether the file is kept whole
+    pub fn is_empty(&self) -> bool {
+        self.cuts.is_empty()
+    }
+
+    /// One line description, e.g. "trimmed 4.1s (leading silence, outro beyond the Spotify duration)"
+    pub fn summary(&self) -> String {
+        if self.cuts.is_empty() {
+            return "nothing trimmed".to_string();
+        }
+        let reasons = self.cuts.iter()
+            .map(|cut| cut.reason.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("trimmed {:.1}s ({})", self.trimmed_secs(), reasons)
+    }
+}
+
+/// Decides where to cut a source based on its silences and the Spotify track length
+pub struct TrimPlanner {
+    config: TrimConfig,
+}
+
+impl TrimPlanner {
+    /// Create a planner with the given settings
+    pub fn new(config: TrimConfig) -> Self {
+        Self { config }
+    }
+
+    /// Plan the cuts for a source of the given duration
+    pub fn plan(&self, silences: &[SilenceInterval], duration_secs: f64, reference_secs: Option<f64>) -> TrimReport {
+        let padding = self.config.padding_secs.max(0.0);
+        let mut keep_start = 0.0;
+        let mut keep_end = duration_secs;
+        let mut cuts = Vec::new();
+
+        // Silence that starts the file
+        if let Some(silence) = silences.iter().find(|silence| silence.start_secs <= EDGE_TOLERANCE_SECS) {
+            let cut_end = (silence.end_secs - padding).max(0.0);
+            if cut_end > 0.0 && cut_end < duration_secs {
+                cuts.push(TrimCut { start_secs: 0.0, end_secs: cut_end, reason: TrimReason::LeadingSilence });
+                keep_start = cut_end;
+            }
+        }
+
+        // Silence that runs to the end of the file
+        if let Some(silence) = silences.iter().rev().find(|silence| {
+            silence.end_secs >= duration_secs - EDGE_TOLERANCE_SECS && silence.start_secs > keep_start
+        }) {
+            let cut_start = (silence.start_secs + padding).min(duration_secs);
+            if cut_start > keep_start && cut_start < duration_secs {
+                cuts.push(TrimCut { start_secs: cut_start, end_secs: duration_secs, reason: TrimReason::TrailingSilence });
+                keep_end = cut_start;
+            }
+        }
+
+        // Music videos pad songs with skits and chatter, cut those at a silent gap when the source runs long
+        if let Some(reference) = reference_secs.filter(|reference| self.config.trim_to_reference && *reference > 0.0) {
+            let tolerance = self.config.reference_tolerance_secs.max(0.0);
+            let max_cut = self.config.max_excess_trim_secs.max(0.0);
+
+            if keep_end - keep_start - reference > tolerance {
+                // Gaps strictly inside the kept range, so the edge silences cut above are never picked again
+                let inner_gaps = || silences.iter().filter(|silence| silence.start_secs > keep_start && silence.end_secs < keep_end);
+                let intro_points: Vec<f64> = std::iter::once(keep_start)
+                    .chain(inner_gaps()
+                        .map(|silence| silence.end_secs - padding)
+                        .filter(|point| *point > keep_start && *point - keep_start <= max_cut))
+                    .collect();
+                let outro_points: Vec<f64> = std::iter::once(keep_end)
+                    .chain(inner_gaps()
+                        .map(|silence| silence.start_secs + padding)
+                        .filter(|point| *point < keep_end && keep_end - *point <= max_cut))
+                    .collect();
+
+                // Pick the intro and outro cut together, whichever pair keeps closest to the Spotify length
+                let mut best = (keep_start, keep_end);
+                for &start in &intro_points {
+                    for &end in outro_points.iter().filter(|end| **end > start) {
+                        let kept_error = (end - start - reference).abs();
+                        if kept_error < (best.1 - best.0 - reference).abs() {
+                            best = (start, end);
+                        }
+                    }
+                }
+
+                let (cut_end, cut_start) = best;
+                if cut_end > keep_start {
+                    cuts.push(TrimCut { start_secs: keep_start, end_secs: cut_end, reason: TrimReason::ExcessIntro });
+                    keep_start = cut_end;
+                }
+                if cut_start < keep_end {
+                    cuts.push(TrimCut { start_secs: cut_start, end_secs: keep_end, reason: TrimReason::ExcessOutro });
+                    keep_end = cut_start;
+                }
+            }
+
+            let excess = keep_end - keep_start - reference;
+            if excess > tolerance {
+                println!("⚠️ Source is still {:.1}s longer than the Spotify track, no silent gap to cut at", excess);
+            }
+        }
+
+        cuts.sort_by(|a, b| a.start_secs.total_cmp(&b.start_secs));
+        TrimReport {
+            original_secs: duration_secs,
+            keep_start_secs: keep_start,
+            keep_end_secs: keep_end,
+            cuts,
+        }
+    }
+}
@@ -1,12 +1,29 @@
 use crate::errors::{Result, SpotifyDownloaderError};
-use crate::config::{AudioFormat, Bitrate};
+use crate::config::{AudioFormat, Quality};
+use crate::downloader::control::DownloadControl;
+use crate::downloader::converter::SourceCodec;
+use crate::downloader::format_selection::SelectedFormat;
+use crate::downloader::TransferStats;
 use std::path::PathBuf;
+use std::collections::VecDeque;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command as AsyncCommand;
+use tokio::sync::mpsc;
 use serde::{Deserialize, Serialize};
 
+/// Marks the line yt-dlp prints with the final file path and source codec
+const SOURCE_INFO_PREFIX: &str = "SOURCE_INFO|";
+
+/// Marks progress lines written through --progress-template
+const PROGRESS_PREFIX: &str = "PROGRESS|";
+
+/// How many lines of other yt-dlp output are kept for error messages
+const OUTPUT_TAIL_LINES: usize = 20;
+
 /// YouTube and SoundCloud downloader using yt-dlp
 pub struct YoutubeDownloader {
     executable_path: String,
+    control: DownloadControl,
 }
 
 impl YoutubeDownloader {
@@ -14,13 +31,22 @@ impl YoutubeDownloader {
     pub fn new() -> Self {
         Self {
             executable_path: "yt-dlp".to_string(),
+            control: DownloadControl::new(),
         }
     }
 
+    /// Pause and cancel running downloads through the control
+    pub fn set_control(&mut self, control: DownloadControl) {
+        self.control = control;
+    }
+
     /// Create a new downloader with custom executable path
     #[allow(dead_code)]
     pub fn with_path(executable_path: String) -> Self {
-        Self { executable_path }
+        Self {
+            executable_path,
+            control: DownloadControl::new(),
+        }
     }
 
     /// Check if yt-dlp is available
@@ -37,36 +63,41 @@ impl YoutubeDownloader {
     /// Optimized search strategy: try ytsearch1 -> ytsearch5 -> scsearch1 -> scsearch5
     pub async fn search_optimized(&self, query: &str, config: &crate::config::Config) -> Result<Vec<SearchResult>> {
         let mut all_results = Vec::new();
-
-        // Try ytsearch1 first (fastest)
-        if let Ok(mut results) = self.search_youtube(query, 1, config).await {
-            all_results.append(&mut results);
-        }
-
-        // Try ytsearch5 if we need more results
-        if all_results.is_empty() {
-            if let Ok(mut results) = self.search_youtube(query, 5, config).await {
-                all_results.append(&mut results);
+        // Only an error if every search failed, an empty result is a real answer
+        let mut searched = false;
+        let mut last_error = None;
+        let attempts: [(&str, u32); 4] = [("YouTube", 1), ("YouTube", 5), ("SoundCloud", 1), ("SoundCloud", 5)];
+
+        // ytsearch1 first (fastest), then ytsearch5, scsearch1 and scsearch5 as a last resort
+        for (platform, max_results) in attempts {
+            if !all_results.is_empty() {
+                break;
             }
-        }
-
-        // Try scsearch1 if YouTube failed
-        if all_results.is_empty() {
-            if let Ok(mut results) = self.search_soundcloud(query, 1, config).await {
-                all_results.append(&mut results);
+            let results = if platform == "YouTube" {
+                self.search_youtube(query, max_results, config).await
+            } else {
+                self.search_soundcloud(query, max_results, config).await
+            };
+            match results {
+                Ok(mut results) => {
+                    searched = true;
+                    all_results.append(&mut results);
+                }
+                Err(e) => last_error = Some(e),
             }
         }
 
-        // Try scsearch5 as last resort
-        if all_results.is_empty() {
-            if let Ok(mut results) = self.search_soundcloud(query, 5, config).await {
-                all_results.append(&mut results);
-            }
+        if let (false, Some(e)) = (searched, last_error) {
+            return Err(e);
         }
 
-        // Filter results to only include valid tracks
+        // Filter results to only include valid tracks, relaxed matching keeps everything that was found
+        let relaxed = config.matching.relaxed;
         let filtered_results: Vec<SearchResult> = all_results.into_iter()
             .filter(|result| {
+                if relaxed {
+                    return true;
+                }
                 let is_track = self.is_track(&result.title);
                 let is_valid_duration = self.is_valid_duration(result.duration);
                 is_track && is_valid_duration
@@ -115,6 +146,21 @@ impl YoutubeDownloader {
         Ok(sorted_results)
     }
 
+    /// Search YouTube for a single upload of a whole album, closest to the album length first.
+    /// Unlike track searches this keeps the "full album" results `is_track` rejects.
+    pub async fn search_full_album(&self, artist: &str, album: &str, album_secs: u32, config: &crate::config::Config) -> Result<Vec<SearchResult>> {
+        let query = format!("{} {} full album", artist, album);
+        println!("🔍 Searching for full album upload: {}", query);
+
+        let mut results: Vec<SearchResult> = self.search_youtube(&query, 5, config).await?
+            .into_iter()
+            .filter(|result| result.duration.is_some())
+            .collect();
+        results.sort_by_key(|result| (result.duration.unwrap_or(0) as i64 - album_secs as i64).abs());
+
+        Ok(results)
+    }
+
     /// Search YouTube specifically using ytsearch5 for better results
     pub async fn search_youtube(&self, query: &str, max_results: u32, config: &crate::config::Config) -> Result<Vec<SearchResult>> {
         let search_limit = std::cmp::min(max_results, 5);
@@ -141,8 +187,8 @@ impl YoutubeDownloader {
             .arg("3") // More retries for better success rate
             .arg("--no-check-certificate") // Skip SSL verification for speed
             .arg("--prefer-free-formats") // Prefer free formats
-            .arg("--format") // Prefer audio-only formats
-            .arg("bestaudio[ext=m4a]/bestaudio[ext=mp3]/bestaudio")
+            .arg("--format") // The download picks its own stream from the formats list
+            .arg("bestaudio/best")
             .arg("--user-agent").arg("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36") // Modern user agent
             .arg("--extractor-retries").arg("3") // Retry extractor operations
             .arg("--fragment-retries").arg("3"); // Retry fragment downloads
@@ -164,14 +210,14 @@ impl YoutubeDownloader {
         
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(SpotifyDownloaderError::Youtube(format!("yt-dlp search failed: {}", stderr)));
+            return Err(SpotifyDownloaderError::from_yt_dlp_output("yt-dlp search failed", &stderr));
         }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let mut results = Vec::new();
         
-        // Parse JSON flexibly to handle both YouTube and SoundCloud formats
-        if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(stdout.trim()) {
+        // Parse JSON flexibly to handle both YouTube and SoundCloud formats (one object per line)
+        for json_value in stdout.lines().filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok()) {
             let title = json_value.get("title")
                 .and_then(|v| v.as_str())
                 .unwrap_or("Unknown Title")
@@ -197,6 +243,16 @@ impl YoutubeDownloader {
             let thumbnail = json_value.get("thumbnail")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
+
+            // Keep every format that parses, entries without the fields we need are skipped
+            let formats = json_value.get("formats")
+                .and_then(|v| v.as_array())
+                .map(|formats| {
+                    formats.iter()
+                        .filter_map(|format| serde_json::from_value::<YtDlpFormat>(format.clone()).ok())
+                        .collect()
+                })
+                .unwrap_or_default();
             
             // Construct URL based on platform
             let url = if platform == "SoundCloud" {
@@ -224,6 +280,7 @@ impl YoutubeDownloader {
                 view_count,
                 platform: platform.to_string(),
                 thumbnail,
+                formats,
             });
         }
 
@@ -231,16 +288,32 @@ impl YoutubeDownloader {
     }
 
     /// Download audio from URL using yt-dlp
+    #[allow(dead_code)]
     pub async fn download_audio(
         &self,
         url: &str,
         output_path: &PathBuf,
         format: AudioFormat,
-        bitrate: Bitrate,
-        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+        quality: Quality,
+        progress_callback: Option<Box<dyn Fn(TransferStats) + Send + Sync>>,
         config: &crate::config::Config,
     ) -> Result<()> {
-        self.download_with_yt_dlp(url, output_path, &format, &bitrate, &progress_callback, config).await
+        // "best" keeps the source codec and only remuxes it into an audio container
+        let audio_format = if quality == Quality::Original { "best" } else { format.yt_dlp_format() };
+        self.download_with_yt_dlp(url, output_path, None, audio_format, &quality, &progress_callback, config).await?;
+        Ok(())
+    }
+
+    /// Download an audio stream without transcoding, leaving conversion to the caller
+    pub async fn download_source_audio(
+        &self,
+        url: &str,
+        output_path: &PathBuf,
+        selected_format: Option<&SelectedFormat>,
+        progress_callback: Option<Box<dyn Fn(TransferStats) + Send + Sync>>,
+        config: &crate::config::Config,
+    ) -> Result<DownloadedAudio> {
+        self.download_with_yt_dlp(url, output_path, selected_format, "best", &Quality::Original, &progress_callback, config).await
     }
     
     
@@ -249,11 +322,12 @@ impl YoutubeDownloader {
         &self,
         url: &str,
         output_path: &PathBuf,
-        format: &AudioFormat,
-        bitrate: &Bitrate,
-        progress_callback: &Option<Box<dyn Fn(f32) + Send + Sync>>,
+        selected_format: Option<&SelectedFormat>,
+        audio_format: &str,
+        quality: &Quality,
+        progress_callback: &Option<Box<dyn Fn(TransferStats) + Send + Sync>>,
         config: &crate::config::Config,
-    ) -> Result<()> {
+    ) -> Result<DownloadedAudio> {
         // Create output directory if it doesn't exist
         if let Some(parent) = output_path.parent() {
             tokio::fs::create_dir_all(parent).await
@@ -277,14 +351,23 @@ impl YoutubeDownloader {
         // Build yt-dlp command with optimized settings
         let mut cmd = AsyncCommand::new(&self.executable_path);
         cmd.arg(url)
+            .arg("--format").arg(selected_format.map(|format| format.format_id.as_str()).unwrap_or("bestaudio/best"))
             .arg("--extract-audio")
-            .arg("--audio-format").arg(format.to_string())
-            .arg("--audio-quality").arg(&format!("{}", bitrate.as_u32()))
+            .arg("--audio-format").arg(audio_format)
+            .arg("--audio-quality").arg(quality.yt_dlp_audio_quality())
             .arg("--output").arg(format!("{}/{}", output_dir.display(), output_template))
             .arg("--paths").arg(format!("temp:{}", temp_dir.display())) // Use temp directory for part files
             .arg("--no-playlist")
             .arg("--progress")
             .arg("--newline")
+            .arg("--progress-template").arg(format!(
+                "download:{}%(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|%(progress.speed)s|%(progress.eta)s|%(progress.fragment_index)s|%(progress.fragment_count)s",
+                PROGRESS_PREFIX
+            ))
+            // Report the final file and the codec it was extracted from
+            .arg("--print").arg(format!("after_move:{}%(acodec)s|%(abr)s|%(filepath)s", SOURCE_INFO_PREFIX))
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
             .arg("--no-check-certificate") // Skip SSL verification for speed
             .arg("--prefer-free-formats") // Prefer free formats
             .arg("--socket-timeout").arg("30") // Reasonable timeout
@@ -313,57 +396,141 @@ impl YoutubeDownloader {
 
         // Cookies disabled - no cookie support
 
+        // Reserve this job's slice of the bandwidth cap until yt-dlp exits
+        let bandwidth_share = crate::utils::bandwidth::BandwidthLimiter::global().reserve_yt_dlp();
+        if let Some(rate) = bandwidth_share.rate().bytes_per_sec() {
+            cmd.arg("--limit-rate").arg(rate.to_string());
+        }
+
         // Execute command with progress monitoring
+        self.control.checkpoint().await?;
+        cmd.kill_on_drop(true);
+        DownloadControl::isolate(&mut cmd);
         let mut child = cmd.spawn()
             .map_err(|e| SpotifyDownloaderError::Youtube(format!("Failed to spawn yt-dlp: {}", e)))?;
+        let _process = self.control.register_process(child.id());
 
-        // For now, we'll use a simpler approach without progress monitoring
-        // TODO: Implement proper progress monitoring with yt-dlp
-        if progress_callback.is_some() {
-            // Just call the callback with 100% progress when done
-            // This is a temporary solution until we implement proper progress parsing
-            if let Some(callback) = progress_callback {
-                callback(1.0);
+        // Merge stdout and stderr so progress is seen wherever yt-dlp writes it
+        let (line_sender, mut line_receiver) = mpsc::unbounded_channel::<String>();
+        if let Some(stdout) = child.stdout.take() {
+            Self::forward_lines(stdout, line_sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            Self::forward_lines(stderr, line_sender.clone());
+        }
+        drop(line_sender);
+
+        let mut source_info = None;
+        let mut output_tail: VecDeque<String> = VecDeque::new();
+        let cancel_token = self.control.cancel_token();
+        loop {
+            let line = tokio::select! {
+                line = line_receiver.recv() => match line {
+                    Some(line) => line,
+                    None => break,
+                },
+                _ = cancel_token.cancelled() => {
+                    let _ = child.kill().await;
+                    // Leave no part files behind for a cancelled download
+                    self.cleanup_temp_files(&temp_dir).await?;
+                    return Err(SpotifyDownloaderError::Cancelled("Download cancelled".to_string()));
+                }
+            };
+            if let Some(stats) = Self::parse_progress_line(&line) {
+                if let Some(callback) = progress_callback {
+                    callback(stats);
+                }
+            } else if let Some(info) = line.strip_prefix(SOURCE_INFO_PREFIX) {
+                // Printed after the file is moved into place
+                source_info = Some(info.to_string());
+            } else if !line.trim().is_empty() {
+                if output_tail.len() == OUTPUT_TAIL_LINES {
+                    output_tail.pop_front();
+                }
+                output_tail.push_back(line);
             }
         }
-        
+
         // Wait for command to complete
         let status = child.wait().await
             .map_err(|e| SpotifyDownloaderError::Youtube(format!("Failed to wait for yt-dlp: {}", e)))?;
         
         if !status.success() {
-            return Err(SpotifyDownloaderError::Youtube(format!("yt-dlp failed with status: {}", status)));
+            let output = Vec::from(output_tail).join("\n");
+            return Err(SpotifyDownloaderError::from_yt_dlp_output(&format!("yt-dlp failed with status: {}", status), &output));
         }
+
+        let mut fields = source_info.as_deref().unwrap_or_default().splitn(3, '|');
+        let source_codec = fields.next().and_then(SourceCodec::from_acodec)
+            .or_else(|| selected_format.and_then(|format| format.codec()));
+        let source_bitrate_kbps = fields.next().and_then(|abr| abr.parse::<f32>().ok())
+            .or_else(|| selected_format.and_then(|format| format.abr));
+        let downloaded_file = fields.next()
+            .map(PathBuf::from)
+            .filter(|path| path.exists());
         
-        // Find the downloaded file and rename it to the expected output path
-        self.rename_downloaded_file(&output_dir, &output_template, &format.to_string(), output_path).await?;
+        // Move the downloaded file next to the expected output path, keeping its real extension
+        let path = match downloaded_file {
+            Some(downloaded_file) => {
+                let mut path = output_path.clone();
+                if let Some(extension) = downloaded_file.extension() {
+                    path.set_extension(extension);
+                }
+                if downloaded_file != path {
+                    tokio::fs::rename(&downloaded_file, &path).await
+                        .map_err(|e| SpotifyDownloaderError::Youtube(format!("Failed to rename downloaded file: {}", e)))?;
+                }
+                path
+            }
+            None => {
+                self.rename_downloaded_file(&output_dir, &output_template, output_path).await?;
+                output_path.clone()
+            }
+        };
 
         // Clean up any remaining temp files
         self.cleanup_temp_files(&temp_dir).await?;
 
-        Ok(())
+        Ok(DownloadedAudio {
+            path,
+            source_codec,
+            source_bitrate_kbps,
+            format: selected_format.cloned(),
+        })
     }
 
-    /// Parse progress line from yt-dlp output
-    #[allow(dead_code)]
-    fn parse_progress_line(&self, line: &str) -> Option<f32> {
-        Self::parse_progress_line_static(line)
+    /// Parse a line written by our --progress-template
+    fn parse_progress_line(line: &str) -> Option<TransferStats> {
+        let mut fields = line.trim().strip_prefix(PROGRESS_PREFIX)?.split('|');
+        // yt-dlp prints "NA" for values it does not know yet
+        let mut next = || fields.next().filter(|value| *value != "NA");
+
+        let downloaded_bytes = next().and_then(|value| value.parse::<f64>().ok())? as u64;
+        let total_bytes = next().and_then(|value| value.parse::<f64>().ok());
+        let total_bytes_estimate = next().and_then(|value| value.parse::<f64>().ok());
+        Some(TransferStats {
+            downloaded_bytes,
+            total_bytes: total_bytes.or(total_bytes_estimate).map(|total| total as u64),
+            speed_bytes_per_sec: next().and_then(|value| value.parse().ok()),
+            eta_secs: next().and_then(|value| value.parse::<f64>().ok()).map(|eta| eta as u64),
+            fragment_index: next().and_then(|value| value.parse().ok()),
+            fragment_count: next().and_then(|value| value.parse().ok()),
+        })
     }
 
-    /// Parse progress line from yt-dlp output (static version)
-    #[allow(dead_code)]
-    fn parse_progress_line_static(line: &str) -> Option<f32> {
-        // yt-dlp progress format: [download] 45.2% of 12.34MiB at 1.23MiB/s ETA 00:05
-        if let Some(start) = line.find("[download]") {
-            let progress_part = &line[start + 10..];
-            if let Some(percent_start) = progress_part.find('%') {
-                let percent_str = &progress_part[..percent_start].trim();
-                if let Ok(percent) = percent_str.parse::<f32>() {
-                    return Some(percent / 100.0);
+    /// Send each line of a child process stream to the channel
+    fn forward_lines<R>(reader: R, sender: mpsc::UnboundedSender<String>)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if sender.send(line).is_err() {
+                    break;
                 }
             }
-        }
-        None
+        });
     }
 
     /// Rename downloaded file to expected output path
@@ -371,16 +538,18 @@ impl YoutubeDownloader {
         &self,
         output_dir: &PathBuf,
         base_name: &str,
-        format: &str,
         expected_path: &PathBuf,
     ) -> Result<()> {
+        let extension = expected_path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
         if let Ok(entries) = tokio::fs::read_dir(output_dir).await {
             let mut found_file = None;
             let mut entries = entries;
             
             while let Some(entry) = entries.next_entry().await.unwrap_or(None) {
                 if let Some(file_name) = entry.file_name().to_str() {
-                    if file_name.starts_with(base_name) && file_name.ends_with(&format!(".{}", format)) {
+                    if file_name.starts_with(base_name) && file_name.ends_with(&format!(".{}", extension)) {
                         found_file = Some(entry.path());
                         break;
                     }
@@ -464,7 +633,6 @@ impl YoutubeDownloader {
     }
 
     /// Get video/audio information
-    #[allow(dead_code)]
     pub async fn get_info(&self, url: &str) -> Result<YtDlpInfo> {
         let mut cmd = AsyncCommand::new(&self.executable_path);
         cmd.arg(url)
@@ -477,7 +645,7 @@ impl YoutubeDownloader {
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(SpotifyDownloaderError::Youtube(format!("yt-dlp failed: {}", stderr)));
+            return Err(SpotifyDownloaderError::from_yt_dlp_output("yt-dlp failed", &stderr));
         }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -501,6 +669,15 @@ impl YoutubeDownloader {
     }
 }
 
+/// Audio file written by yt-dlp and the stream it came from
+#[derive(Debug, Clone)]
+pub struct DownloadedAudio {
+    pub path: PathBuf,
+    pub source_codec: Option<SourceCodec>,
+    pub source_bitrate_kbps: Option<f32>,
+    pub format: Option<SelectedFormat>,
+}
+
 /// Search result from YouTube or SoundCloud
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -511,6 +688,7 @@ pub struct SearchResult {
     pub view_count: u64,
     pub platform: String,
     pub thumbnail: Option<String>,
+    pub formats: Vec<YtDlpFormat>,
 }
 
 /// yt-dlp information structure
@@ -526,6 +704,16 @@ pub struct YtDlpInfo {
     pub description: Option<String>,
     pub upload_date: Option<String>,
     pub formats: Option<Vec<YtDlpFormat>>,
+    #[serde(default)]
+    pub chapters: Option<Vec<YtDlpChapter>>,
+}
+
+/// A chapter marker set on a video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YtDlpChapter {
+    pub start_time: f64,
+    pub end_time: Option<f64>,
+    pub title: Option<String>,
 }
 
 /// yt-dlp format information
@@ -537,7 +725,10 @@ pub struct YtDlpFormat {
     pub vcodec: Option<String>,
     pub abr: Option<f32>, // audio bitrate
     pub vbr: Option<f32>, // video bitrate
+    pub asr: Option<u32>, // audio sample rate
+    pub format_note: Option<String>,
     pub filesize: Option<u64>,
+    #[serde(default)]
     pub url: String,
 }
 
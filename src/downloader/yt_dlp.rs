@@ -1,4 +1,5 @@
 use crate::errors::{Result, SpotifyDownloaderError};
+use crate::config::Quality;
 use std::path::PathBuf;
 use tokio::process::Command as AsyncCommand;
 
@@ -36,7 +37,7 @@ impl YtDlpDownloader {
         url: &str,
         output_path: &PathBuf,
         format: &str,
-        bitrate: u32,
+        quality: Quality,
     ) -> Result<()> {
         let output_dir = output_path.parent()
             .ok_or_else(|| SpotifyDownloaderError::Download("Invalid output path".to_string()))?;
@@ -49,11 +50,16 @@ impl YtDlpDownloader {
         cmd.arg(url)
             .arg("--extract-audio")
             .arg("--audio-format").arg(format)
-            .arg("--audio-quality").arg(&format!("{}", bitrate))
+            .arg("--audio-quality").arg(quality.yt_dlp_audio_quality())
             .arg("--output").arg(format!("{}/%(title)s.%(ext)s", output_dir.display()))
             .arg("--no-playlist")
             .arg("--quiet");
 
+        let bandwidth_share = crate::utils::bandwidth::BandwidthLimiter::global().reserve_yt_dlp();
+        if let Some(rate) = bandwidth_share.rate().bytes_per_sec() {
+            cmd.arg("--limit-rate").arg(rate.to_string());
+        }
+
         let output = cmd.output().await
             .map_err(|e| SpotifyDownloaderError::Download(format!("Failed to execute yt-dlp: {}", e)))?;
 
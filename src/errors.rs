@@ -75,6 +75,28 @@ pub enum SpotifyDownloaderError {
     #[error("Conversion error: {0}")]
     Conversion(String),
 
+    #[error("Loudness analysis error: {0}")]
+    Loudness(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        /// Wait requested by the server, if it said
+        retry_after_secs: Option<u64>,
+    },
+
+    #[error("HTTP {status}: {message}")]
+    HttpStatus { status: u16, message: String },
+
+    #[error("Source unavailable: {0}")]
+    SourceUnavailable(String),
+
+    #[error("No matching source: {0}")]
+    NoMatch(String),
+
     #[error("CSV import error: {0}")]
     CsvImport(String),
 
@@ -97,6 +119,183 @@ pub enum SpotifyDownloaderError {
     Unknown(String),
 }
 
+/// Whether retrying a failed operation can help
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    /// Network trouble, server errors and rate limiting, worth retrying
+    Transient,
+    /// Retrying gives the same result (unavailable video, no match, invalid input)
+    Permanent,
+    /// Stopped by the user
+    Cancelled,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Transient => write!(f, "transient"),
+            ErrorKind::Permanent => write!(f, "permanent"),
+            ErrorKind::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Messages from yt-dlp and the APIs that mean the request may succeed later
+const TRANSIENT_MARKERS: &[&str] = &[
+    "http error 429",
+    "too many requests",
+    "http error 500",
+    "http error 502",
+    "http error 503",
+    "http error 504",
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "connection aborted",
+    "broken pipe",
+    "network is unreachable",
+    "temporary failure in name resolution",
+    "unable to download webpage",
+    "unable to download json metadata",
+    "incompleteread",
+    "throttl",
+    "temporarily unavailable",
+];
+
+/// Messages that mean the source itself is gone, checked before the transient markers
+const PERMANENT_MARKERS: &[&str] = &[
+    "video unavailable",
+    "private video",
+    "this video is not available",
+    "has been removed",
+    "copyright",
+    "sign in to confirm your age",
+    "members-only",
+    "unsupported url",
+    "is not a valid url",
+    "requested format is not available",
+];
+
+impl SpotifyDownloaderError {
+    /// Classify the error as transient, permanent or cancelled
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SpotifyDownloaderError::Cancelled(_) => ErrorKind::Cancelled,
+            SpotifyDownloaderError::RateLimited { .. } => ErrorKind::Transient,
+            SpotifyDownloaderError::HttpStatus { status, .. } => Self::status_kind(*status),
+            SpotifyDownloaderError::SourceUnavailable(_)
+            | SpotifyDownloaderError::NoMatch(_)
+            | SpotifyDownloaderError::InvalidUrl(_)
+            | SpotifyDownloaderError::InvalidFormat(_)
+            | SpotifyDownloaderError::InvalidBitrate(_)
+            | SpotifyDownloaderError::FileNotFound(_)
+            | SpotifyDownloaderError::PermissionDenied(_) => ErrorKind::Permanent,
+            SpotifyDownloaderError::Network(e) => match e.status() {
+                Some(status) => Self::status_kind(status.as_u16()),
+                // Timeouts, refused connections and dropped bodies
+                None if e.is_builder() || e.is_decode() => ErrorKind::Permanent,
+                None => ErrorKind::Transient,
+            },
+            SpotifyDownloaderError::Io(e) => match e.kind() {
+                std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::Interrupted
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::UnexpectedEof => ErrorKind::Transient,
+                _ => ErrorKind::Permanent,
+            },
+            SpotifyDownloaderError::Youtube(message)
+            | SpotifyDownloaderError::Soundcloud(message)
+            | SpotifyDownloaderError::Download(message)
+            | SpotifyDownloaderError::Spotify(message)
+            | SpotifyDownloaderError::Itunes(message)
+            | SpotifyDownloaderError::Lastfm(message)
+            | SpotifyDownloaderError::Musicbrainz(message)
+            | SpotifyDownloaderError::Api(message)
+            | SpotifyDownloaderError::Lyrics(message)
+            | SpotifyDownloaderError::CoverArt(message) => Self::message_kind(message),
+            _ => ErrorKind::Permanent,
+        }
+    }
+
+    /// Whether retrying might succeed
+    pub fn is_transient(&self) -> bool {
+        self.kind() == ErrorKind::Transient
+    }
+
+    /// Wait the server asked for before the next attempt
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            SpotifyDownloaderError::RateLimited { retry_after_secs, .. } => {
+                retry_after_secs.map(std::time::Duration::from_secs)
+            }
+            _ => None,
+        }
+    }
+
+    /// Turn yt-dlp's error output into a classified error
+    pub fn from_yt_dlp_output(context: &str, output: &str) -> Self {
+        let lower = output.to_lowercase();
+        let message = format!("{}: {}", context, output.trim());
+        if lower.contains("http error 429") || lower.contains("too many requests") {
+            SpotifyDownloaderError::RateLimited { message, retry_after_secs: None }
+        } else if PERMANENT_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            SpotifyDownloaderError::SourceUnavailable(message)
+        } else {
+            SpotifyDownloaderError::Youtube(message)
+        }
+    }
+
+    /// Turn a failed HTTP response into a classified error, keeping the wait a 429 asked for
+    pub async fn from_response(context: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after_secs = Self::retry_after_secs(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        let message = if body.trim().is_empty() {
+            context.to_string()
+        } else {
+            format!("{}: {}", context, body.trim())
+        };
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            SpotifyDownloaderError::RateLimited { message, retry_after_secs }
+        } else {
+            SpotifyDownloaderError::HttpStatus { status: status.as_u16(), message }
+        }
+    }
+
+    /// Seconds from a Retry-After header; the HTTP date form is left to the normal backoff
+    fn retry_after_secs(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+        headers.get(reqwest::header::RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    fn status_kind(status: u16) -> ErrorKind {
+        match status {
+            408 | 425 | 429 | 500..=599 => ErrorKind::Transient,
+            _ => ErrorKind::Permanent,
+        }
+    }
+
+    fn message_kind(message: &str) -> ErrorKind {
+        let lower = message.to_lowercase();
+        if PERMANENT_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            ErrorKind::Permanent
+        } else if TRANSIENT_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            ErrorKind::Transient
+        } else {
+            ErrorKind::Permanent
+        }
+    }
+}
+
 /// Result type alias for the application
 pub type Result<T> = std::result::Result<T, SpotifyDownloaderError>;
 
@@ -38,6 +38,8 @@ async fn main() -> Result<()> {
         proxy_config: Some(config.proxy_config.clone()),
     };
     init_api_manager(api_config);
+    utils::bandwidth::BandwidthLimiter::global().configure(config.bandwidth.clone());
+    utils::bandwidth::BandwidthLimiter::global().set_pool_size(config.ui_preferences.max_concurrent_downloads as usize);
 
     // Parse command line arguments
     let cli = Cli::parse();
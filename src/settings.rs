@@ -99,6 +99,24 @@ impl Settings {
         self.save()
     }
 
+    /// Update the quality used for one output format
+    pub fn set_format_quality(&mut self, format: crate::config::AudioFormat, quality: crate::config::Quality) -> Result<()> {
+        self.config.format_quality.insert(format, quality);
+        self.save()
+    }
+
+    /// Update how lossless formats are produced from lossy sources
+    pub fn set_lossy_to_lossless_policy(&mut self, policy: crate::config::LossyToLosslessPolicy) -> Result<()> {
+        self.config.conversion_config.lossy_to_lossless = policy;
+        self.save()
+    }
+
+    /// Update the bandwidth cap for all downloads together
+    pub fn set_bandwidth_limit(&mut self, limit: crate::config::RateLimit) -> Result<()> {
+        self.config.bandwidth.limit = limit;
+        self.save()
+    }
+
     /// Update cover art configuration
     pub fn set_cover_config(&mut self, width: u32, height: u32, format: String) -> Result<()> {
         self.config.cover_config.width = width;
@@ -11,8 +11,9 @@ use std::path::PathBuf;
 use std::collections::HashMap;
 use tokio::sync::mpsc;
 
-use crate::config::{AudioFormat, Bitrate};
+use crate::config::{AudioFormat, Bitrate, Quality};
 use crate::downloader::{TrackMetadata, DownloadStage};
+use crate::downloader::control::DownloadControl;
 use crate::settings::Settings as AppSettings;
 use crate::csv_import::{CsvImporter, CsvInfo};
 use crate::errors::Result;
@@ -55,6 +56,8 @@ pub struct SpotifyDownloaderApp {
     // Download State
     download_progress: HashMap<String, f32>,
     download_status: HashMap<String, DownloadStage>,
+    /// Jobs left unfinished by the last session, offered for resuming until dismissed
+    unfinished_jobs: Vec<crate::downloader::job_store::DownloadJob>,
     
     // UI State
     url_validation_error: Option<String>,
@@ -62,6 +65,7 @@ pub struct SpotifyDownloaderApp {
     output_directory: String,
     selected_format: AudioFormat,
     selected_bitrate: Bitrate,
+    format_quality: HashMap<AudioFormat, Quality>,
     download_lyrics: bool,
     download_cover: bool,
     embed_metadata: bool,
@@ -78,10 +82,13 @@ pub struct SpotifyDownloaderApp {
     embed_album_artist: bool,
     embed_composer: bool,
     embed_comment: bool,
+    enrich_from_lastfm: bool,
+    embed_replaygain: bool,
     
     // Async Communication
     command_sender: Option<mpsc::UnboundedSender<AppCommand>>,
     progress_sender: Option<mpsc::UnboundedSender<crate::downloader::DownloadProgress>>,
+    download_control: Option<DownloadControl>,
     
     // Drag & Drop State
     is_drag_over: bool,
@@ -136,6 +143,7 @@ pub enum AppCommand {
     AddMultipleTracks(Vec<TrackMetadata>),
     UpdateProgress(String, f32),
     UpdateStatus(String, DownloadStage),
+    UpdateEvent(String, crate::downloader::DownloadEvent),
     SetError(String, String),
     CompleteDownload(String),
     BatchDownloadComplete(Vec<crate::downloader::DownloadTaskResult>),
@@ -177,6 +185,8 @@ pub enum Message {
     StartDownload,
     PauseDownload,
     StopDownload,
+    ResumeUnfinished,
+    DismissUnfinished,
     
     // Settings
     ToggleSettings,
@@ -184,7 +194,7 @@ pub enum Message {
     SelectOutputDirectory,
     OpenOutputFolder,
     UpdateFormat(AudioFormat),
-    UpdateBitrate(Bitrate),
+    UpdateQuality(Quality),
     ToggleLyrics(bool),
     ToggleCover(bool),
     ToggleMetadata(bool),
@@ -201,6 +211,8 @@ pub enum Message {
     ToggleAlbumArtist(bool),
     ToggleComposer(bool),
     ToggleComment(bool),
+    ToggleLastfmEnrichment(bool),
+    ToggleReplayGain(bool),
     SaveSettings,
     ResetSettings,
     
@@ -261,6 +273,13 @@ impl Application for SpotifyDownloaderApp {
 
         let config = settings.config().clone();
         let ui_prefs = &config.ui_preferences;
+        let unfinished_jobs = match crate::downloader::job_store::JobStore::open_default() {
+            Ok(store) => store.unfinished(),
+            Err(e) => {
+                println!("⚠️ Download queue unavailable, unfinished downloads can't be resumed: {}", e);
+                Vec::new()
+            }
+        };
         
         let app = Self {
             current_view: View::Import,
@@ -288,11 +307,13 @@ impl Application for SpotifyDownloaderApp {
             musixmatch_api_key: config.api_keys.musixmatch_api_key.clone().unwrap_or_default(),
             download_progress: HashMap::new(),
             download_status: HashMap::new(),
+            unfinished_jobs,
             url_validation_error: None,
             settings_message: None,
             output_directory: config.download_directory.to_string_lossy().to_string(),
             selected_format: config.default_format,
             selected_bitrate: config.default_bitrate,
+            format_quality: config.format_quality.clone(),
             download_lyrics: ui_prefs.auto_download_lyrics,
             download_cover: ui_prefs.auto_download_covers,
             embed_metadata: config.metadata_config.embed_metadata,
@@ -307,8 +328,11 @@ impl Application for SpotifyDownloaderApp {
             embed_album_artist: config.metadata_config.embed_album_artist,
             embed_composer: true, // Not in metadata config yet
             embed_comment: true, // Not in metadata config yet
+            enrich_from_lastfm: config.metadata_config.enrich_from_lastfm,
+            embed_replaygain: config.metadata_config.embed_replaygain,
             command_sender: None,
             progress_sender: None,
+            download_control: None,
             is_drag_over: false,
             csv_import_progress: None,
             csv_import_status: None,
@@ -605,6 +629,7 @@ impl Application for SpotifyDownloaderApp {
                             let output_dir = PathBuf::from(&self.output_directory);
                             let format = self.selected_format;
                             let bitrate = self.selected_bitrate;
+                            let quality = self.selected_quality();
                             
                             // Create progress channel
                             let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
@@ -616,7 +641,8 @@ impl Application for SpotifyDownloaderApp {
                                 while let Some(progress) = progress_rx.recv().await {
                                     if let Some(sender) = &command_sender {
                                         let _ = sender.send(AppCommand::UpdateProgress(track_id_clone.clone(), progress.progress));
-                                        let _ = sender.send(AppCommand::UpdateStatus(track_id_clone.clone(), progress.stage));
+                                        let _ = sender.send(AppCommand::UpdateStatus(track_id_clone.clone(), progress.stage()));
+                                        let _ = sender.send(AppCommand::UpdateEvent(track_id_clone.clone(), progress.event));
                                     }
                                 }
                             });
@@ -635,6 +661,7 @@ impl Application for SpotifyDownloaderApp {
                             
                             // Clone config for the async closure
                             let config = self.settings.config().clone();
+                            let control = Self::active_download_control(&mut self.download_control);
                             
                             // Spawn download task
                             return Command::perform(
@@ -647,9 +674,11 @@ impl Application for SpotifyDownloaderApp {
                                     });
                                     let client = api_manager.client().clone();
                                     let mut downloader = AudioDownloader::new_with_client(client);
+                                    downloader.set_control(control);
                                     let options = DownloadOptions {
                                         format,
                                         bitrate,
+                                        quality,
                                         output_dir,
                                         download_lyrics: true,
                                         download_cover: true,
@@ -668,6 +697,12 @@ impl Application for SpotifyDownloaderApp {
                                         embed_album_artist,
                                         embed_composer,
                                         embed_comment,
+                                        embed_bpm: config.metadata_config.embed_bpm,
+                                        embed_audio_features: config.metadata_config.embed_audio_features,
+                                        key_notation: config.metadata_config.key_notation,
+                                        normalization: config.normalization.clone(),
+                                        trim: config.trim.clone(),
+                                        output_path: None,
                                     };
                                     
                                     match downloader.download_track(&metadata, &options, Some(progress_tx), &config).await {
@@ -701,6 +736,7 @@ impl Application for SpotifyDownloaderApp {
                         let output_dir = PathBuf::from(&self.output_directory);
                         let format = self.selected_format;
                         let bitrate = self.selected_bitrate;
+                        let quality = self.selected_quality();
                         
                         // Create progress channel
                         let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
@@ -712,7 +748,8 @@ impl Application for SpotifyDownloaderApp {
                             while let Some(progress) = progress_rx.recv().await {
                                 if let Some(sender) = &command_sender {
                                     let _ = sender.send(AppCommand::UpdateProgress(track_id_clone.clone(), progress.progress));
-                                    let _ = sender.send(AppCommand::UpdateStatus(track_id_clone.clone(), progress.stage));
+                                    let _ = sender.send(AppCommand::UpdateStatus(track_id_clone.clone(), progress.stage()));
+                                    let _ = sender.send(AppCommand::UpdateEvent(track_id_clone.clone(), progress.event));
                                 }
                             }
                         });
@@ -731,6 +768,7 @@ impl Application for SpotifyDownloaderApp {
                         
                         // Clone config for the async closure
                         let config = self.settings.config().clone();
+                        let control = Self::active_download_control(&mut self.download_control);
                         
                         return Command::perform(
                             async move {
@@ -742,9 +780,11 @@ impl Application for SpotifyDownloaderApp {
                                 });
                                 let client = api_manager.client().clone();
                                 let mut downloader = AudioDownloader::new_with_client(client);
+                                downloader.set_control(control);
                                 let options = DownloadOptions {
                                     format,
                                     bitrate,
+                                    quality,
                                     output_dir,
                                     download_lyrics: true,
                                     download_cover: true,
@@ -763,6 +803,12 @@ impl Application for SpotifyDownloaderApp {
                                     embed_album_artist,
                                     embed_composer,
                                     embed_comment,
+                                    embed_bpm: config.metadata_config.embed_bpm,
+                                    embed_audio_features: config.metadata_config.embed_audio_features,
+                                    key_notation: config.metadata_config.key_notation,
+                                    normalization: config.normalization.clone(),
+                                    trim: config.trim.clone(),
+                                    output_path: None,
                                 };
                                 
                                 match downloader.download_track(&metadata, &options, Some(progress_tx), &config).await {
@@ -800,6 +846,7 @@ impl Application for SpotifyDownloaderApp {
                     let output_dir = PathBuf::from(&self.output_directory);
                     let format = self.selected_format;
                     let bitrate = self.selected_bitrate;
+                    let quality = self.selected_quality();
                     let max_concurrent = self.max_concurrent_downloads as usize;
                     
                     // Clone metadata toggles for the async closure
@@ -816,6 +863,7 @@ impl Application for SpotifyDownloaderApp {
                     
                     // Clone config for the async closure
                     let config = self.settings.config().clone();
+                    let control = Self::active_download_control(&mut self.download_control);
                     
                     // Create progress channel for monitoring
                     let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
@@ -830,7 +878,8 @@ impl Application for SpotifyDownloaderApp {
                         while let Some(progress) = progress_rx.recv().await {
                             // Use the track_id from the progress message
                             let _ = command_tx_clone.send(AppCommand::UpdateProgress(progress.track_id.clone(), progress.progress));
-                            let _ = command_tx_clone.send(AppCommand::UpdateStatus(progress.track_id, progress.stage));
+                            let _ = command_tx_clone.send(AppCommand::UpdateStatus(progress.track_id.clone(), progress.stage()));
+                            let _ = command_tx_clone.send(AppCommand::UpdateEvent(progress.track_id, progress.event));
                         }
                     });
                     
@@ -840,10 +889,20 @@ impl Application for SpotifyDownloaderApp {
                             
                             let mut download_manager = AsyncDownloadManager::new(max_concurrent);
                             download_manager.set_progress_sender(progress_tx);
+                            download_manager.set_control(control);
+                            match crate::downloader::job_store::JobStore::open_shared() {
+                                Ok(store) => download_manager.set_job_store(store),
+                                Err(e) => println!("⚠️ Download queue unavailable, progress will not survive a restart: {}", e),
+                            }
+                            match crate::downloader::archive::DownloadArchive::open_shared() {
+                                Ok(archive) => download_manager.set_archive(archive),
+                                Err(e) => println!("⚠️ Download archive unavailable, already downloaded tracks won't be skipped: {}", e),
+                            }
                             
                             let options = DownloadOptions {
                                 format,
                                 bitrate,
+                                quality,
                                 output_dir,
                                 download_lyrics: true,
                                 download_cover: true,
@@ -862,6 +921,12 @@ impl Application for SpotifyDownloaderApp {
                                 embed_album_artist,
                                 embed_composer,
                                 embed_comment,
+                                embed_bpm: config.metadata_config.embed_bpm,
+                                embed_audio_features: config.metadata_config.embed_audio_features,
+                                key_notation: config.metadata_config.key_notation,
+                                normalization: config.normalization.clone(),
+                                trim: config.trim.clone(),
+                                output_path: None,
                             };
                             
                             match download_manager.download_tracks(pending_tracks, &options, &config).await {
@@ -877,20 +942,107 @@ impl Application for SpotifyDownloaderApp {
                 }
             }
             
+            Message::ResumeUnfinished => {
+                let jobs = std::mem::take(&mut self.unfinished_jobs);
+                for job in jobs {
+                    let stage_message = Some("Resuming unfinished download...".to_string());
+                    if let Some(track) = self.tracks.iter_mut().find(|t| t.metadata.id == job.track.id) {
+                        track.status = TrackStatus::Downloading;
+                        track.stage_message = stage_message;
+                    } else {
+                        self.tracks.push(TrackItem {
+                            metadata: job.track,
+                            status: TrackStatus::Downloading,
+                            progress: 0.0,
+                            error_message: None,
+                            current_stage: job.stage,
+                            stage_message,
+                        });
+                    }
+                }
+                self.current_view = View::TrackList;
+
+                let max_concurrent = self.max_concurrent_downloads as usize;
+                let config = self.settings.config().clone();
+                let control = Self::active_download_control(&mut self.download_control);
+
+                let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<crate::downloader::DownloadProgress>();
+                let (command_tx, _command_rx) = mpsc::unbounded_channel::<AppCommand>();
+                self.command_sender = Some(command_tx.clone());
+                tokio::spawn(async move {
+                    while let Some(progress) = progress_rx.recv().await {
+                        let _ = command_tx.send(AppCommand::UpdateProgress(progress.track_id.clone(), progress.progress));
+                        let _ = command_tx.send(AppCommand::UpdateStatus(progress.track_id.clone(), progress.stage()));
+                        let _ = command_tx.send(AppCommand::UpdateEvent(progress.track_id, progress.event));
+                    }
+                });
+
+                return Command::perform(
+                    async move {
+                        use crate::downloader::AsyncDownloadManager;
+
+                        let mut download_manager = AsyncDownloadManager::new(max_concurrent);
+                        download_manager.set_progress_sender(progress_tx);
+                        download_manager.set_control(control);
+                        let store = crate::downloader::job_store::JobStore::open_shared()
+                            .map_err(|e| format!("Download queue unavailable: {}", e))?;
+                        download_manager.set_job_store(store);
+                        match crate::downloader::archive::DownloadArchive::open_shared() {
+                            Ok(archive) => download_manager.set_archive(archive),
+                            Err(e) => println!("⚠️ Download archive unavailable, already downloaded tracks won't be skipped: {}", e),
+                        }
+
+                        download_manager.resume_jobs(&config).await
+                            .map_err(|e| format!("Download manager failed: {}", e))
+                    },
+                    |result| match result {
+                        Ok(results) => Message::CommandReceived(AppCommand::BatchDownloadComplete(results)),
+                        Err(error) => Message::CommandReceived(AppCommand::SetError("batch_download".to_string(), error)),
+                    }
+                );
+            }
+
+            Message::DismissUnfinished => {
+                // The jobs stay queued, so they are offered again next time
+                self.unfinished_jobs.clear();
+            }
+            
             Message::PauseDownload => {
                 println!("PauseDownload button pressed");
-                let mut paused_count = 0;
-                for track in &mut self.tracks {
-                    if matches!(track.status, TrackStatus::Downloading) {
-                        track.status = TrackStatus::Paused;
-                        paused_count += 1;
+                let Some(control) = self.download_control.clone().filter(|control| !control.is_cancelled()) else {
+                    println!("No downloads running");
+                    return Command::none();
+                };
+                // The same button resumes a paused batch
+                if control.is_paused() {
+                    control.resume();
+                    let mut resumed_count = 0;
+                    for track in &mut self.tracks {
+                        if matches!(track.status, TrackStatus::Paused) {
+                            track.status = TrackStatus::Downloading;
+                            resumed_count += 1;
+                        }
+                    }
+                    println!("Resumed {} paused tracks", resumed_count);
+                } else {
+                    control.pause();
+                    let mut paused_count = 0;
+                    for track in &mut self.tracks {
+                        if matches!(track.status, TrackStatus::Downloading) {
+                            track.status = TrackStatus::Paused;
+                            paused_count += 1;
+                        }
                     }
+                    println!("Paused {} downloading tracks", paused_count);
                 }
-                println!("Paused {} downloading tracks", paused_count);
             }
             
             Message::StopDownload => {
                 println!("StopDownload button pressed");
+                // Kill running yt-dlp/ffmpeg processes, the tasks clean up their partial files
+                if let Some(control) = self.download_control.take() {
+                    control.cancel();
+                }
                 let mut stopped_count = 0;
                 for track in &mut self.tracks {
                     if matches!(track.status, TrackStatus::Downloading | TrackStatus::Paused) {
@@ -930,8 +1082,8 @@ impl Application for SpotifyDownloaderApp {
                 self.selected_format = format;
             }
             
-            Message::UpdateBitrate(bitrate) => {
-                self.selected_bitrate = bitrate;
+            Message::UpdateQuality(quality) => {
+                self.format_quality.insert(self.selected_format, quality);
             }
             
             Message::UpdateConcurrency(concurrency) => {
@@ -991,6 +1143,14 @@ impl Application for SpotifyDownloaderApp {
                 self.embed_comment = enabled;
             }
             
+            Message::ToggleLastfmEnrichment(enabled) => {
+                self.enrich_from_lastfm = enabled;
+            }
+            
+            Message::ToggleReplayGain(enabled) => {
+                self.embed_replaygain = enabled;
+            }
+            
             Message::SaveSettings => {
                 println!("SaveSettings button pressed");
                 // Update all settings from UI state
@@ -1009,6 +1169,13 @@ impl Application for SpotifyDownloaderApp {
                     self.settings_message = Some(format!("Settings Error: {}", e));
                     return Command::none();
                 }
+
+                for (format, quality) in &self.format_quality {
+                    if let Err(e) = self.settings.set_format_quality(*format, *quality) {
+                        self.settings_message = Some(format!("Settings Error: {}", e));
+                        return Command::none();
+                    }
+                }
                 
                 // Update API keys
                 if let Err(e) = self.settings.set_spotify_credentials(
@@ -1061,6 +1228,8 @@ impl Application for SpotifyDownloaderApp {
                 metadata_config.embed_album_artist = self.embed_album_artist;
                 metadata_config.embed_lyrics = self.download_lyrics;
                 metadata_config.embed_cover = self.download_cover;
+                metadata_config.enrich_from_lastfm = self.enrich_from_lastfm;
+                metadata_config.embed_replaygain = self.embed_replaygain;
                 
                 // Save settings
                 if let Err(e) = self.settings.save() {
@@ -1083,6 +1252,7 @@ impl Application for SpotifyDownloaderApp {
                 self.output_directory = config.download_directory.to_string_lossy().to_string();
                 self.selected_format = config.default_format;
                 self.selected_bitrate = config.default_bitrate;
+                self.format_quality = config.format_quality.clone();
                 self.download_lyrics = ui_prefs.auto_download_lyrics;
                 self.download_cover = ui_prefs.auto_download_covers;
                 self.embed_metadata = config.metadata_config.embed_metadata;
@@ -1096,6 +1266,8 @@ impl Application for SpotifyDownloaderApp {
                 self.embed_album_artist = config.metadata_config.embed_album_artist;
                 self.embed_composer = true; // Not in metadata config yet
                 self.embed_comment = true; // Not in metadata config yet
+                self.enrich_from_lastfm = config.metadata_config.enrich_from_lastfm;
+                self.embed_replaygain = config.metadata_config.embed_replaygain;
                 
                 // Clear API keys
                 self.spotify_client_id = config.api_keys.spotify_client_id.clone().unwrap_or_default();
@@ -1318,7 +1490,9 @@ impl Application for SpotifyDownloaderApp {
                             track.stage_message = Some(match status {
                                 DownloadStage::SearchingSource => "Searching for audio source...".to_string(),
                                 DownloadStage::DownloadingAudio => "Downloading audio...".to_string(),
+                                DownloadStage::TrimmingAudio => "Trimming silence...".to_string(),
                                 DownloadStage::ConvertingAudio => "Converting audio format...".to_string(),
+                                DownloadStage::AnalyzingLoudness => "Analyzing loudness...".to_string(),
                                 DownloadStage::DownloadingCover => "Downloading cover art and lyrics...".to_string(),
                                 DownloadStage::EmbeddingMetadata => "Embedding metadata...".to_string(),
                                 DownloadStage::Completed => "Download completed successfully!".to_string(),
@@ -1329,6 +1503,28 @@ impl Application for SpotifyDownloaderApp {
                             });
                         }
                     }
+                    AppCommand::UpdateEvent(track_id, event) => {
+                        use crate::downloader::DownloadEvent;
+                        if let Some(track) = self.tracks.iter_mut().find(|t| t.metadata.id == track_id) {
+                            let message = match event {
+                                DownloadEvent::Resolved { source, title, score, .. } => {
+                                    Some(format!("Found on {}: {} ({:.0}% match)", source, title, score * 100.0))
+                                }
+                                DownloadEvent::Downloading(transfer) => Some(format!("Downloading audio... {}", transfer.summary())),
+                                DownloadEvent::Converting { percent } => Some(format!("Converting audio... {:.0}%", percent)),
+                                DownloadEvent::Completed { size, .. } => {
+                                    Some(format!("Download completed ({:.1} MiB)", size as f64 / (1024.0 * 1024.0)))
+                                }
+                                DownloadEvent::Failed { stage, error_kind } => {
+                                    Some(format!("Failed at {} ({})", stage.to_string().to_lowercase(), error_kind))
+                                }
+                                _ => None,
+                            };
+                            if let Some(message) = message {
+                                track.stage_message = Some(message);
+                            }
+                        }
+                    }
                     AppCommand::SetError(track_id, error) => {
                         if let Some(track) = self.tracks.iter_mut().find(|t| t.metadata.id == track_id) {
                             track.error_message = Some(error);
@@ -1350,7 +1546,19 @@ impl Application for SpotifyDownloaderApp {
                                     track.status = TrackStatus::Completed;
                                     track.progress = 1.0;
                                     track.current_stage = Some(DownloadStage::Completed);
-                                    track.stage_message = Some("Download completed successfully!".to_string());
+                                    let mut message = match &result.source_format {
+                                        Some(format) => format!("Downloaded from format {}", format),
+                                        None => "Download completed successfully!".to_string(),
+                                    };
+                                    if let Some(trim) = result.trim.as_ref().filter(|trim| !trim.is_empty()) {
+                                        message.push_str(&format!(", {}", trim.summary()));
+                                    }
+                                    track.stage_message = Some(message);
+                                } else if result.cancelled {
+                                    track.status = TrackStatus::Pending;
+                                    track.progress = 0.0;
+                                    track.current_stage = None;
+                                    track.stage_message = Some("Download stopped".to_string());
                                 } else {
                                     track.status = TrackStatus::Failed;
                                     track.error_message = result.error;
@@ -1385,25 +1593,8 @@ impl Application for SpotifyDownloaderApp {
                         }
                     }
                     "Space" => {
-                        if !self.tracks.is_empty() {
-                            // Toggle play/pause for first selected track or first track
-                            let track_index = if !self.selected_tracks.is_empty() {
-                                self.selected_tracks[0]
-                            } else {
-                                0
-                            };
-                            if track_index < self.tracks.len() {
-                                match self.tracks[track_index].status {
-                                    TrackStatus::Paused => {
-                                        self.tracks[track_index].status = TrackStatus::Downloading;
-                                    }
-                                    TrackStatus::Downloading => {
-                                        self.tracks[track_index].status = TrackStatus::Paused;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
+                        // Downloads are paused as a batch, same as the Pause/Resume button
+                        return self.update(Message::PauseDownload);
                     }
                     _ => {}
                 }
@@ -1455,6 +1646,10 @@ impl Application for SpotifyDownloaderApp {
         };
 
         let sidebar = self.sidebar();
+        let content = match self.unfinished_prompt() {
+            Some(prompt) => column![prompt, content].spacing(16).into(),
+            None => content,
+        };
 
         row![
             sidebar,
@@ -1471,6 +1666,37 @@ impl Application for SpotifyDownloaderApp {
 
 impl SpotifyDownloaderApp {
 
+    /// Offer to resume the downloads the last session left unfinished
+    fn unfinished_prompt(&self) -> Option<Element<'_, Message>> {
+        if self.unfinished_jobs.is_empty() {
+            return None;
+        }
+
+        let resume_button = button("Resume")
+            .on_press(Message::ResumeUnfinished)
+            .padding([8, 14])
+            .style(iced::theme::Button::Primary);
+
+        let dismiss_button = button("Not now")
+            .on_press(Message::DismissUnfinished)
+            .padding([8, 14])
+            .style(iced::theme::Button::Secondary);
+
+        Some(
+            row![
+                text(format!("{} download(s) were left unfinished last time", self.unfinished_jobs.len()))
+                    .size(16)
+                    .style(Color::from_rgb(0.9, 0.6, 0.2)),
+                horizontal_space(),
+                resume_button,
+                horizontal_space().width(8),
+                dismiss_button,
+            ]
+            .align_items(Alignment::Center)
+            .into()
+        )
+    }
+
     fn sidebar(&self) -> Element<'_, Message> {
         let import_button = button("Import")
             .on_press(Message::SwitchView(View::Import))
@@ -1741,7 +1967,9 @@ impl SpotifyDownloaderApp {
                         DownloadStage::FetchingMetadata => "Fetching Metadata",
                         DownloadStage::SearchingSource => "Searching Source",
                         DownloadStage::DownloadingAudio => "Downloading Audio",
+                        DownloadStage::TrimmingAudio => "Trimming Audio",
                         DownloadStage::ConvertingAudio => "Converting Audio",
+                        DownloadStage::AnalyzingLoudness => "Analyzing Loudness",
                         DownloadStage::DownloadingCover => "Downloading Cover",
                         DownloadStage::DownloadingLyrics => "Downloading Lyrics",
                         DownloadStage::EmbeddingMetadata => "Embedding Metadata",
@@ -1934,8 +2162,23 @@ impl SpotifyDownloaderApp {
         .into()
     }
 
+    /// Control shared by running downloads, replaced once the previous one was cancelled
+    fn active_download_control(download_control: &mut Option<DownloadControl>) -> DownloadControl {
+        match download_control {
+            Some(control) if !control.is_cancelled() => control.clone(),
+            _ => download_control.insert(DownloadControl::new()).clone(),
+        }
+    }
+
+    /// Quality chosen for the selected format, falling back to the default bitrate
+    fn selected_quality(&self) -> Quality {
+        self.format_quality.get(&self.selected_format)
+            .copied()
+            .unwrap_or_else(|| Quality::from(self.selected_bitrate))
+    }
+
     fn format_settings_section(&self) -> Element<'_, Message> {
-        let formats = [AudioFormat::Mp3, AudioFormat::M4a, AudioFormat::Flac, AudioFormat::Wav];
+        let formats = [AudioFormat::Mp3, AudioFormat::M4a, AudioFormat::Flac, AudioFormat::Wav, AudioFormat::Opus, AudioFormat::Ogg, AudioFormat::Alac, AudioFormat::Aiff];
         let format_picklist = pick_list(
             formats,
             Some(self.selected_format),
@@ -1943,27 +2186,26 @@ impl SpotifyDownloaderApp {
         )
         .width(Length::Fixed(150.0));
 
-        let is_lossless = matches!(self.selected_format, AudioFormat::Flac | AudioFormat::Wav);
+        let is_lossless = self.selected_format.is_lossless();
         
         let bitrate_section = if is_lossless {
             column![
-                text("Bitrate: Not applicable for lossless formats")
+                text("Quality: Not applicable for lossless formats")
                     .size(14)
                     .style(Color::from_rgb(0.6, 0.6, 0.6)),
             ]
         } else {
-            let bitrates = [Bitrate::Kbps128, Bitrate::Kbps192, Bitrate::Kbps256, Bitrate::Kbps320];
-            let bitrate_picklist = pick_list(
-                bitrates,
-                Some(self.selected_bitrate),
-                Message::UpdateBitrate,
+            let quality_picklist = pick_list(
+                Quality::PRESETS,
+                Some(self.selected_quality()),
+                Message::UpdateQuality,
             )
             .width(Length::Fixed(150.0));
             
             column![
-                text("Bitrate:").size(14),
+                text("Quality:").size(14),
                 horizontal_space().width(10),
-                bitrate_picklist,
+                quality_picklist,
             ]
             .align_items(Alignment::Center)
         };
@@ -2035,6 +2277,8 @@ impl SpotifyDownloaderApp {
             self.create_metadata_toggle("Album Artist", self.embed_album_artist, Message::ToggleAlbumArtist),
             self.create_metadata_toggle("Composer", self.embed_composer, Message::ToggleComposer),
             self.create_metadata_toggle("Comment", self.embed_comment, Message::ToggleComment),
+            self.create_metadata_toggle("Last.fm Enrichment", self.enrich_from_lastfm, Message::ToggleLastfmEnrichment),
+            self.create_metadata_toggle("ReplayGain", self.embed_replaygain, Message::ToggleReplayGain),
         ]
         .spacing(8);
 
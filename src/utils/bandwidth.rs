@@ -0,0 +1,231 @@
+use crate::config::{BandwidthConfig, RateLimit};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Smallest share of the cap handed to one yt-dlp job, and left over for our own transfers
+const MIN_YT_DLP_RATE: u64 = 16 * 1024;
+
+static GLOBAL: OnceLock<BandwidthLimiter> = OnceLock::new();
+
+/// Token bucket allowing up to one second of burst at its rate
+#[derive(Debug)]
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// Bytes that may be sent right away; negative while transfers are paying off a burst
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Create an empty bucket
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                tokens: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take bytes out of the bucket at the given rate, waiting until they are paid for
+    pub async fn take(&self, bytes: u64, limit: RateLimit) {
+        let Some(rate) = limit.bytes_per_sec() else { return };
+        let rate = rate as f64;
+        let wait = {
+            let Ok(mut state) = self.state.lock() else { return };
+            let now = Instant::now();
+            let refill = now.duration_since(state.refilled_at).as_secs_f64() * rate;
+            state.tokens = (state.tokens + refill).min(rate);
+            state.refilled_at = now;
+            state.tokens -= bytes as f64;
+            if state.tokens < 0.0 { -state.tokens / rate } else { 0.0 }
+        };
+        if wait > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+impl Default for TokenBucket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Process wide bandwidth cap: yt-dlp jobs reserve a slice of the cap each, our own transfers share a bucket with what is left
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    config: Mutex<BandwidthConfig>,
+    bucket: TokenBucket,
+    /// Downloads that may run at once, the cap is split into this many yt-dlp slices
+    pool_size: AtomicUsize,
+    /// Bytes per second reserved by the running yt-dlp jobs
+    reserved: Arc<AtomicU64>,
+}
+
+impl BandwidthLimiter {
+    /// The limiter shared by every download of this process, unlimited until configured
+    pub fn global() -> &'static BandwidthLimiter {
+        GLOBAL.get_or_init(|| BandwidthLimiter {
+            config: Mutex::new(BandwidthConfig::default()),
+            bucket: TokenBucket::new(),
+            pool_size: AtomicUsize::new(1),
+            reserved: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Set how many downloads run at once
+    pub fn set_pool_size(&self, pool_size: usize) {
+        self.pool_size.store(pool_size.max(1), Ordering::SeqCst);
+    }
+
+    /// Replace the caps and schedule
+    pub fn configure(&self, config: BandwidthConfig) {
+        if let Ok(mut current) = self.config.lock() {
+            *current = config;
+        }
+    }
+
+    /// Use a fixed cap for the rest of this run, ignoring the schedule
+    pub fn override_limit(&self, limit: RateLimit) {
+        if let Ok(mut config) = self.config.lock() {
+            config.limit = limit;
+            config.schedule.clear();
+        }
+    }
+
+    /// Total cap in effect right now
+    pub fn current_limit(&self) -> RateLimit {
+        self.config.lock()
+            .map(|config| config.limit_at(local_minute_of_day()))
+            .unwrap_or_default()
+    }
+
+    /// Cap for a single download
+    pub fn per_download_limit(&self) -> RateLimit {
+        self.config.lock()
+            .map(|config| config.per_download_limit)
+            .unwrap_or_default()
+    }
+
+    /// Reserve the rate for a yt-dlp job starting now: the cap split over the download pool, capped per download.
+    /// yt-dlp can't change its rate while running, so the share is fixed when the job starts and held until the guard is dropped.
+    pub fn reserve_yt_dlp(&self) -> YtDlpShare {
+        let share = self.current_limit().bytes_per_sec().map(|total| {
+            let slice = total / self.pool_size.load(Ordering::SeqCst).max(1) as u64;
+            let unreserved = total.saturating_sub(self.reserved.load(Ordering::SeqCst));
+            slice.min(unreserved).max(MIN_YT_DLP_RATE)
+        });
+        let per_download = self.per_download_limit().bytes_per_sec();
+        let rate = match (share, per_download) {
+            (Some(share), Some(per_download)) => Some(share.min(per_download)),
+            (Some(limit), None) | (None, Some(limit)) => Some(limit),
+            (None, None) => None,
+        };
+        // Only a share of the total cap needs to be kept away from our own transfers
+        let reserved = if share.is_some() { rate.unwrap_or(0) } else { 0 };
+        self.reserved.fetch_add(reserved, Ordering::SeqCst);
+        YtDlpShare {
+            rate: rate.map(RateLimit::BytesPerSec).unwrap_or(RateLimit::Unlimited),
+            reserved,
+            pool: Arc::clone(&self.reserved),
+        }
+    }
+
+    /// Wait until a chunk of one of our own transfers fits under the part of the cap yt-dlp jobs haven't reserved
+    pub async fn acquire(&self, bytes: u64) {
+        let limit = match self.current_limit().bytes_per_sec() {
+            Some(total) => {
+                let unreserved = total.saturating_sub(self.reserved.load(Ordering::SeqCst));
+                RateLimit::BytesPerSec(unreserved.max(MIN_YT_DLP_RATE))
+            }
+            None => RateLimit::Unlimited,
+        };
+        self.bucket.take(bytes, limit).await;
+    }
+}
+
+/// A running yt-dlp job's slice of the bandwidth cap
+pub struct YtDlpShare {
+    rate: RateLimit,
+    reserved: u64,
+    pool: Arc<AtomicU64>,
+}
+
+impl YtDlpShare {
+    /// Rate to pass to yt-dlp's --limit-rate
+    pub fn rate(&self) -> RateLimit {
+        self.rate
+    }
+}
+
+impl Drop for YtDlpShare {
+    fn drop(&mut self) {
+        self.pool.fetch_sub(self.reserved, Ordering::SeqCst);
+    }
+}
+
+/// Throttle for one transfer: the shared cap plus the per-download cap
+pub struct TransferThrottle {
+    limiter: &'static BandwidthLimiter,
+    bucket: TokenBucket,
+    per_download_limit: RateLimit,
+}
+
+impl TransferThrottle {
+    /// Throttle a new transfer with the global limiter
+    pub fn new() -> Self {
+        let limiter = BandwidthLimiter::global();
+        Self {
+            limiter,
+            bucket: TokenBucket::new(),
+            per_download_limit: limiter.per_download_limit(),
+        }
+    }
+
+    /// Wait until a received chunk fits under both caps
+    pub async fn throttle(&self, bytes: u64) {
+        self.bucket.take(bytes, self.per_download_limit).await;
+        self.limiter.acquire(bytes).await;
+    }
+}
+
+impl Default for TransferThrottle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Minutes since local midnight
+#[cfg(unix)]
+fn local_minute_of_day() -> u16 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as libc::time_t)
+        .unwrap_or(0);
+    // SAFETY: localtime_r only writes to the tm struct we pass in
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return utc_minute_of_day();
+    }
+    (tm.tm_hour * 60 + tm.tm_min) as u16
+}
+
+/// Minutes since midnight; schedules follow UTC where the local time zone isn't available
+#[cfg(not(unix))]
+fn local_minute_of_day() -> u16 {
+    utc_minute_of_day()
+}
+
+fn utc_minute_of_day() -> u16 {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    ((seconds % 86_400) / 60) as u16
+}
@@ -1,5 +1,6 @@
 use crate::errors::Result;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
 
 /// File system utilities
 #[allow(dead_code)]
@@ -191,3 +192,40 @@ impl FileUtils {
         Ok(())
     }
 }
+
+/// Lock file held shared by every open handle of an append-only log.
+/// Rewriting a log replaces its file, which would leave other handles appending to the unlinked
+/// old one, so a rewrite only runs while this handle can hold the lock alone.
+pub struct LogLock {
+    file: File,
+}
+
+impl LogLock {
+    /// Open the lock next to a log and hold it shared, waiting while another handle rewrites the log
+    pub fn acquire(log_path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(log_path.with_extension("lock"))?;
+        file.lock_shared()?;
+        Ok(Self { file })
+    }
+
+    /// Run `rewrite` if no other handle has the log open, returning `None` when it didn't run.
+    /// Another handle may have rewritten the log meanwhile, so writers must be reopened afterwards.
+    pub fn exclusively<T>(&self, rewrite: impl FnOnce() -> Result<T>) -> Result<Option<T>> {
+        self.file.unlock()?;
+        let outcome = match self.file.try_lock() {
+            Ok(()) => {
+                let result = rewrite();
+                self.file.unlock()?;
+                result.map(Some)
+            }
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        };
+        self.file.lock_shared()?;
+        outcome
+    }
+}
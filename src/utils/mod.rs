@@ -1,6 +1,8 @@
 pub mod fs;
 pub mod net;
 pub mod logger;
+pub mod retry;
+pub mod bandwidth;
 
 
 use crate::errors::Result;
@@ -172,34 +174,43 @@ impl Utils {
         Ok(hash)
     }
 
-    /// Retry operation with exponential backoff
-    pub async fn retry_with_backoff<F, T, E>(
-        operation: F,
-        max_retries: u32,
-        initial_delay_ms: u64,
+    /// Retry an operation while it fails with transient errors, waiting with backoff and jitter in between.
+    /// Permanent errors and cancellation are returned right away, and cancelling also interrupts the wait.
+    pub async fn retry_with_backoff<F, Fut, T>(
+        operation_name: &str,
+        policy: &retry::RetryPolicy,
+        cancel_token: &tokio_util::sync::CancellationToken,
+        mut operation: F,
     ) -> Result<T>
     where
-        F: Fn() -> std::pin::Pin<Box<dyn std::future::Future<Output = std::result::Result<T, E>> + Send>>,
-        E: std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
     {
-        let mut delay = initial_delay_ms;
-        
-        for attempt in 0..=max_retries {
+        let mut attempt = 0;
+        loop {
             match operation().await {
                 Ok(result) => return Ok(result),
+                Err(e) if policy.should_retry(attempt, &e) => {
+                    let delay = policy.delay(attempt, &e);
+                    attempt += 1;
+                    println!(
+                        "🔁 {} failed ({} error), retry {}/{} in {:.1}s: {}",
+                        operation_name, e.kind(), attempt, policy.max_retries, delay.as_secs_f64(), e
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = cancel_token.cancelled() => {
+                            return Err(crate::errors::SpotifyDownloaderError::Cancelled(format!("{} cancelled", operation_name)));
+                        }
+                    }
+                }
                 Err(e) => {
-                    if attempt == max_retries {
-                        return Err(crate::errors::SpotifyDownloaderError::Unknown(
-                            format!("Operation failed after {} retries: {}", max_retries + 1, e)
-                        ));
+                    if attempt > 0 && e.is_transient() {
+                        println!("❌ {} still failing after {} retries", operation_name, attempt);
                     }
-                    
-                    tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
-                    delay *= 2; // Exponential backoff
+                    return Err(e);
                 }
             }
         }
-        
-        unreachable!()
     }
 }
@@ -65,7 +65,7 @@ impl NetworkUtils {
     where
         F: Fn(u64, u64), // (downloaded, total)
     {
-        let response = self.client.get(url).send().await?;
+        let mut response = self.client.get(url).send().await?;
 
         if !response.status().is_success() {
             return Err(crate::errors::SpotifyDownloaderError::Network(
@@ -85,16 +85,19 @@ impl NetworkUtils {
             .await
             .map_err(|e| crate::errors::SpotifyDownloaderError::Io(e))?;
 
-        // Download the response body
-        let bytes = response.bytes().await
-            .map_err(|e| crate::errors::SpotifyDownloaderError::Network(e))?;
-        
-        tokio::io::AsyncWriteExt::write_all(&mut file, &bytes)
-            .await
-            .map_err(|e| crate::errors::SpotifyDownloaderError::Io(e))?;
-            
-        downloaded += bytes.len() as u64;
-        progress_callback(downloaded, total_size);
+        // Stream the response body, holding back each chunk until it fits under the bandwidth caps
+        let throttle = crate::utils::bandwidth::TransferThrottle::new();
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| crate::errors::SpotifyDownloaderError::Network(e))?
+        {
+            throttle.throttle(chunk.len() as u64).await;
+            tokio::io::AsyncWriteExt::write_all(&mut file, &chunk)
+                .await
+                .map_err(|e| crate::errors::SpotifyDownloaderError::Io(e))?;
+
+            downloaded += chunk.len() as u64;
+            progress_callback(downloaded, total_size);
+        }
 
         Ok(())
     }
@@ -0,0 +1,81 @@
+use crate::config::{RetryConfig, StageRetry};
+use crate::errors::SpotifyDownloaderError;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Longest wait honoured from a server's rate limit response
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Pipeline stage a retry policy applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryStage {
+    Search,
+    Metadata,
+    Download,
+}
+
+impl std::fmt::Display for RetryStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetryStage::Search => write!(f, "search"),
+            RetryStage::Metadata => write!(f, "metadata lookup"),
+            RetryStage::Download => write!(f, "download"),
+        }
+    }
+}
+
+/// How often and how long to wait before retrying a transient failure
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized, 0.0 to 1.0
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Policy for one stage from the retry settings
+    pub fn for_stage(config: &RetryConfig, stage: RetryStage) -> Self {
+        let limits: StageRetry = match stage {
+            RetryStage::Search => config.search,
+            RetryStage::Metadata => config.metadata,
+            RetryStage::Download => config.download,
+        };
+        Self {
+            max_retries: limits.max_retries,
+            initial_delay: Duration::from_millis(limits.initial_delay_ms),
+            max_delay: Duration::from_millis(limits.max_delay_ms.max(limits.initial_delay_ms)),
+            jitter: config.jitter.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Whether another attempt should follow the given failed attempt (0 based)
+    pub fn should_retry(&self, attempt: u32, error: &SpotifyDownloaderError) -> bool {
+        attempt < self.max_retries && error.is_transient()
+    }
+
+    /// Wait before the attempt after `attempt`: doubling from the initial delay, capped and jittered.
+    /// A rate limit that names its own wait is honoured instead.
+    pub fn delay(&self, attempt: u32, error: &SpotifyDownloaderError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(MAX_RETRY_AFTER);
+        }
+        let backoff = self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        // Spread the delay over [1 - jitter, 1 + jitter] of the backoff
+        let factor = 1.0 + self.jitter * (Self::random_unit() * 2.0 - 1.0);
+        backoff.mul_f64(factor.max(0.0))
+    }
+
+    /// Random number in [0, 1) without pulling in a random number crate
+    fn random_unit() -> f64 {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default());
+        (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}